@@ -1,21 +1,28 @@
-#![feature(test)]
-
-#![allow(non_snake_case)]
-
-// extern crate gram_schmidt;
-// extern crate ndarray;
 extern crate openblas_src;
 
-extern crate test; // Built-in crate for benchmarking.
-
+use criterion::{
+    BenchmarkId,
+    Criterion,
+    black_box,
+    criterion_group,
+    criterion_main,
+};
 use gramschmidt::{
     GramSchmidt,
+    BlockClassical,
+    CholeskyQr,
     Classical,
     Modified,
     Reorthogonalized,
 };
 
 use ndarray::prelude::*;
+use ndarray_rand::RandomExt;
+use rand::{
+    SeedableRng,
+    distributions::Normal,
+    rngs::StdRng,
+};
 
 // The strategy employed in ModifiedGramSchmidt::compute_inplace is to orthogonalize the current
 // row with respect to the already-orthogonalized ones and then normalize it. We can call this
@@ -51,62 +58,89 @@ use ndarray::prelude::*;
 //     }
 // }
 
-macro_rules! create_bench {
-    (c $n:expr, $name:ident, $method:ty) => {
-        #[bench]
-        fn $name(bench: &mut test::Bencher) {
-            let n = $n;
+/// The seed of the random matrices, fixed so that the timings of different runs are comparable.
+const SEED: u64 = 42;
 
-            let matrix = Array2::eye(n);
-            let mut method = <$method>::from_matrix(&matrix).unwrap();
-            let method = test::black_box(&mut method);
+/// Copies `matrix` into row major layout or, if `f` is set, into column major layout.
+fn with_layout(matrix: Array2<f64>, f: bool) -> Array2<f64> {
+    let mut copy = Array2::zeros(matrix.dim().set_f(f));
+    copy.assign(&matrix);
+    copy
+}
 
-            bench.iter(|| {
-                method.compute(&matrix).unwrap();
-            });
-        }
-    };
+/// A square matrix of size `n` with independent standard normal entries.
+fn random_matrix(n: usize, f: bool) -> Array2<f64> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    with_layout(Array2::random_using((n, n), Normal::new(0.0, 1.0), &mut rng), f)
+}
+
+/// A tall and skinny `m×n` matrix with independent standard normal entries, in row major layout.
+fn random_tall_matrix(m: usize, n: usize) -> Array2<f64> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    Array2::random_using((m, n), Normal::new(0.0, 1.0), &mut rng)
+}
 
-    (f $n:expr, $name:ident, $method:ty) => {
-        #[bench]
-        fn $name(bench: &mut test::Bencher) {
-            let n = $n;
+/// The identity of size `n`, which is already orthonormal: no projection removes anything from
+/// it, making it the best case of every procedure.
+fn identity(n: usize, f: bool) -> Array2<f64> {
+    with_layout(Array2::eye(n), f)
+}
 
-            let matrix = Array2::eye(n).reversed_axes();
-            let mut method = <$method>::from_matrix(&matrix).unwrap();
-            let method = test::black_box(&mut method);
+/// Benchmarks the factorization of `matrix` by the procedure `G` as part of `group`.
+fn bench_procedure<G>(group: &mut criterion::BenchmarkGroup<'_, criterion::measurement::WallTime>, name: &str, matrix: &Array2<f64>)
+    where G: GramSchmidt<Scalar = f64>
+{
+    let mut method = G::from_matrix(matrix).unwrap();
+    group.bench_with_input(BenchmarkId::new(name, matrix.rows()), matrix, |bench, matrix| {
+        bench.iter(|| method.compute(black_box(matrix)).unwrap());
+    });
+}
 
-            bench.iter(|| {
-                method.compute(&matrix).unwrap();
-            });
+/// Compares the classical, modified, and reorthogonalized procedures for both memory layouts on
+/// the matrices returned by `matrix`, as the group `name`.
+fn compare_procedures(c: &mut Criterion, name: &str, matrix: fn(usize, bool) -> Array2<f64>) {
+    for &(layout, f) in [("c", false), ("f", true)].iter() {
+        let mut group = c.benchmark_group(format!("{}/{}", name, layout));
+        for &n in [256, 512].iter() {
+            let matrix = matrix(n, f);
+            bench_procedure::<Classical>(&mut group, "cgs", &matrix);
+            bench_procedure::<Modified>(&mut group, "mgs", &matrix);
+            bench_procedure::<Reorthogonalized>(&mut group, "cgs2", &matrix);
         }
-    };
+        group.finish();
+    }
+}
+
+fn matrix_sizes(c: &mut Criterion) {
+    compare_procedures(c, "matrix_sizes", random_matrix);
+}
+
+fn identity_matrix_sizes(c: &mut Criterion) {
+    compare_procedures(c, "identity", identity);
+}
+
+/// Compares the classical procedure with its blocked variant.
+fn block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block");
+    group.sample_size(10);
+    for &n in [768, 1536].iter() {
+        let matrix = random_matrix(n, false);
+        bench_procedure::<Classical>(&mut group, "cgs", &matrix);
+        bench_procedure::<BlockClassical>(&mut group, "bcgs", &matrix);
+    }
+    group.finish();
 }
 
-macro_rules! bench_sizes {
-    (c $n:expr, $name_cgs:ident, $name_mgs: ident, $name_cgs2: ident) => {
-        create_bench!(c $n, $name_cgs, Classical);
-        create_bench!(c $n, $name_cgs2, Reorthogonalized);
-        create_bench!(c $n, $name_mgs, Modified);
-    };
-
-    (f $n:expr, $name_cgs:ident, $name_mgs: ident, $name_cgs2: ident) => {
-        create_bench!(f $n, $name_cgs, Classical);
-        create_bench!(f $n, $name_cgs2, Reorthogonalized);
-        create_bench!(f $n, $name_mgs, Modified);
-    };
+/// Compares the classical procedure with CholeskyQR on tall and skinny matrices.
+fn tall_skinny(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tall_skinny");
+    for &m in [1000, 10000].iter() {
+        let matrix = random_tall_matrix(m, 20);
+        bench_procedure::<Classical>(&mut group, "cgs", &matrix);
+        bench_procedure::<CholeskyQr>(&mut group, "cholesky_qr", &matrix);
+    }
+    group.finish();
 }
 
-bench_sizes!(c  256, c_cgs__256, c_mgs__256, c_cgs2__256);
-bench_sizes!(c  512, c_cgs__512, c_mgs__512, c_cgs2__512);
-// bench_sizes!(c  768, c_cgs__768, c_mgs__768, c_cgs2__768);
-// bench_sizes!(c 1024, c_cgs_1024, c_mgs_1024, c_cgs2_1024);
-// bench_sizes!(c 1536, c_cgs_1536, c_mgs_1536, c_cgs2_1536);
-// bench_sizes!(c 2048, c_cgs_2048, c_mgs_2048, c_cgs2_2048);
-
-bench_sizes!(f  256, f_cgs__256, f_mgs__256, f_cgs2__256);
-bench_sizes!(f  512, f_cgs__512, f_mgs__512, f_cgs2__512);
-// bench_sizes!(f  768, f_cgs__768, f_mgs__768, f_cgs2__768);
-// bench_sizes!(f 1024, f_cgs_1024, f_mgs_1024, f_cgs2_1024);
-// bench_sizes!(f 1536, f_cgs_1536, f_mgs_1536, f_cgs2_1536);
-// bench_sizes!(f 2048, f_cgs_2048, f_mgs_2048, f_cgs2_2048);
+criterion_group!(benches, matrix_sizes, identity_matrix_sizes, block, tall_skinny);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Compares the serial and the parallel procedures, run with `cargo bench --features rayon`.
+
+extern crate openblas_src;
+
+use criterion::{
+    BenchmarkId,
+    Criterion,
+    black_box,
+    criterion_group,
+    criterion_main,
+};
+use gramschmidt::{
+    GramSchmidt,
+    Classical,
+    Modified,
+    ParallelGramSchmidt,
+    Reorthogonalized,
+};
+
+use ndarray::prelude::*;
+
+/// Benchmarks the serial and the parallel factorization of `matrix` by the procedure `G` as part
+/// of `group`.
+fn bench_procedure<G>(group: &mut criterion::BenchmarkGroup<'_, criterion::measurement::WallTime>, name: &str, matrix: &Array2<f64>)
+    where G: GramSchmidt<Scalar = f64> + ParallelGramSchmidt
+{
+    let mut method = G::from_matrix(matrix).unwrap();
+    group.bench_with_input(BenchmarkId::new(name, matrix.rows()), matrix, |bench, matrix| {
+        bench.iter(|| method.compute(black_box(matrix)).unwrap());
+    });
+    let parallel_name = format!("par_{}", name);
+    group.bench_with_input(BenchmarkId::new(parallel_name, matrix.rows()), matrix, |bench, matrix| {
+        bench.iter(|| method.par_compute(black_box(matrix)).unwrap());
+    });
+}
+
+fn parallel(c: &mut Criterion) {
+    for &(layout, f) in [("c", false), ("f", true)].iter() {
+        let mut group = c.benchmark_group(format!("parallel/{}", layout));
+        group.sample_size(10);
+        let matrix = Array2::eye(1024);
+        let matrix = if f { matrix.reversed_axes() } else { matrix };
+        bench_procedure::<Classical>(&mut group, "cgs", &matrix);
+        bench_procedure::<Modified>(&mut group, "mgs", &matrix);
+        bench_procedure::<Reorthogonalized>(&mut group, "cgs2", &matrix);
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parallel);
+criterion_main!(benches);
@@ -1,9 +1,9 @@
 extern crate openblas_src;
 
 use gramschmidt::{
-    cgs,
-    cgs2,
-    mgs
+    cgs_qr,
+    cgs2_qr,
+    mgs_qr
 };
 use lapacke::dlange;
 use ndarray::{
@@ -53,10 +53,10 @@ fn main()
 
     let lauchli_matrix = create_lauchli(3, epsilon);
 
-    let (q_cgs, r_cgs) = cgs(&lauchli_matrix).expect("Failed to perform CGS");
-    let (q_cgs_repeated, r_cgs_repeated) = cgs(&q_cgs).expect("Failed to perform CGS a second time");
-    let (q_cgs2, r_cgs2) = cgs2(&lauchli_matrix).expect("Failed to perform CGS2");
-    let (q_mgs, r_mgs) = mgs(&lauchli_matrix).expect("Failed to perform MGS");
+    let (q_cgs, r_cgs) = cgs_qr(&lauchli_matrix).expect("Failed to perform CGS").into_tuple();
+    let (q_cgs_repeated, r_cgs_repeated) = cgs_qr(&q_cgs).expect("Failed to perform CGS a second time").into_tuple();
+    let (q_cgs2, r_cgs2) = cgs2_qr(&lauchli_matrix).expect("Failed to perform CGS2").into_tuple();
+    let (q_mgs, r_mgs) = mgs_qr(&lauchli_matrix).expect("Failed to perform MGS").into_tuple();
 
     let unity = Array2::<f64>::eye(3);
 
@@ -0,0 +1,36 @@
+//! Factorizes a small matrix without a blas library, so that it also runs in the browser.
+//!
+//! Build it for wasm with
+//!
+//! ```text
+//! cargo build --example wasm --target wasm32-unknown-unknown --no-default-features
+//! ```
+//!
+//! or run it natively with `cargo run --example wasm --no-default-features`. The standard output
+//! is discarded on `wasm32-unknown-unknown`; a web application would pass the formatted matrices
+//! to `console.log` instead, e.g. via `web_sys::console::log_1`.
+
+// With the default cblas feature, a blas library has to be linked like in the other examples.
+#[cfg(feature = "cblas")]
+extern crate openblas_src;
+
+use gramschmidt::{
+    GramSchmidt,
+    Reorthogonalized,
+    Result,
+};
+use ndarray::arr2;
+
+fn main() -> Result<()> {
+    let a = arr2(&[[2.0, 0.5, 0.0],
+                   [0.0, 0.3, 0.0],
+                   [0.0, 1.0, 0.7],
+                   [1.0, 0.0, 3.0]]);
+
+    let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+    cgs2.compute(&a)?;
+
+    println!("Q = {:.4}", cgs2.q());
+    println!("R = {:.4}", cgs2.r());
+    Ok(())
+}
@@ -0,0 +1,712 @@
+use ndarray::{
+    Data,
+    Dim,
+    Ix,
+    ShapeBuilder,
+    s,
+};
+use ndarray::prelude::*;
+use std::slice;
+use std::time::Instant;
+
+use crate::{
+    ColumnProvenance,
+    ComputeStatus,
+    Error,
+    GramSchmidt,
+    GramSchmidtScalar,
+    Result,
+    StabilityClass,
+    blas,
+    givens,
+    utils::{
+        as_slice_with_layout,
+        check_finite,
+        check_pinned,
+        check_rank,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        deadline_passed,
+        default_tolerance,
+        force_positive_diagonal,
+        get_layout,
+        is_broadcast,
+        last_flops,
+        normalization,
+        observe_columns,
+        parts_layouts,
+        pin_columns,
+        provenance,
+        rescale_columns,
+        reset_factors,
+        transpose_layout,
+    },
+};
+
+/// The number of columns per panel used by [`GramSchmidt::from_shape`] and the other trait
+/// constructors.
+const DEFAULT_BLOCK: usize = 32;
+
+/// A block classical Gram Schmidt factorization, which orthonormalizes panels of columns at a
+/// time.
+///
+/// Every panel of `block` consecutive columns is first orthogonalized against all previous
+/// panels with two blas `?gemm` calls, and then orthonormalized by classical Gram Schmidt within
+/// the panel. Moving most of the work into matrix-matrix products makes it considerably faster
+/// than [`Classical`] for large matrices, which is limited by memory bandwidth. Both agree in
+/// exact arithmetic and lose orthogonality at the same rate.
+///
+/// Use this struct via the [`GramSchmidt` trait], and [`from_shape_with_block`] to configure the
+/// block size. The default block size is 32.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{BlockClassical, GramSchmidt};
+/// use ndarray::Array2;
+/// use ndarray_rand::RandomExt;
+/// use rand::distributions::Normal;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = Array2::random((100, 50), Normal::new(0.0, 1.0));
+/// let mut bcgs = BlockClassical::from_shape_with_block(a.dim(), 16)?;
+/// bcgs.compute(&a)?;
+/// assert!(a.all_close(&bcgs.q().dot(bcgs.r()), 1e-12));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Classical`]: struct.Classical.html
+/// [`GramSchmidt` trait]: GramSchmidt
+/// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+/// [`from_shape_with_block`]: #method.from_shape_with_block
+#[derive(Clone, Debug)]
+pub struct BlockClassical<A = f64> {
+    q: Array2<A>,
+    r: Array2<A>,
+    memory_layout: blas::Layout,
+    r_layout: blas::Layout,
+    block: usize,
+    pinned: usize,
+    sign_flipped: Vec<bool>,
+    computed: bool,
+    tolerance: Option<f64>,
+    skip_finite_check: bool,
+    accept_any_layout: bool,
+}
+
+impl<A: GramSchmidtScalar> GramSchmidt for BlockClassical<A> {
+    type Scalar = A;
+
+    const STABILITY: StabilityClass = StabilityClass::Low;
+    const COST_FACTOR: f64 = 1.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let shape = shape.into_shape();
+        let q = Array2::zeros(shape);
+        let memory_layout = match get_layout(&q) {
+            Some(layout) => layout,
+            None => Err(Error::NonContiguous)?,
+        };
+        check_shape(q.dim())?;
+
+        let (_, n_cols) = q.dim();
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
+        let r = Array2::zeros(
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
+        );
+
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            r_layout,
+            block: DEFAULT_BLOCK,
+            pinned: 0,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+            accept_any_layout: false,
+        })
+    }
+
+    fn from_parts(q: Array2<A>, r: Array2<A>) -> Result<Self> {
+        let (memory_layout, r_layout) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            r_layout,
+            block: DEFAULT_BLOCK,
+            pinned: 0,
+            sign_flipped,
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+            accept_any_layout: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<A>(self.q.rows(), self.q.cols()))
+    }
+
+    fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, 0, None, |_, _| true).map(|_| ())
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, next_col, Some(deadline), |_, _| true)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, start, None, |_, _| true).map(|_| ())
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: FnMut(usize, A::Real) -> bool
+    {
+        self.compute_from(a, 0, None, observe)
+    }
+
+    fn q(&self) -> &Array2<A> {
+        &self.q
+    }
+
+    fn r(&self) -> &Array2<A> {
+        &self.r
+    }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(self.pinned, &self.sign_flipped, |_| false)
+    }
+
+    fn last_flops(&self) -> u64 {
+        last_flops(self.computed, self.q.rows(), &self.provenance(), Self::COST_FACTOR)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = A>,
+              T: Data<Elem = A>
+    {
+        givens::low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        givens::remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        if index < self.pinned {
+            self.pinned -= 1;
+        }
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+    }
+}
+
+impl<A: GramSchmidtScalar> BlockClassical<A> {
+    /// Reserves the memory for a block classical Gram Schmidt factorization of matrices with the
+    /// given `shape`, which orthonormalizes panels of `block` columns at a time. See
+    /// [`GramSchmidt::from_shape`].
+    ///
+    /// Larger blocks move more of the work into matrix-matrix products, while the columns within
+    /// a block are orthonormalized with matrix-vector products. A block size of 1 is equivalent to
+    /// [`Classical`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is zero.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Classical`]: struct.Classical.html
+    pub fn from_shape_with_block<T>(shape: T, block: usize) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        assert!(block > 0, "the block size has to be positive");
+        let mut bcgs = Self::from_shape(shape)?;
+        bcgs.block = block;
+        Ok(bcgs)
+    }
+
+    /// Returns the number of columns per panel. See [`from_shape_with_block`] for details.
+    ///
+    /// [`from_shape_with_block`]: #method.from_shape_with_block
+    pub fn block_size(&self) -> usize {
+        self.block
+    }
+
+    /// Sets the number of leading columns which are pinned. See [`Classical::set_pinned`] for
+    /// details.
+    ///
+    /// [`Classical::set_pinned`]: struct.Classical.html#method.set_pinned
+    pub fn set_pinned(&mut self, pinned: usize) -> Result<()> {
+        check_pinned(self.q.cols(), pinned)?;
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// Returns the number of leading columns which are pinned. See [`set_pinned`] for details.
+    ///
+    /// [`set_pinned`]: #method.set_pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped.
+    ///
+    /// By default, [`compute`] scans the input once before factorizing it, and returns
+    /// [`Error::NonFinite`] for the first NaN or infinite entry, which would otherwise silently
+    /// propagate into Q and R. Skipping the scan saves a pass over the input in hot loops whose
+    /// inputs are known to be finite. Resuming a computation with `continue_from` never scans.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Reserves the memory for a QR decomposition like [`GramSchmidt::from_shape`], but accepts
+    /// matrices in either memory layout in `compute`, instead of returning
+    /// [`Error::IncompatibleLayouts`] for the layout opposite to the one of `shape`.
+    ///
+    /// The columns of the input are copied into Q before they are orthonormalized, so that this
+    /// costs nothing but a strided copy for an input in the other layout.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::IncompatibleLayouts`]: enum.Error.html#variant.IncompatibleLayouts
+    pub fn from_shape_accepting_any_layout<T>(shape: T) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let mut bcgs = Self::from_shape(shape)?;
+        bcgs.accept_any_layout = true;
+        Ok(bcgs)
+    }
+
+    /// Returns whether matrices in either memory layout are accepted. See
+    /// [`from_shape_accepting_any_layout`] for details.
+    ///
+    /// [`from_shape_accepting_any_layout`]: #method.from_shape_accepting_any_layout
+    pub fn accepts_any_layout(&self) -> bool {
+        self.accept_any_layout
+    }
+
+    /// Resizes the factorization for matrices of a new `shape` like [`GramSchmidt::from_shape`],
+    /// but reuses the memory of Q and R where possible, e.g. to factorize a stream of matrices of
+    /// varying sizes without allocating for every one of them.
+    ///
+    /// The memory layout is derived from `shape` anew, and R keeps being stored in the same or the
+    /// opposite layout. All settings are kept, except that the number of pinned columns is reduced
+    /// to the new number of columns if necessary. Returns [`Error::Underdetermined`] for a wide
+    /// shape and [`Error::InvalidShape`] for one without rows or columns, leaving the
+    /// factorization unchanged.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn reset_shape<T>(&mut self, shape: T) -> Result<()>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let transpose_r = self.r_layout != self.memory_layout;
+        let (memory_layout, r_layout) = reset_factors(&mut self.q, &mut self.r, shape, transpose_r)?;
+        self.memory_layout = memory_layout;
+        self.r_layout = r_layout;
+        self.sign_flipped.clear();
+        self.sign_flipped.resize(self.q.cols(), false);
+        self.computed = false;
+        self.pinned = self.pinned.min(self.q.cols());
+        Ok(())
+    }
+
+    /// Orthonormalizes the columns `start..` of `a` panel by panel, assuming that the columns
+    /// `0..start` have already been processed. Returns early if the optional `deadline` has
+    /// passed, after at least one column has been processed, or once `observe` returns `false`
+    /// for a processed column.
+    fn compute_from<S, O>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        start: usize,
+        deadline: Option<Instant>,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        use blas::Layout::*;
+        use Error::*;
+
+        if a.dim() != self.q.dim() {
+            Err(ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        let (n_rows, n_cols) = self.q.dim();
+
+        match (self.memory_layout, as_slice_with_layout(a)) {
+            (a, Some((_, b))) if a != b && !self.accept_any_layout => Err(IncompatibleLayouts)?,
+            (_, Some(_)) => {},
+            (_, None) if is_broadcast(a) => Err(BroadcastInput)?,
+            (_, None) => Err(NonContiguous)?,
+        };
+
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        // The signs of the columns computed anew are determined by the procedure again.
+        clear_sign_flips(&mut self.sign_flipped, if start < self.pinned { 0 } else { start });
+        self.computed = true;
+
+        // Pinned columns are not orthonormalized, but only copied.
+        let start = if start < self.pinned {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+            if let Some(status) = observe_columns(&self.r, 0..self.pinned, &mut observe) {
+                return Ok(status);
+            }
+            self.pinned
+        } else {
+            start
+        };
+
+        // leading_dim: the number of elements in the leading dimension
+        // next_elem: how many elements to jump to get to the next element in a column
+        // next_col: how many elements in the array to jump to get to the next column
+        let (leading_dim, next_elem, next_col) = match self.memory_layout {
+            ColumnMajor => (n_rows as i32, 1, n_rows),
+            RowMajor => (n_cols as i32, n_cols as i32, 1),
+        };
+
+        // r_next_elem, r_next_col: the same as next_elem and next_col, but for the matrix r, whose
+        // layout is allowed to differ from the one of q.
+        let (r_next_elem, r_next_elem_offset, r_next_col) = match self.r_layout {
+            ColumnMajor => (1, 1, n_cols),
+            RowMajor => (n_cols as i32, n_cols, 1),
+        };
+
+        // The projections of a panel onto the previous panels, which are copied into R.
+        let mut work = vec![A::zero(); n_cols * self.block];
+
+        // Panels start at `start` rather than at multiples of the block size, so that a computation
+        // interrupted within a panel can be continued from any column.
+        let mut panel_start = start;
+        while panel_start < n_cols {
+            let panel_end = (panel_start + self.block).min(n_cols);
+            let panel_len = panel_end - panel_start;
+
+            if panel_start > start && deadline_passed(deadline) {
+                return Ok(ComputeStatus::InProgress { next_col: panel_start });
+            }
+
+            self.q.slice_mut(s![.., panel_start..panel_end]).assign(&a.slice(s![.., panel_start..panel_end]));
+
+            // As in the classical procedure, several overlapping slices into the q matrix are
+            // needed: the immutable `q_matrix` holds the already orthonormalized columns, and the
+            // mutable `q_panel` the columns which are currently orthonormalized. Choosing the
+            // offsets and leading dimensions appropriately makes sure that blas does not access
+            // the same memory location through both of them.
+            let q_len = self.q.len();
+            let q_ptr = self.q.as_mut_ptr();
+            let q_matrix = unsafe {
+                slice::from_raw_parts(q_ptr, q_len)
+            };
+            let panel_offset = next_col * panel_start;
+
+            if panel_start > 0 {
+                // The projections W = Q_prevᴴ·A_panel, stored in the layout of q.
+                let work_leading_dim = match self.memory_layout {
+                    ColumnMajor => panel_start,
+                    RowMajor => panel_len,
+                };
+                let work_slice = &mut work[..panel_start * panel_len];
+                let q_panel = unsafe {
+                    slice::from_raw_parts_mut(q_ptr.add(panel_offset), q_len - panel_offset)
+                };
+
+                unsafe {
+                    A::gemm(
+                        self.memory_layout,
+                        A::ADJOINT,
+                        blas::Transpose::None,
+                        panel_start as i32,
+                        panel_len as i32,
+                        n_rows as i32,
+                        A::one(),
+                        q_matrix,
+                        leading_dim,
+                        q_panel,
+                        leading_dim,
+                        A::zero(),
+                        work_slice,
+                        work_leading_dim as i32,
+                    );
+
+                    // A_panel ← A_panel - Q_prev·W removes the projections onto all previous
+                    // panels at once.
+                    A::gemm(
+                        self.memory_layout,
+                        blas::Transpose::None,
+                        blas::Transpose::None,
+                        n_rows as i32,
+                        panel_len as i32,
+                        panel_start as i32,
+                        -A::one(),
+                        q_matrix,
+                        leading_dim,
+                        work_slice,
+                        work_leading_dim as i32,
+                        A::one(),
+                        q_panel,
+                        leading_dim,
+                    );
+                }
+
+                // NOTE: This unwrap is safe, because the work slice holds exactly the elements of
+                // a contiguous array of this shape.
+                let projections = ArrayView2::from_shape(
+                    (panel_start, panel_len).set_f(self.memory_layout == ColumnMajor),
+                    &*work_slice,
+                ).unwrap();
+                self.r.slice_mut(s![..panel_start, panel_start..panel_end]).assign(&projections);
+            }
+
+            for i in panel_start..panel_end {
+                if i > start && deadline_passed(deadline) {
+                    return Ok(ComputeStatus::InProgress { next_col: i });
+                }
+
+                let q_column = unsafe {
+                    slice::from_raw_parts_mut(q_ptr.add(next_col * i), q_len - next_col * i)
+                };
+
+                if i > panel_start {
+                    // NOTE: This unwrap is save, because we have made sure at creation that r_slice
+                    // is contiguous.
+                    let r_slice = self.r.as_slice_memory_order_mut().unwrap();
+                    let r_column = &mut r_slice[r_next_col * i + r_next_elem_offset * panel_start..];
+
+                    // Orthogonalize the column against the previous columns of the same panel by
+                    // classical Gram Schmidt.
+                    unsafe {
+                        A::gemv(
+                            self.memory_layout,
+                            A::ADJOINT,
+                            n_rows as i32,
+                            (i - panel_start) as i32,
+                            A::one(),
+                            &q_matrix[panel_offset..],
+                            leading_dim,
+                            q_column,
+                            next_elem,
+                            A::zero(),
+                            r_column,
+                            r_next_elem,
+                        );
+
+                        A::gemv(
+                            self.memory_layout,
+                            blas::Transpose::None,
+                            n_rows as i32,
+                            (i - panel_start) as i32,
+                            -A::one(),
+                            &q_matrix[panel_offset..],
+                            leading_dim,
+                            r_column,
+                            r_next_elem,
+                            A::one(),
+                            q_column,
+                            next_elem,
+                        );
+                    }
+                }
+
+                let norm = unsafe {
+                    A::nrm2(n_rows as i32, q_column, next_elem)
+                };
+
+                check_rank(i, norm, self.tolerance(), || normalization(&a.column(i)))?;
+
+                let mut v = self.q.column_mut(i);
+                v /= A::from_real(norm);
+                self.r[(i,i)] = A::from_real(norm);
+
+                if let Some(status) = observe_columns(&self.r, i..i + 1, &mut observe) {
+                    return Ok(status);
+                }
+            }
+
+            panel_start = panel_end;
+        }
+
+        Ok(ComputeStatus::Done)
+    }
+}
+
+#[cfg(test)]
+mod block_tests {
+    extern crate openblas_src;
+
+    use crate::Classical;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn block_matches_classical() {
+        let a = matrix(60, 45);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            let mut cgs = Classical::from_matrix(*a).unwrap();
+            cgs.compute(*a).unwrap();
+
+            for &block in &[1, 4, 7, 45, 64] {
+                for &transpose_r in &[false, true] {
+                    let column_major = get_layout(*a) == Some(blas::Layout::ColumnMajor);
+                    let mut bcgs = BlockClassical::from_shape_with_r_transposed(a.dim().set_f(column_major), transpose_r).unwrap();
+                    bcgs.block = block;
+                    bcgs.compute(*a).unwrap();
+                    assert!(bcgs.q().all_close(cgs.q(), 1e-12));
+                    assert!(bcgs.r().all_close(cgs.r(), 1e-12));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn interrupted_panels_are_continued() {
+        let a = matrix(60, 45);
+        let mut bcgs = BlockClassical::from_shape_with_block(a.dim(), 8).unwrap();
+        // Every call with a passed deadline orthogonalizes a whole panel against the previous
+        // ones, but only orthonormalizes its first column.
+        let deadline = Instant::now();
+        let mut status = bcgs.compute_budgeted(&a, deadline).unwrap();
+        let mut steps = 1;
+        while let ComputeStatus::InProgress { next_col } = status {
+            assert_eq!(next_col, steps);
+            status = bcgs.continue_from(&a, next_col, deadline).unwrap();
+            steps += 1;
+        }
+        assert_eq!(steps, a.cols());
+        assert!(a.all_close(&bcgs.q().dot(bcgs.r()), 1e-12));
+        assert!(crate::utils::orthogonality_loss(bcgs.q()) < 1e-12);
+    }
+
+    // Like the classical procedure, the blocked one breaks down completely on the Hilbert matrix
+    // beyond n = 6.
+    #[test]
+    fn hilbert_breaks_down() {
+        for &n in [8, 10].iter() {
+            let a = crate::utils::hilbert(n);
+            let mut bcgs = BlockClassical::from_matrix(&a).unwrap();
+            bcgs.compute(&a).unwrap();
+            let loss = crate::utils::orthogonality_loss(bcgs.q());
+            assert!(loss > 1.0, "n = {}: loss of orthogonality {:e} does not exceed 1", n, loss);
+            assert!(a.all_close(&bcgs.q().dot(bcgs.r()), 1e-14));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_block_size_panics() {
+        let _ = BlockClassical::<f64>::from_shape_with_block((4, 4), 0);
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn mixed_layouts_are_accepted() {
+        crate::utils::check_mixed_layouts(BlockClassical::<f64>::from_shape_accepting_any_layout);
+    }
+}
+
+#[cfg(test)]
+generate_tests!(
+    BlockClassical,
+    1e-12,
+    precisions: [double: f64 => 1e-12, single: f32 => 1e-4]
+);
+
+#[cfg(test)]
+mod stability_tests {
+    extern crate openblas_src;
+
+    use crate::utils::{
+        check_conditioned_matrices,
+        check_hilbert_orthogonality,
+        check_identity_plus_noise_orthogonality,
+    };
+    use super::*;
+
+    // The blocked procedure loses orthogonality just like the classical one, see
+    // `block_tests::hilbert_breaks_down`.
+    #[test]
+    fn hilbert_orthogonality() {
+        check_hilbert_orthogonality::<BlockClassical<f64>>(&[(6, 1e-4)]);
+    }
+
+    #[test]
+    fn identity_plus_noise_orthogonality() {
+        check_identity_plus_noise_orthogonality::<BlockClassical<f64>, _>(|kappa| 10.0 * f64::EPSILON * kappa * kappa);
+    }
+
+    #[test]
+    fn random_conditioned_matrices() {
+        check_conditioned_matrices::<BlockClassical<f64>, _>(1e-12, |kappa| 10.0 * f64::EPSILON * kappa * kappa);
+    }
+}
@@ -0,0 +1,590 @@
+//! The blas routines used throughout the crate.
+//!
+//! By default, these are the cblas routines of the blas library linked into the final binary. With
+//! the `pure-rust` feature, or without the default `cblas` feature, they are replaced by
+//! implementations on top of ndarray with the same signatures and semantics, so that no blas
+//! library is needed. Only positive increments are supported, which is all the crate uses.
+//! Without the `cblas` feature, the crate does not depend on the cblas crate at all and builds for
+//! targets like `wasm32-unknown-unknown`; the enums describing the arguments are mirrored here.
+//!
+//! The fallback multiplies matrices with ndarray's `dot` and matrix-vector products with plain
+//! loops, which are slower than an optimized blas library. Compared with a single threaded
+//! OpenBLAS, factorizing a row major 1024×1024 matrix takes about five times as long with
+//! [`Classical`](crate::Classical) and about two and a half times as long with
+//! [`BlockClassical`](crate::BlockClassical). Column major matrices are hardly affected.
+
+#[cfg(feature = "cblas")]
+pub use cblas::{
+    Diagonal,
+    Layout,
+    Part,
+    Side,
+    Transpose,
+};
+
+/// Mirrors of the enums of the cblas crate, which is not available on all targets, e.g. wasm32.
+#[cfg(not(feature = "cblas"))]
+mod types {
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Diagonal {
+        Generic,
+        Unit,
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Layout {
+        RowMajor,
+        ColumnMajor,
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Part {
+        Upper,
+        Lower,
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Side {
+        Left,
+        Right,
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Transpose {
+        None,
+        Ordinary,
+        Conjugate,
+    }
+}
+
+#[cfg(not(feature = "cblas"))]
+pub use self::types::*;
+
+#[cfg(all(feature = "cblas", not(feature = "pure-rust")))]
+pub(crate) use cblas::{
+    cgemm,
+    cgemv,
+    caxpy,
+    dgemm,
+    dgemv,
+    daxpy,
+    dnrm2,
+    dsyr,
+    dsyrk,
+    dtrsm,
+    dtrsv,
+    dznrm2,
+    scnrm2,
+    sgemm,
+    sgemv,
+    saxpy,
+    snrm2,
+    zgemm,
+    zgemv,
+    zaxpy,
+};
+
+#[cfg(any(not(feature = "cblas"), feature = "pure-rust"))]
+pub(crate) use self::fallback::{
+    axpy as caxpy,
+    axpy as daxpy,
+    axpy as saxpy,
+    axpy as zaxpy,
+    gemm as cgemm,
+    gemm as dgemm,
+    gemm as sgemm,
+    gemm as zgemm,
+    gemv as cgemv,
+    gemv as dgemv,
+    gemv as sgemv,
+    gemv as zgemv,
+    nrm2 as dnrm2,
+    nrm2 as dznrm2,
+    nrm2 as scnrm2,
+    nrm2 as snrm2,
+    syr as dsyr,
+    syrk as dsyrk,
+    trsm as dtrsm,
+    trsv as dtrsv,
+};
+
+#[cfg(any(not(feature = "cblas"), feature = "pure-rust"))]
+mod fallback {
+    use super::{
+        Diagonal,
+        Layout,
+        Part,
+        Side,
+        Transpose,
+    };
+    use ndarray::{
+        Dimension,
+        ShapeBuilder,
+        Zip,
+        s,
+    };
+    use ndarray::prelude::*;
+    use num_traits::{
+        Float,
+        Zero,
+    };
+
+    use crate::GramSchmidtScalar;
+
+    /// Returns a view of the `rows×cols` matrix stored in `a` in `layout` with the leading
+    /// dimension `ld`.
+    unsafe fn matrix<A>(layout: Layout, (rows, cols): (i32, i32), a: &[A], ld: i32) -> ArrayView2<'_, A> {
+        let strides = match layout {
+            Layout::RowMajor => (ld as usize, 1),
+            Layout::ColumnMajor => (1, ld as usize),
+        };
+        ArrayView2::from_shape_ptr((rows as usize, cols as usize).strides(strides), a.as_ptr())
+    }
+
+    /// The mutable counterpart of [`matrix`].
+    unsafe fn matrix_mut<A>(layout: Layout, (rows, cols): (i32, i32), a: &mut [A], ld: i32) -> ArrayViewMut2<'_, A> {
+        let strides = match layout {
+            Layout::RowMajor => (ld as usize, 1),
+            Layout::ColumnMajor => (1, ld as usize),
+        };
+        ArrayViewMut2::from_shape_ptr((rows as usize, cols as usize).strides(strides), a.as_mut_ptr())
+    }
+
+    /// Returns a view of the vector of length `n` stored in `x` with the increment `inc`.
+    unsafe fn vector<A>(n: i32, x: &[A], inc: i32) -> ArrayView1<'_, A> {
+        ArrayView1::from_shape_ptr((n as usize,).strides((inc as usize,)), x.as_ptr())
+    }
+
+    /// The mutable counterpart of [`vector`].
+    unsafe fn vector_mut<A>(n: i32, x: &mut [A], inc: i32) -> ArrayViewMut1<'_, A> {
+        ArrayViewMut1::from_shape_ptr((n as usize,).strides((inc as usize,)), x.as_mut_ptr())
+    }
+
+    /// Returns `op(a)` for the stored matrix `a`.
+    fn op<A: GramSchmidtScalar>(a: ArrayView2<A>, trans: Transpose) -> Array2<A> {
+        match trans {
+            Transpose::None => a.to_owned(),
+            Transpose::Ordinary => a.t().to_owned(),
+            Transpose::Conjugate => a.t().mapv(A::conj),
+        }
+    }
+
+    /// Computes `y ← α·product + β·y`. Like blas, `y` is not read if `β` is zero.
+    fn update<A, D>(mut y: ArrayViewMut<A, D>, alpha: A, product: &Array<A, D>, beta: A)
+        where A: GramSchmidtScalar,
+              D: Dimension,
+    {
+        if beta.is_zero() {
+            Zip::from(&mut y).and(product).apply(|y, &p| *y = alpha * p);
+        } else {
+            Zip::from(&mut y).and(product).apply(|y, &p| *y = alpha * p + beta * *y);
+        }
+    }
+
+    /// Solves `t·x = b` in place of `x = b`, where `t` is upper or lower triangular.
+    fn substitute<A: GramSchmidtScalar>(t: ArrayView2<A>, upper: bool, unit: bool, mut x: ArrayViewMut1<A>) {
+        let n = x.len();
+        let mut solve = |i: usize, done: ndarray::Slice| {
+            let sum = t.row(i).slice_axis(Axis(0), done).dot(&x.slice_axis(Axis(0), done));
+            let x_i = x[i] - sum;
+            x[i] = if unit { x_i } else { x_i / t[(i, i)] };
+        };
+        if upper {
+            for i in (0..n).rev() {
+                solve(i, ndarray::Slice::from(i + 1..));
+            }
+        } else {
+            for i in 0..n {
+                solve(i, ndarray::Slice::from(..i));
+            }
+        }
+    }
+
+    /// See `cblas::dgemv`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn gemv<A: GramSchmidtScalar>(
+        layout: Layout,
+        transa: Transpose,
+        m: i32,
+        n: i32,
+        alpha: A,
+        a: &[A],
+        lda: i32,
+        x: &[A],
+        incx: i32,
+        beta: A,
+        y: &mut [A],
+        incy: i32,
+    ) {
+        let a = matrix(layout, (m, n), a, lda);
+        let (len_x, len_y) = match transa {
+            Transpose::None => (n, m),
+            _ => (m, n),
+        };
+        let x = vector(len_x, x, incx);
+        let product = match transa {
+            Transpose::None => mat_vec(a, x),
+            Transpose::Ordinary => mat_vec(a.t(), x),
+            // Aᴴx is the conjugate of Aᵀx̄.
+            Transpose::Conjugate => mat_vec(a.t(), x.mapv(A::conj).view()).mapv(A::conj),
+        };
+        update(vector_mut(len_y, y, incy), alpha, &product, beta);
+    }
+
+    /// Returns `a·x`, traversing `a` along its contiguous axis.
+    fn mat_vec<A: GramSchmidtScalar>(a: ArrayView2<A>, x: ArrayView1<A>) -> Array1<A> {
+        if a.strides()[1] == 1 {
+            a.genrows().into_iter().map(|row| row.dot(&x)).collect()
+        } else {
+            // Summing up the scaled columns avoids striding through the rows.
+            let mut product = Array1::zeros(a.rows());
+            for (column, &x_j) in a.gencolumns().into_iter().zip(&x) {
+                product.scaled_add(x_j, &column);
+            }
+            product
+        }
+    }
+
+    /// See `cblas::dgemm`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn gemm<A: GramSchmidtScalar>(
+        layout: Layout,
+        transa: Transpose,
+        transb: Transpose,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: A,
+        a: &[A],
+        lda: i32,
+        b: &[A],
+        ldb: i32,
+        beta: A,
+        c: &mut [A],
+        ldc: i32,
+    ) {
+        let stored = |trans: Transpose, rows: i32, cols: i32| match trans {
+            Transpose::None => (rows, cols),
+            _ => (cols, rows),
+        };
+        let a = op(matrix(layout, stored(transa, m, k), a, lda), transa);
+        let b = op(matrix(layout, stored(transb, k, n), b, ldb), transb);
+        update(matrix_mut(layout, (m, n), c, ldc), alpha, &a.dot(&b), beta);
+    }
+
+    /// See `cblas::dnrm2`. The sum of squares is scaled by the largest modulus to avoid overflow
+    /// and underflow.
+    pub(crate) unsafe fn nrm2<A: GramSchmidtScalar>(n: i32, x: &[A], incx: i32) -> A::Real {
+        let x = vector(n, x, incx);
+        // A NaN is propagated, since no modulus compares greater than it.
+        let scale = x.iter()
+            .map(|&x_i| x_i.modulus())
+            .fold(A::Real::zero(), |max, r| if r > max || r.is_nan() { r } else { max });
+        if scale.is_zero() || !scale.is_finite() {
+            return scale;
+        }
+        let sum = x.iter().fold(A::Real::zero(), |sum, &x_i| {
+            let ratio = x_i.modulus() / scale;
+            sum + ratio * ratio
+        });
+        scale * sum.sqrt()
+    }
+
+    /// See `cblas::daxpy`.
+    pub(crate) unsafe fn axpy<A: GramSchmidtScalar>(n: i32, alpha: A, x: &[A], incx: i32, y: &mut [A], incy: i32) {
+        vector_mut(n, y, incy).scaled_add(alpha, &vector(n, x, incx));
+    }
+
+    /// See `cblas::dtrsv`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn trsv(
+        layout: Layout,
+        uplo: Part,
+        transa: Transpose,
+        diag: Diagonal,
+        n: i32,
+        a: &[f64],
+        lda: i32,
+        x: &mut [f64],
+        incx: i32,
+    ) {
+        let a = matrix(layout, (n, n), a, lda);
+        let (t, upper) = match transa {
+            Transpose::None => (a, uplo == Part::Upper),
+            _ => (a.reversed_axes(), uplo == Part::Lower),
+        };
+        substitute(t, upper, diag == Diagonal::Unit, vector_mut(n, x, incx));
+    }
+
+    /// See `cblas::dtrsm`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn trsm(
+        layout: Layout,
+        side: Side,
+        uplo: Part,
+        transa: Transpose,
+        diag: Diagonal,
+        m: i32,
+        n: i32,
+        alpha: f64,
+        a: &[f64],
+        lda: i32,
+        b: &mut [f64],
+        ldb: i32,
+    ) {
+        let order = match side {
+            Side::Left => m,
+            Side::Right => n,
+        };
+        let a = matrix(layout, (order, order), a, lda);
+        let (t, upper) = match transa {
+            Transpose::None => (a, uplo == Part::Upper),
+            _ => (a.reversed_axes(), uplo == Part::Lower),
+        };
+        let unit = diag == Diagonal::Unit;
+
+        let mut b = matrix_mut(layout, (m, n), b, ldb);
+        b *= alpha;
+        match side {
+            // op(A)·X = B is solved column by column.
+            Side::Left => for column in b.gencolumns_mut() {
+                substitute(t, upper, unit, column);
+            },
+            // X·op(A) = B is equivalent to op(A)ᵀ·Xᵀ = Bᵀ, solved row by row.
+            Side::Right => for row in b.genrows_mut() {
+                substitute(t.reversed_axes(), !upper, unit, row);
+            },
+        }
+    }
+
+    /// See `cblas::dsyrk`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn syrk(
+        layout: Layout,
+        uplo: Part,
+        trans: Transpose,
+        n: i32,
+        k: i32,
+        alpha: f64,
+        a: &[f64],
+        lda: i32,
+        beta: f64,
+        c: &mut [f64],
+        ldc: i32,
+    ) {
+        let product = match trans {
+            Transpose::None => {
+                let a = matrix(layout, (n, k), a, lda);
+                a.dot(&a.t())
+            },
+            _ => {
+                let a = matrix(layout, (k, n), a, lda);
+                a.t().dot(&a)
+            },
+        };
+        let mut c = matrix_mut(layout, (n, n), c, ldc);
+        for ((i, j), c_ij) in c.indexed_iter_mut() {
+            if (uplo == Part::Upper && j >= i) || (uplo == Part::Lower && j <= i) {
+                *c_ij = if beta == 0.0 {
+                    alpha * product[(i, j)]
+                } else {
+                    alpha * product[(i, j)] + beta * *c_ij
+                };
+            }
+        }
+    }
+
+    /// See `cblas::dsyr`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn syr(
+        layout: Layout,
+        uplo: Part,
+        n: i32,
+        alpha: f64,
+        x: &[f64],
+        incx: i32,
+        a: &mut [f64],
+        lda: i32,
+    ) {
+        let x = vector(n, x, incx);
+        let mut a = matrix_mut(layout, (n, n), a, lda);
+        for i in 0..n as usize {
+            let columns = match uplo {
+                Part::Upper => s![i..],
+                Part::Lower => s![..=i],
+            };
+            let offset = match uplo {
+                Part::Upper => i,
+                Part::Lower => 0,
+            };
+            let mut row = a.row_mut(i);
+            let mut row = row.slice_mut(columns);
+            for (j, a_ij) in row.iter_mut().enumerate() {
+                *a_ij += alpha * x[i] * x[offset + j];
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "cblas", feature = "pure-rust"))]
+mod tests {
+    extern crate openblas_src;
+
+    use cblas::{
+        Diagonal,
+        Layout,
+        Part,
+        Side,
+        Transpose,
+    };
+    use ndarray::prelude::*;
+    use ndarray_rand::RandomExt;
+    use num_complex::Complex;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+
+    use super::fallback;
+
+    const LAYOUTS: [Layout; 2] = [Layout::RowMajor, Layout::ColumnMajor];
+
+    /// Random data for matrices and vectors of up to 6×6 elements, interpreted with the leading
+    /// dimensions and increments below.
+    fn data(seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Array1::random_using(64, Normal::new(0.0, 1.0), &mut rng).to_vec()
+    }
+
+    fn assert_close(fallback: &[f64], blas: &[f64]) {
+        assert!(fallback.iter().zip(blas).all(|(x, y)| (x - y).abs() < 1e-12), "{:?} != {:?}", fallback, blas);
+    }
+
+    #[test]
+    fn level_one_and_two_match_blas() {
+        let a = data(1);
+        let x = data(2);
+        for &layout in &LAYOUTS {
+            for &trans in &[Transpose::None, Transpose::Ordinary] {
+                for &beta in &[0.0, 0.5] {
+                    let mut y_fallback = data(3);
+                    let mut y_blas = y_fallback.clone();
+                    unsafe {
+                        fallback::gemv(layout, trans, 4, 3, 1.5, &a, 7, &x, 2, beta, &mut y_fallback, 3);
+                        cblas::dgemv(layout, trans, 4, 3, 1.5, &a, 7, &x, 2, beta, &mut y_blas, 3);
+                    }
+                    assert_close(&y_fallback, &y_blas);
+                }
+
+                for &uplo in &[Part::Upper, Part::Lower] {
+                    // A dominant diagonal keeps the triangular solves well conditioned.
+                    let mut a = a.clone();
+                    for i in 0..5 {
+                        a[i * 8] += 10.0;
+                    }
+                    let mut x_fallback = x.clone();
+                    let mut x_blas = x.clone();
+                    unsafe {
+                        fallback::trsv(layout, uplo, trans, Diagonal::Generic, 5, &a, 7, &mut x_fallback, 2);
+                        cblas::dtrsv(layout, uplo, trans, Diagonal::Generic, 5, &a, 7, &mut x_blas, 2);
+                    }
+                    assert_close(&x_fallback, &x_blas);
+
+                    let mut a_fallback = a.clone();
+                    let mut a_blas = a.clone();
+                    unsafe {
+                        fallback::syr(layout, uplo, 5, 0.5, &x, 2, &mut a_fallback, 7);
+                        cblas::dsyr(layout, uplo, 5, 0.5, &x, 2, &mut a_blas, 7);
+                    }
+                    assert_close(&a_fallback, &a_blas);
+                }
+            }
+        }
+
+        let mut y_fallback = data(3);
+        let mut y_blas = y_fallback.clone();
+        unsafe {
+            fallback::axpy(5, -2.0, &x, 3, &mut y_fallback, 2);
+            cblas::daxpy(5, -2.0, &x, 3, &mut y_blas, 2);
+            assert!((fallback::nrm2(5, &x, 3) - cblas::dnrm2(5, &x, 3)).abs() < 1e-12);
+            assert_eq!(fallback::nrm2::<f64>(0, &x, 1), 0.0);
+        }
+        assert_close(&y_fallback, &y_blas);
+    }
+
+    #[test]
+    fn level_three_matches_blas() {
+        let a = data(4);
+        let b = data(5);
+        let transposes = [Transpose::None, Transpose::Ordinary];
+        for &layout in &LAYOUTS {
+            for &transa in &transposes {
+                for &transb in &transposes {
+                    let mut c_fallback = data(6);
+                    let mut c_blas = c_fallback.clone();
+                    unsafe {
+                        fallback::gemm(layout, transa, transb, 4, 3, 5, 1.5, &a, 6, &b, 6, 0.5, &mut c_fallback, 6);
+                        cblas::dgemm(layout, transa, transb, 4, 3, 5, 1.5, &a, 6, &b, 6, 0.5, &mut c_blas, 6);
+                    }
+                    assert_close(&c_fallback, &c_blas);
+                }
+
+                for &uplo in &[Part::Upper, Part::Lower] {
+                    let mut c_fallback = data(6);
+                    let mut c_blas = c_fallback.clone();
+                    unsafe {
+                        fallback::syrk(layout, uplo, transa, 4, 3, 1.5, &a, 6, 0.0, &mut c_fallback, 6);
+                        cblas::dsyrk(layout, uplo, transa, 4, 3, 1.5, &a, 6, 0.0, &mut c_blas, 6);
+                    }
+                    assert_close(&c_fallback, &c_blas);
+
+                    let mut a = a.clone();
+                    for i in 0..5 {
+                        a[i * 7] += 10.0;
+                    }
+                    for &(side, m, n) in &[(Side::Left, 5, 3), (Side::Right, 3, 5)] {
+                        let mut b_fallback = b.clone();
+                        let mut b_blas = b.clone();
+                        unsafe {
+                            fallback::trsm(layout, side, uplo, transa, Diagonal::Unit, m, n, 2.0, &a, 6, &mut b_fallback, 6);
+                            cblas::dtrsm(layout, side, uplo, transa, Diagonal::Unit, m, n, 2.0, &a, 6, &mut b_blas, 6);
+                        }
+                        assert_close(&b_fallback, &b_blas);
+
+                        let mut b_fallback = b.clone();
+                        let mut b_blas = b.clone();
+                        unsafe {
+                            fallback::trsm(layout, side, uplo, transa, Diagonal::Generic, m, n, 2.0, &a, 6, &mut b_fallback, 6);
+                            cblas::dtrsm(layout, side, uplo, transa, Diagonal::Generic, m, n, 2.0, &a, 6, &mut b_blas, 6);
+                        }
+                        assert_close(&b_fallback, &b_blas);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conjugate_transpose_matches_blas() {
+        let a: Vec<_> = data(7).chunks(2).map(|pair| Complex::new(pair[0], pair[1])).collect();
+        let b: Vec<_> = data(8).chunks(2).map(|pair| Complex::new(pair[0], pair[1])).collect();
+        for &layout in &LAYOUTS {
+            let mut y_fallback = vec![Complex::new(1.0, 0.0); 5];
+            let mut y_blas = y_fallback.clone();
+            let mut c_fallback = vec![Complex::new(0.0, 0.0); 16];
+            let mut c_blas = c_fallback.clone();
+            let one = Complex::new(1.0, 0.0);
+            let zero = Complex::new(0.0, 0.0);
+            unsafe {
+                fallback::gemv(layout, Transpose::Conjugate, 4, 3, one, &a, 5, &b, 1, one, &mut y_fallback, 1);
+                cblas::zgemv(layout, Transpose::Conjugate, 4, 3, one, &a, 5, &b, 1, one, &mut y_blas, 1);
+                fallback::gemm(layout, Transpose::Conjugate, Transpose::None, 3, 2, 4, one, &a, 5, &b, 5, zero, &mut c_fallback, 4);
+                cblas::zgemm(layout, Transpose::Conjugate, Transpose::None, 3, 2, 4, one, &a, 5, &b, 5, zero, &mut c_blas, 4);
+                assert!((fallback::nrm2(5, &a, 2) - cblas::dznrm2(5, &a, 2)).abs() < 1e-12);
+            }
+            assert!(y_fallback.iter().zip(&y_blas).all(|(x, y)| (x - y).norm() < 1e-12));
+            assert!(c_fallback.iter().zip(&c_blas).all(|(x, y)| (x - y).norm() < 1e-12));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use ndarray::prelude::*;
+use ndarray::ShapeBuilder;
+
+use crate::{
+    Classical,
+    Error,
+    GramSchmidt,
+    GramSchmidtScalar,
+    Layout,
+    Modified,
+    Procedure,
+    Reorthogonalized,
+    Result,
+    utils,
+};
+
+/// An object safe subset of [`GramSchmidt`], so that a procedure selected at runtime can be used
+/// through a `Box<dyn DynGramSchmidt>`, as returned by [`GramSchmidtBuilder::build`].
+///
+/// [`GramSchmidt`]: trait.GramSchmidt.html
+/// [`GramSchmidtBuilder::build`]: struct.GramSchmidtBuilder.html#method.build
+pub trait DynGramSchmidt<A = f64> {
+    /// Computes the QR decomposition of `a`, see
+    /// [`GramSchmidt::compute`](trait.GramSchmidt.html#tymethod.compute).
+    fn compute(&mut self, a: ArrayView2<'_, A>) -> Result<()>;
+
+    /// Returns the orthonormal matrix Q of the last factorization.
+    fn q(&self) -> &Array2<A>;
+
+    /// Returns the upper triangular matrix R of the last factorization.
+    fn r(&self) -> &Array2<A>;
+
+    /// Returns the memory layout the procedure was configured for. Matrices passed to
+    /// [`compute`](#tymethod.compute) in the other layout are rejected with
+    /// [`Error::IncompatibleLayouts`](enum.Error.html#variant.IncompatibleLayouts).
+    fn layout(&self) -> Layout;
+}
+
+/// Wraps a procedure to implement [`DynGramSchmidt`]. Implementing it for the procedures
+/// directly would make their methods ambiguous whenever both traits are in scope.
+struct Erased<G>(G);
+
+impl<G: GramSchmidt> DynGramSchmidt<G::Scalar> for Erased<G> {
+    fn compute(&mut self, a: ArrayView2<'_, G::Scalar>) -> Result<()> {
+        // Not all procedures insist on their layout, e.g. `Modified` accepts either one.
+        match utils::get_layout(&a) {
+            Some(layout) if Layout::from(layout) != self.layout() => Err(Error::IncompatibleLayouts)?,
+            _ => {},
+        }
+        self.0.compute(&a)
+    }
+
+    fn q(&self) -> &Array2<G::Scalar> {
+        self.0.q()
+    }
+
+    fn r(&self) -> &Array2<G::Scalar> {
+        self.0.r()
+    }
+
+    fn layout(&self) -> Layout {
+        // NOTE: This unwrap is safe, because Q is always allocated contiguously.
+        utils::get_layout(self.0.q()).unwrap().into()
+    }
+}
+
+/// Prepares a Gram Schmidt procedure before the matrix to be factorized is available, with the
+/// procedure and the memory layout chosen explicitly instead of inferred from a matrix.
+///
+/// By default, the reorthogonalized procedure is used with a row major layout, matching
+/// [`Options`] and `ndarray` respectively.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{GramSchmidtBuilder, Layout, Procedure};
+/// use ndarray::{arr2, ShapeBuilder};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let mut procedure = GramSchmidtBuilder::new((3, 2))
+///     .layout(Layout::ColumnMajor)
+///     .procedure(Procedure::Modified)
+///     .build()?;
+///
+/// let mut a = ndarray::Array2::zeros((3, 2).f());
+/// a.assign(&arr2(&[[1.0, 0.0],
+///                  [1.0, 1.0],
+///                  [1.0, 2.0]]));
+/// procedure.compute(a.view())?;
+/// assert!(a.all_close(&procedure.q().dot(procedure.r()), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Options`]: struct.Options.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GramSchmidtBuilder {
+    shape: (usize, usize),
+    layout: Layout,
+    procedure: Procedure,
+}
+
+impl GramSchmidtBuilder {
+    /// Starts configuring a procedure for matrices of the given shape.
+    pub fn new(shape: (usize, usize)) -> Self {
+        Self {
+            shape,
+            layout: Layout::RowMajor,
+            procedure: Procedure::Reorthogonalized,
+        }
+    }
+
+    /// Sets the memory layout of the matrices to be factorized.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets the procedure used for the factorization.
+    pub fn procedure(mut self, procedure: Procedure) -> Self {
+        self.procedure = procedure;
+        self
+    }
+
+    /// Reserves the memory for the configured procedure, see
+    /// [`GramSchmidt::from_shape`](trait.GramSchmidt.html#method.from_shape).
+    pub fn build<A>(&self) -> Result<Box<dyn DynGramSchmidt<A>>>
+        where A: GramSchmidtScalar + 'static
+    {
+        let shape = self.shape.set_f(self.layout == Layout::ColumnMajor);
+        Ok(match self.procedure {
+            Procedure::Classical => Box::new(Erased(Classical::<A>::from_shape(shape)?)),
+            Procedure::Modified => Box::new(Erased(Modified::<A>::from_shape(shape)?)),
+            Procedure::Reorthogonalized => Box::new(Erased(Reorthogonalized::<A>::from_shape(shape)?)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn layout_is_enforced() {
+        let a = arr2(&[[1.0, 0.0],
+                       [1.0, 1.0],
+                       [1.0, 2.0]]);
+        for &procedure in [Procedure::Classical, Procedure::Modified, Procedure::Reorthogonalized].iter() {
+            let mut column_major = GramSchmidtBuilder::new((3, 2))
+                .layout(Layout::ColumnMajor)
+                .procedure(procedure)
+                .build()
+                .unwrap();
+            assert_eq!(column_major.layout(), Layout::ColumnMajor);
+            match column_major.compute(a.view()) {
+                Err(crate::Error::IncompatibleLayouts) => {},
+                other => panic!("expected incompatible layouts, got {:?}", other),
+            }
+
+            let mut row_major = GramSchmidtBuilder::new((3, 2)).procedure(procedure).build().unwrap();
+            assert_eq!(row_major.layout(), Layout::RowMajor);
+            row_major.compute(a.view()).unwrap();
+            assert!(a.all_close(&row_major.q().dot(row_major.r()), 1e-14));
+        }
+    }
+
+    #[test]
+    fn single_precision_is_supported() {
+        let procedure = GramSchmidtBuilder::new((4, 3)).build::<f32>().unwrap();
+        assert_eq!(procedure.q().dim(), (4, 3));
+    }
+}
@@ -1,4 +1,3 @@
-use cblas;
 use ndarray::{
     Data,
     Dim,
@@ -6,15 +5,44 @@ use ndarray::{
     ShapeBuilder,
 };
 use ndarray::prelude::*;
+use std::fmt;
 use std::slice;
+use std::time::Instant;
 
 use crate::{
+    ColumnProvenance,
+    ComputeStatus,
     Error,
     GramSchmidt,
+    GramSchmidtScalar,
     Result,
+    StabilityClass,
+    blas,
+    givens,
     utils::{
         as_slice_with_layout,
+        check_finite,
+        check_pinned,
+        check_rank,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        deadline_passed,
+        default_tolerance,
+        display_factorization,
+        force_positive_diagonal,
         get_layout,
+        is_broadcast,
+        last_flops,
+        normalization,
+        observe_columns,
+        orthonormal_shortcut,
+        parts_layouts,
+        pin_columns,
+        provenance,
+        rescale_columns,
+        reset_factors,
+        transpose_layout,
     },
 };
 
@@ -25,14 +53,36 @@ use crate::{
 /// [Gram Schmidt Wikipedia entry]: https://en.wikipedia.org/wiki/Gram-Schmidt_process
 /// [`GramSchmidt` trait]: GramSchmidt
 #[derive(Clone, Debug)]
-pub struct Classical {
-    q: Array2<f64>,
-    r: Array2<f64>,
-    memory_layout: cblas::Layout,
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    crate = "serde_crate",
+    try_from = "crate::serialization::ClassicalState<A>",
+    bound(serialize = "A: serde_crate::Serialize", deserialize = "A: GramSchmidtScalar + serde_crate::Deserialize<'de>"),
+))]
+pub struct Classical<A = f64> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::CblasLayout"))]
+    memory_layout: blas::Layout,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::CblasLayout"))]
+    r_layout: blas::Layout,
+    pinned: usize,
+    sign_flipped: Vec<bool>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    computed: bool,
+    tolerance: Option<f64>,
+    orthonormal_threshold: f64,
+    skip_finite_check: bool,
+    accept_any_layout: bool,
 }
 
-impl GramSchmidt for Classical {
-    fn from_shape<T>(shape: T) -> Result<Self>
+impl<A: GramSchmidtScalar> GramSchmidt for Classical<A> {
+    type Scalar = A;
+
+    const STABILITY: StabilityClass = StabilityClass::Low;
+    const COST_FACTOR: f64 = 1.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
         where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
     {
         // Unfortunately we cannot check the shape itself to see if it's
@@ -44,45 +94,510 @@ impl GramSchmidt for Classical {
             Some(layout) => layout,
             None => Err(Error::NonContiguous)?,
         };
+        check_shape(q.dim())?;
 
         let (_, n_cols) = q.dim();
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
         let r = Array2::zeros(
-            (n_cols, n_cols).set_f(memory_layout == cblas::Layout::ColumnMajor)
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
         );
 
         Ok(Self {
             q,
             r,
             memory_layout,
+            r_layout,
+            pinned: 0,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
+            accept_any_layout: false,
         })
     }
 
+    fn from_parts(q: Array2<A>, r: Array2<A>) -> Result<Self> {
+        let (memory_layout, r_layout) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            r_layout,
+            pinned: 0,
+            sign_flipped,
+            computed: false,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
+            accept_any_layout: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<A>(self.q.rows(), self.q.cols()))
+    }
+
     fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
-        where S: Data<Elem = f64>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, 0, None, |_, _| true).map(|_| ())
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, next_col, Some(deadline), |_, _| true)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, start, None, |_, _| true).map(|_| ())
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: FnMut(usize, A::Real) -> bool
+    {
+        self.compute_from(a, 0, None, observe)
+    }
+
+    fn q(&self) -> &Array2<A> {
+        &self.q
+    }
+
+    fn r(&self) -> &Array2<A> {
+        &self.r
+    }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(self.pinned, &self.sign_flipped, |_| false)
+    }
+
+    fn last_flops(&self) -> u64 {
+        last_flops(self.computed, self.q.rows(), &self.provenance(), Self::COST_FACTOR)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = A>,
+              T: Data<Elem = A>
+    {
+        givens::low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        givens::remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        if index < self.pinned {
+            self.pinned -= 1;
+        }
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+    }
+}
+
+impl<A: GramSchmidtScalar> Classical<A> {
+    /// Sets the number of leading columns which are pinned.
+    ///
+    /// The columns `0..pinned` of the matrices passed to `compute` are then taken to be an
+    /// already orthonormal set: they are copied into Q unchanged, and only the remaining
+    /// columns are orthonormalized against them and each other. The corresponding block of R is
+    /// the identity. Computing returns [`Error::NotOrthonormal`] if the pinned columns deviate
+    /// from orthonormality by more than `1e-10`.
+    ///
+    /// Returns [`Error::TooManyPinned`] if `pinned` exceeds the number of columns, leaving the
+    /// number of pinned columns unchanged.
+    ///
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    /// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+    pub fn set_pinned(&mut self, pinned: usize) -> Result<()> {
+        check_pinned(self.q.cols(), pinned)?;
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// Returns the number of leading columns which are pinned. See [`set_pinned`] for details.
+    ///
+    /// [`set_pinned`]: #method.set_pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    /// Sets the threshold below which the input is considered to be orthonormal already, so
+    /// that the projections are skipped.
+    ///
+    /// If the loss of orthogonality `‖I - AᵀA‖_F` of the matrix A passed to [`compute`] is
+    /// below `threshold`, Q is set to the normalized columns of A and R to the diagonal matrix of
+    /// their norms. This is much cheaper than a full factorization, e.g. when restoring the
+    /// orthonormality of a slowly drifting basis, but the resulting Q is only orthonormal up to
+    /// about `threshold`. Otherwise, the factorization proceeds as usual. The shortcut is never
+    /// taken when columns are pinned, or when a computation is resumed with `continue_from`. The
+    /// default, `0.0`, disables it.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    pub fn set_orthonormal_threshold(&mut self, threshold: f64) {
+        self.orthonormal_threshold = threshold;
+    }
+
+    /// Returns the threshold below which the input is considered to be orthonormal already. See
+    /// [`set_orthonormal_threshold`] for details.
+    ///
+    /// [`set_orthonormal_threshold`]: #method.set_orthonormal_threshold
+    pub fn orthonormal_threshold(&self) -> f64 {
+        self.orthonormal_threshold
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped.
+    ///
+    /// By default, [`compute`] scans the input once before factorizing it, and returns
+    /// [`Error::NonFinite`] for the first NaN or infinite entry, which would otherwise silently
+    /// propagate into Q and R. Skipping the scan saves a pass over the input in hot loops whose
+    /// inputs are known to be finite. Resuming a computation with `continue_from` never scans.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Reserves the memory for a QR decomposition like [`GramSchmidt::from_shape`], but accepts
+    /// matrices in either memory layout in `compute`, instead of returning
+    /// [`Error::IncompatibleLayouts`] for the layout opposite to the one of `shape`.
+    ///
+    /// The blas routines then read the columns of the input with the strides of its own layout, so
+    /// that an input in the other layout does not have to be copied.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::IncompatibleLayouts`]: enum.Error.html#variant.IncompatibleLayouts
+    pub fn from_shape_accepting_any_layout<T>(shape: T) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
     {
-        use cblas::Layout::*;
+        let mut cgs = Self::from_shape(shape)?;
+        cgs.accept_any_layout = true;
+        Ok(cgs)
+    }
+
+    /// Returns whether matrices in either memory layout are accepted. See
+    /// [`from_shape_accepting_any_layout`] for details.
+    ///
+    /// [`from_shape_accepting_any_layout`]: #method.from_shape_accepting_any_layout
+    pub fn accepts_any_layout(&self) -> bool {
+        self.accept_any_layout
+    }
+
+    /// Resizes the factorization for matrices of a new `shape` like [`GramSchmidt::from_shape`],
+    /// but reuses the memory of Q and R where possible, e.g. to factorize a stream of matrices of
+    /// varying sizes without allocating for every one of them.
+    ///
+    /// The memory layout is derived from `shape` anew, and R keeps being stored in the same or the
+    /// opposite layout. All settings are kept, except that the number of pinned columns is reduced
+    /// to the new number of columns if necessary. Returns [`Error::Underdetermined`] for a wide
+    /// shape and [`Error::InvalidShape`] for one without rows or columns, leaving the
+    /// factorization unchanged.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn reset_shape<T>(&mut self, shape: T) -> Result<()>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let transpose_r = self.r_layout != self.memory_layout;
+        let (memory_layout, r_layout) = reset_factors(&mut self.q, &mut self.r, shape, transpose_r)?;
+        self.memory_layout = memory_layout;
+        self.r_layout = r_layout;
+        self.sign_flipped.clear();
+        self.sign_flipped.resize(self.q.cols(), false);
+        self.computed = false;
+        self.pinned = self.pinned.min(self.q.cols());
+        Ok(())
+    }
+
+    /// Computes the QR decomposition of the matrix `a` like [`GramSchmidt::compute`], but takes
+    /// ownership of `a` and orthonormalizes it in place, instead of copying it into Q first.
+    ///
+    /// The memory of `a` becomes the memory of Q, so that `a` has to be contiguous and to have the
+    /// layout of Q, even if any layout is accepted by `compute`. Otherwise,
+    /// [`Error::IncompatibleLayouts`] or [`Error::NonContiguous`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [1.0, 2.0]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute_owned(a.clone())?;
+    /// assert!(a.all_close(&cgs.q().dot(cgs.r()), 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::IncompatibleLayouts`]: enum.Error.html#variant.IncompatibleLayouts
+    /// [`Error::NonContiguous`]: enum.Error.html#variant.NonContiguous
+    pub fn compute_owned(&mut self, a: Array2<A>) -> Result<()> {
         use Error::*;
 
-        assert_eq!(a.shape(), self.q.shape());
+        if a.dim() != self.q.dim() {
+            Err(ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+        match get_layout(&a) {
+            Some(layout) if layout == self.memory_layout => {},
+            Some(_) => Err(IncompatibleLayouts)?,
+            None => Err(NonContiguous)?,
+        }
+        if !self.skip_finite_check {
+            check_finite(&a)?;
+        }
+
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+        if self.pinned == 0
+            && orthonormal_shortcut(&mut self.q, &mut self.r, &a, self.orthonormal_threshold)
+        {
+            return Ok(());
+        }
+        // The pinned columns stay unchanged in `a`, which becomes Q, so that only R is set up.
+        if self.pinned > 0 {
+            pin_columns(&mut self.q, &mut self.r, &a, self.pinned)?;
+        }
 
-        let (n_rows, n_cols) = self.q.dim();
+        // The norms of the columns are overwritten by the orthonormalization, and are thus only
+        // computed beforehand if the rank check needs them.
+        let column_norms: Vec<_> = if self.tolerance() > 0.0 {
+            a.gencolumns().into_iter().map(|column| normalization(&column)).collect()
+        } else {
+            Vec::new()
+        };
+
+        self.q = a;
+        self.orthonormalize_columns(None::<&Array2<A>>, self.pinned, None, |i| column_norms[i], |_, _| true)?;
+        Ok(())
+    }
+
+    /// Computes only the R factor of the QR decomposition of the matrix `a`, e.g. for statistics
+    /// which need `AᵀA = RᵀR` but not Q.
+    ///
+    /// Like [`compute_owned`], `a` is orthonormalized in place, so that Q only ever occupies the
+    /// memory of `a`, which is freed before returning. Only the `n×n` matrix R is retained. Unlike
+    /// [`r_from_gram`], the Gram matrix `AᵀA` is never formed, so that R is as accurate as the one
+    /// computed by [`GramSchmidt::compute`]; both agree exactly. R is stored in the layout of `a`.
+    ///
+    /// Returns an error if `a` is wider than tall, not contiguous, contains entries which are not
+    /// finite, or if a column vanishes after removing its projections onto the previous ones.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::Classical;
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [1.0, 2.0]]);
+    /// let r = Classical::compute_r_only(a.clone())?;
+    /// assert!(r.t().dot(&r).all_close(&a.t().dot(&a), 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`compute_owned`]: #method.compute_owned
+    /// [`r_from_gram`]: fn.r_from_gram.html
+    /// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+    pub fn compute_r_only(a: Array2<A>) -> Result<Array2<A>> {
+        let n_cols = a.cols();
+        let column_major = get_layout(&a) == Some(blas::Layout::ColumnMajor);
+        let r = Array2::zeros((n_cols, n_cols).set_f(column_major));
+        let mut cgs = Self::from_parts(a, r)?;
+        check_finite(&cgs.q)?;
+
+        // The columns are orthonormalized in place, so that the norms of the original columns for
+        // the rank check have to be taken beforehand. This costs as much as a single projection.
+        let norms: Vec<_> = cgs.q.gencolumns().into_iter().map(|column| normalization(&column)).collect();
+        cgs.orthonormalize_columns(None::<&Array2<A>>, 0, None, |i| norms[i], |_, _| true)?;
+        Ok(cgs.r)
+    }
+
+    /// Restores whether matrices in either memory layout are accepted, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_accept_any_layout(&mut self, accept_any_layout: bool) {
+        self.accept_any_layout = accept_any_layout;
+    }
 
-        let a_slice = match (self.memory_layout, as_slice_with_layout(a)) {
-            (a, Some((_, b))) if a != b => Err(IncompatibleLayouts)?,
-            (_, Some((a_slice, _))) => a_slice,
+    /// Restores which columns had their signs flipped, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_sign_flipped(&mut self, sign_flipped: Vec<bool>) {
+        self.sign_flipped = sign_flipped;
+    }
+
+    /// Orthonormalizes the columns `start..` of `a`, assuming that the columns `0..start` have
+    /// already been processed. Returns early if the optional `deadline` has passed, after at least
+    /// one column has been processed, or once `observe` returns `false` for a processed column.
+    fn compute_from<S, O>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        start: usize,
+        deadline: Option<Instant>,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        use Error::*;
+
+        if a.dim() != self.q.dim() {
+            Err(ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        match (self.memory_layout, as_slice_with_layout(a)) {
+            (a, Some((_, b))) if a != b && !self.accept_any_layout => Err(IncompatibleLayouts)?,
+            (_, Some(_)) => {},
+            (_, None) if is_broadcast(a) => Err(BroadcastInput)?,
             (_, None) => Err(NonContiguous)?,
         };
 
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        // The signs of the columns computed anew are determined by the procedure again.
+        clear_sign_flips(&mut self.sign_flipped, if start < self.pinned { 0 } else { start });
+        self.computed = true;
+
+        if start == 0 && self.pinned == 0
+            && orthonormal_shortcut(&mut self.q, &mut self.r, a, self.orthonormal_threshold)
+        {
+            let status = observe_columns(&self.r, 0..self.q.cols(), &mut observe);
+            return Ok(status.unwrap_or(ComputeStatus::Done));
+        }
+
+        // Pinned columns are not orthonormalized, but only copied.
+        let start = if start < self.pinned {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+            if let Some(status) = observe_columns(&self.r, 0..self.pinned, &mut observe) {
+                return Ok(status);
+            }
+            self.pinned
+        } else {
+            start
+        };
+
+        self.orthonormalize_columns(Some(a), start, deadline, |i| normalization(&a.column(i)), observe)
+    }
+
+    /// Orthonormalizes the columns `start..` of Q, after copying each of them from `input`. If
+    /// there is no `input`, Q has to hold the input matrix already, which is then orthonormalized
+    /// in place. `column_norm` returns the norm of a column of the input for the rank check, and
+    /// `observe` is called with every orthonormalized column as in [`compute_observed`].
+    ///
+    /// The input has to be contiguous; it has to have the layout of Q unless any layout is
+    /// accepted.
+    ///
+    /// [`compute_observed`]: trait.GramSchmidt.html#tymethod.compute_observed
+    fn orthonormalize_columns<S, F, O>(
+        &mut self,
+        input: Option<&ArrayBase<S, Ix2>>,
+        start: usize,
+        deadline: Option<Instant>,
+        column_norm: F,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: Fn(usize) -> A::Real,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        use blas::Layout::*;
+
+        let (n_rows, n_cols) = self.q.dim();
+
+        // NOTE: This unwrap is safe, because the callers have made sure that the input is
+        // contiguous.
+        let input = input.map(|a| (a, as_slice_with_layout(a).unwrap()));
+        let a_layout = input.map_or(self.memory_layout, |(_, (_, a_layout))| a_layout);
+
         // leading_dim: the number of elements in the leading dimension
         // next_elem: how many elements to jump to get to the next element in a column
-        // next_col: how many elements in the array to jump to get to the next column
-        let (leading_dim, next_elem, next_col) = match self.memory_layout {
-            ColumnMajor => (n_rows as i32, 1, n_rows),
-            RowMajor => (n_cols as i32, n_cols as i32, 1),
+        let (leading_dim, next_elem) = match self.memory_layout {
+            ColumnMajor => (n_rows as i32, 1),
+            RowMajor => (n_cols as i32, n_cols as i32),
         };
 
-        for i in 0..n_cols {
-            self.q.column_mut(i).assign(&a.column(i));
+        // a_next_elem: the same as next_elem, but for the matrix a, whose layout may differ from
+        // the one of q if any layout is accepted.
+        // a_next_col: how many elements in a to jump to get to the next column
+        let (a_next_elem, a_next_col) = match a_layout {
+            ColumnMajor => (1, n_rows),
+            RowMajor => (n_cols as i32, 1),
+        };
+
+        // r_next_elem, r_next_col: the same as a_next_elem and a_next_col, but for the matrix r,
+        // whose layout is allowed to differ from the one of q.
+        let (r_next_elem, r_next_col) = match self.r_layout {
+            ColumnMajor => (1, n_cols),
+            RowMajor => (n_cols as i32, 1),
+        };
+
+        for i in start..n_cols {
+            if i > start && deadline_passed(deadline) {
+                return Ok(ComputeStatus::InProgress { next_col: i });
+            }
+
+            if let Some((a, _)) = input {
+                self.q.column_mut(i).assign(&a.column(i));
+            }
 
             // The unsafe blocks below are because we need several overlapping slices into the
             // q matrix. The mutable `q_column` is the i-th orthogonal vector which is currently
@@ -103,7 +618,7 @@ impl GramSchmidt for Classical {
                 ColumnMajor => {
                     let offset = n_rows * i;
                     unsafe {
-                        slice::from_raw_parts_mut(q_ptr.offset(offset as isize), q_len - offset)
+                        slice::from_raw_parts_mut(q_ptr.add(offset), q_len - offset)
                     }
                 },
 
@@ -117,7 +632,11 @@ impl GramSchmidt for Classical {
             };
 
             if i > 0 {
-                let a_column = &a_slice[next_col * i..];
+                // Without an input, the i-th column of Q itself is the column of the input.
+                let a_column = match input {
+                    Some((_, (a_slice, _))) => &a_slice[a_next_col * i..],
+                    None => &q_matrix[a_next_col * i..],
+                };
 
                 // NOTE: This unwrap is save, because we have made sure at creation that r_slice is
                 // contiguous.
@@ -126,40 +645,40 @@ impl GramSchmidt for Classical {
                 // end of the loop, which invalidates the mutable borrow. We thus have to pull the
                 // slice definition into the loop.
                 let r_slice = self.r.as_slice_memory_order_mut().unwrap();
-                let r_column = &mut r_slice[next_col * i..];
+                let r_column = &mut r_slice[r_next_col * i..];
 
                 // Calculate the product R_(i) = Q^T·A_(i), where A_(i) is the i-th column of the matrix A,
                 // and R_(i) is the i-th column of matrix R.
                 unsafe {
-                    cblas::dgemv(
+                    A::gemv(
                         self.memory_layout,
-                        cblas::Transpose::Ordinary,
+                        A::ADJOINT,
                         n_rows as i32,
                         i as i32,
-                        1.0,
+                        A::one(),
                         q_matrix,
                         leading_dim,
                         a_column,
-                        next_elem,
-                        0.0,
+                        a_next_elem,
+                        A::zero(),
                         r_column,
-                        next_elem,
+                        r_next_elem,
                     );
 
                     // Calculate Q_(i) = A_(i) - Q · R_(i) = A_(i) - Q · (Q^T · A_(i)), where
                     // Q · (Q^T ·A_(i)) is the projection of the i-th column of A onto the already
                     // orthonormalized basis vectors Q_{0..i}.
-                    cblas::dgemv(
+                    A::gemv(
                         self.memory_layout,
-                        cblas::Transpose::None,
+                        blas::Transpose::None,
                         n_rows as i32,
                         i as i32,
-                        -1.0,
+                        -A::one(),
                         q_matrix,
                         leading_dim,
                         r_column,
-                        next_elem,
-                        1.0,
+                        r_next_elem,
+                        A::one(),
                         q_column,
                         next_elem,
                     );
@@ -167,25 +686,448 @@ impl GramSchmidt for Classical {
             };
 
             let norm = unsafe {
-                    cblas::dnrm2(n_rows as i32, q_column, next_elem)
+                    A::nrm2(n_rows as i32, q_column, next_elem)
             };
 
+            check_rank(i, norm, self.tolerance(), || column_norm(i))?;
+
+            // NOTE: The diagonal of R has to be the norm of the orthogonalized column. Computing
+            // it as the projection A_(i)ᵀ·Q_(i) instead is only equivalent if Q_(i) is exactly
+            // orthogonal to the previous columns, and breaks A = QR if orthogonality is lost.
             let mut v = self.q.column_mut(i);
-            v /= norm;
-            self.r[(i,i)] = a.column(i).dot(&v);
+            v /= A::from_real(norm);
+            self.r[(i,i)] = A::from_real(norm);
+
+            if let Some(status) = observe_columns(&self.r, i..i + 1, &mut observe) {
+                return Ok(status);
+            }
+        }
+
+        Ok(ComputeStatus::Done)
+    }
+}
+
+#[cfg(feature = "sprs")]
+impl Classical<f64> {
+    /// Computes a classical Gram Schmidt QR decomposition of the sparse matrix `a`.
+    ///
+    /// Each column of `a` is densified into the corresponding column of Q one at a time, so that
+    /// the dense input matrix is never materialized. This suits sparse matrices with few enough
+    /// columns for dense Q and R factors. A matrix in CSR storage is converted to CSC first.
+    /// Pinned columns are copied into Q like in [`compute`], and only the remaining columns are
+    /// orthonormalized.
+    ///
+    /// Returns an error if the shape of `a` differs from the configured one, [`Error::NonFinite`]
+    /// for the first stored entry which is NaN or infinite, and [`Error::NotOrthonormal`] if the
+    /// pinned columns are not orthonormal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::ShapeBuilder;
+    /// use sprs::TriMat;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let mut triplets = TriMat::new((100, 2));
+    /// triplets.add_triplet(0, 0, 3.0);
+    /// triplets.add_triplet(99, 0, 4.0);
+    /// triplets.add_triplet(0, 1, 1.0);
+    /// triplets.add_triplet(50, 1, 1.0);
+    /// let a = triplets.to_csc();
+    ///
+    /// let mut cgs = Classical::from_shape((100, 2).f())?;
+    /// cgs.compute_sparse(&a)?;
+    /// assert!(a.to_dense().all_close(&cgs.q().dot(cgs.r()), 1e-15));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    pub fn compute_sparse(&mut self, a: &sprs::CsMat<f64>) -> Result<()> {
+        if a.shape() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.shape() })?;
+        }
+
+        let converted;
+        let a = if a.is_csc() {
+            a
+        } else {
+            converted = a.to_csc();
+            &converted
+        };
+
+        // Only the stored entries can be non finite.
+        if !self.skip_finite_check {
+            for (col, a_column) in a.outer_iterator().enumerate() {
+                if let Some((row, _)) = a_column.iter().find(|(_, x)| !x.is_finite()) {
+                    Err(Error::NonFinite { row, col })?;
+                }
+            }
+        }
+
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+        // Only the pinned columns are densified to check that they are orthonormal.
+        if self.pinned > 0 {
+            let mut basis = Array2::zeros((a.rows(), self.pinned));
+            for (mut basis_column, a_column) in basis.gencolumns_mut().into_iter().zip(a.outer_iterator()) {
+                for (row, &x) in a_column.iter() {
+                    basis_column[row] = x;
+                }
+            }
+            pin_columns(&mut self.q, &mut self.r, &basis, self.pinned)?;
+        }
+
+        for mut r_column in self.r.gencolumns_mut().into_iter().skip(self.pinned) {
+            r_column.fill(0.0);
+        }
+        let tolerance = self.tolerance();
+        for (i, a_column) in a.outer_iterator().enumerate().skip(self.pinned) {
+            let (q_done, mut q_todo) = self.q.view_mut().split_at(Axis(1), i);
+            let mut q_column = q_todo.column_mut(0);
+            q_column.fill(0.0);
+            for (row, &x) in a_column.iter() {
+                q_column[row] = x;
+            }
+
+            let projections = q_done.t().dot(&q_column);
+            q_column.scaled_add(-1.0, &q_done.dot(&projections));
+            let norm = normalization(&q_column);
+            check_rank(i, norm, tolerance, || a_column.l2_norm())?;
+            q_column /= norm;
+
+            let mut r_column = self.r.column_mut(i);
+            for (r_ji, &projection) in r_column.iter_mut().zip(&projections) {
+                *r_ji = projection;
+            }
+            r_column[i] = norm;
         }
 
         Ok(())
     }
+}
 
-    fn q(&self) -> &Array2<f64> {
-        &self.q
+/// Prints the dimensions and the layout of the factorization followed by Q and R. The precision
+/// of the entries can be set with the formatter, e.g. `{:.3}`.
+impl<A: GramSchmidtScalar + fmt::Display> fmt::Display for Classical<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_factorization(f, "Classical", self.memory_layout, &self.q, &self.r)
     }
+}
 
-    fn r(&self) -> &Array2<f64> {
-        &self.r
+#[cfg(feature = "rayon")]
+impl<A: GramSchmidtScalar> crate::ParallelGramSchmidt for Classical<A> {
+    fn par_compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+        if is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        if !self.skip_finite_check {
+            check_finite(a)?;
+        }
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+        if self.pinned > 0 {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+        }
+
+        let tolerance = self.tolerance();
+        crate::parallel::compute_columns(&mut self.q, &mut self.r, a, self.pinned, tolerance, |_, _, _| false)
+    }
+}
+
+#[cfg(all(test, feature = "sprs"))]
+mod sparse_tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use sprs::CsMat;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn sparse_equals_dense() {
+        // Dropping the small entries leaves structural zeros in the sparse matrices.
+        let a = matrix(5, 4).mapv(|a_ij| if a_ij.abs() < 0.5 { 0.0 } else { a_ij });
+        let mut dense = Classical::from_matrix(&a).unwrap();
+        dense.compute(&a).unwrap();
+
+        let csr = CsMat::csr_from_dense(a.view(), 0.0);
+        let csc = CsMat::csc_from_dense(a.view(), 0.0);
+        for sparse in [csr, csc].iter() {
+            let mut cgs = Classical::from_matrix(&a).unwrap();
+            cgs.compute_sparse(sparse).unwrap();
+            assert!(cgs.q().all_close(dense.q(), 1e-14));
+            assert!(cgs.r().all_close(dense.r(), 1e-14));
+        }
+    }
+
+    #[test]
+    fn sparse_pinned_columns_are_kept() {
+        let mut a = matrix(5, 4);
+        a.column_mut(0).assign(&arr1(&[0.0, 0.0, 0.0, 0.0, 1.0]));
+        let mut dense = Classical::from_matrix(&a).unwrap();
+        dense.set_pinned(1).unwrap();
+        dense.compute(&a).unwrap();
+
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.set_pinned(1).unwrap();
+        cgs.compute_sparse(&CsMat::csc_from_dense(a.view(), 0.0)).unwrap();
+        assert_eq!(cgs.q().column(0), a.column(0));
+        assert!(cgs.q().all_close(dense.q(), 1e-14));
+        assert!(cgs.r().all_close(dense.r(), 1e-14));
+
+        let q = cgs.q().clone();
+        match cgs.compute_sparse(&CsMat::csc_from_dense(matrix(5, 4).view(), 0.0)) {
+            Err(Error::NotOrthonormal { column: 0 }) => {},
+            other => panic!("expected a non-orthonormal pinned column, got {:?}", other),
+        }
+        assert_eq!(cgs.q(), &q);
+    }
+
+    #[test]
+    fn sparse_non_finite_input_is_rejected() {
+        let mut a = matrix(5, 4);
+        a[(4, 2)] = f64::NEG_INFINITY;
+        a[(1, 3)] = f64::NAN;
+        let csr = CsMat::csr_from_dense(a.view(), 0.0);
+        let csc = CsMat::csc_from_dense(a.view(), 0.0);
+        for sparse in [csr, csc].iter() {
+            let mut cgs = Classical::from_matrix(&a).unwrap();
+            match cgs.compute_sparse(sparse) {
+                Err(Error::NonFinite { row: 4, col: 2 }) => {},
+                other => panic!("expected a non finite entry, got {:?}", other),
+            }
+
+            cgs.set_skip_finite_check(true);
+            if let Err(Error::NonFinite { .. }) = cgs.compute_sparse(sparse) {
+                panic!("the check was not skipped");
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_shape_mismatch_is_rejected() {
+        let mut cgs = Classical::from_shape((4, 4)).unwrap();
+        let sparse = CsMat::csc_from_dense(matrix(5, 4).view(), 0.0);
+        match cgs.compute_sparse(&sparse) {
+            Err(Error::ShapeMismatch { expected: (4, 4), found: (5, 4) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn mixed_layouts_are_accepted() {
+        crate::utils::check_mixed_layouts(Classical::<f64>::from_shape_accepting_any_layout);
+    }
+}
+
+#[cfg(test)]
+mod stability_tests {
+    extern crate openblas_src;
+
+    use crate::utils::{
+        check_conditioned_matrices,
+        check_hilbert_orthogonality,
+        check_identity_plus_noise_orthogonality,
+        hilbert,
+        orthogonality_loss,
+    };
+    use super::*;
+
+    // Classical Gram Schmidt loses orthogonality with the square of the condition number, and
+    // breaks down completely for n ≥ 8.
+    #[test]
+    fn hilbert_orthogonality() {
+        check_hilbert_orthogonality::<Classical<f64>>(&[(6, 1e-4)]);
+    }
+
+    #[test]
+    fn identity_plus_noise_orthogonality() {
+        check_identity_plus_noise_orthogonality::<Classical<f64>, _>(|kappa| 10.0 * f64::EPSILON * kappa * kappa);
+    }
+
+    #[test]
+    fn random_conditioned_matrices() {
+        check_conditioned_matrices::<Classical<f64>, _>(1e-12, |kappa| 10.0 * f64::EPSILON * kappa * kappa);
+    }
+
+    // Beyond n = 6, the columns of Q obtained from the Hilbert matrix are no longer even close to
+    // orthogonal, although Q·R still reconstructs it.
+    #[test]
+    fn hilbert_breaks_down() {
+        for &n in [8, 10].iter() {
+            let a = hilbert(n);
+            let mut f_a = Array2::zeros((n, n).f());
+            f_a.assign(&a);
+
+            for a in [a, f_a].iter() {
+                let mut cgs = Classical::from_matrix(a).unwrap();
+                cgs.compute(a).unwrap();
+                let loss = orthogonality_loss(cgs.q());
+                assert!(loss > 1.0, "n = {}: loss of orthogonality {:e} does not exceed 1", n, loss);
+                assert!(a.all_close(&cgs.q().dot(cgs.r()), 1e-14));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod owned_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    fn matrices() -> (Array2<f64>, Array2<f64>) {
+        let a = arr2(
+            &[[1.0, 2.0, 0.5, 0.0],
+              [0.0, 0.3, 1.0, 0.0],
+              [0.0, 1.0, 0.7, 2.0],
+              [0.0, 0.0, 4.0, 3.0],
+              [0.0, 0.5, 0.0, 1.0]]
+        );
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        (a, f_a)
+    }
+
+    #[test]
+    fn owned_matches_compute() {
+        let (a, f_a) = matrices();
+        for input in [a, f_a].iter() {
+            // The first column is a unit vector and can thus be pinned.
+            for &pinned in &[0, 1] {
+                let mut expected = Classical::from_matrix(input).unwrap();
+                expected.set_pinned(pinned).unwrap();
+                expected.compute(input).unwrap();
+
+                let mut cgs = Classical::from_matrix(input).unwrap();
+                cgs.set_pinned(pinned).unwrap();
+                cgs.compute_owned(input.clone()).unwrap();
+                assert_eq!(cgs.q(), expected.q());
+                assert_eq!(cgs.r(), expected.r());
+                assert_eq!(get_layout(cgs.q()), get_layout(input));
+            }
+        }
+    }
+
+    #[test]
+    fn owned_rank_check_uses_original_norms() {
+        let (mut a, _) = matrices();
+        let scaled = &a.column(0) * 3.0;
+        a.column_mut(1).assign(&scaled);
+
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.set_tolerance(1e-10);
+        match cgs.compute_owned(a) {
+            Err(Error::RankDeficient { column: 1 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn r_only_matches_compute() {
+        let small = arr2(
+            &[[2.0, 0.5, 0.0, 0.0],
+              [0.0, 0.3, 0.0, 0.0],
+              [0.0, 1.0, 0.7, 0.0],
+              [0.0, 0.0, 0.0, 3.0]]
+        );
+        let mut f_small = Array2::zeros(small.dim().f());
+        f_small.assign(&small);
+        let (a, f_a) = matrices();
+
+        for input in [small, f_small, a, f_a].iter() {
+            let mut cgs = Classical::from_matrix(input).unwrap();
+            cgs.compute(input).unwrap();
+            let r = Classical::compute_r_only(input.clone()).unwrap();
+            assert_eq!(&r, cgs.r());
+            assert_eq!(get_layout(&r), get_layout(cgs.r()));
+        }
+
+        let mut wide = Array2::zeros((2, 3));
+        wide[(0, 0)] = 1.0;
+        match Classical::compute_r_only(wide) {
+            Err(Error::Underdetermined { rows: 2, cols: 3 }) => {},
+            other => panic!("expected an underdetermined matrix, got {:?}", other),
+        }
+
+        let (mut dependent, _) = matrices();
+        let first = dependent.column(0).to_owned();
+        dependent.column_mut(2).assign(&first);
+        match Classical::compute_r_only(dependent) {
+            Err(Error::RankDeficient { column: 2 }) => {},
+            other => panic!("expected a rank deficient matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn owned_layout_mismatch_is_rejected() {
+        let (a, f_a) = matrices();
+        let mut strict = Classical::from_matrix(&a).unwrap();
+        let mut any_layout = Classical::from_shape_accepting_any_layout(a.dim()).unwrap();
+        for cgs in [&mut strict, &mut any_layout].iter_mut() {
+            match cgs.compute_owned(f_a.clone()) {
+                Err(Error::IncompatibleLayouts) => {},
+                other => panic!("expected incompatible layouts, got {:?}", other),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn display_shows_dimensions_layout_and_factors() {
+        let a = arr2(&[[3.0, 1.0],
+                       [4.0, 2.0],
+                       [0.0, 5.0]]);
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.compute(&a).unwrap();
+        let displayed = format!("{:.2}", cgs);
+        assert!(displayed.starts_with("Classical factorization of a 3×2 matrix in row major layout\nQ =\n"));
+        assert!(displayed.contains("\nR =\n  5.00  2.20\n  0.00  5.02"));
+    }
+}
+
+#[cfg(test)]
+mod orthonormal_shortcut_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn orthonormal_shortcut_is_opt_in() {
+        assert_eq!(Classical::<f64>::from_shape((8, 6)).unwrap().orthonormal_threshold(), 0.0);
+        crate::utils::check_orthonormal_shortcut(Classical::set_orthonormal_threshold);
     }
 }
 
 #[cfg(test)]
-generate_tests!(Classical, 1e-12);
+generate_tests!(
+    Classical,
+    1e-12,
+    precisions: [double: f64 => 1e-12, single: f32 => 1e-4]
+);
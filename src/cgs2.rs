@@ -1,20 +1,57 @@
-use cblas;
 use ndarray::{
     Data,
     Dim,
     Ix,
     ShapeBuilder,
+    s,
 };
 use ndarray::prelude::*;
+use num_traits::{
+    NumCast,
+    One,
+    Zero,
+};
+use std::fmt;
+use std::mem;
 use std::slice;
+use std::time::Instant;
 
 use crate::{
+    ColumnProvenance,
+    ComputeStatus,
     Error,
     GramSchmidt,
+    GramSchmidtScalar,
+    Layout,
     Result,
+    StabilityClass,
+    blas,
+    givens,
     utils::{
         as_slice_with_layout,
+        check_finite,
+        check_pinned,
+        check_rank,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        deadline_passed,
+        default_tolerance,
+        display_factorization,
+        force_positive_diagonal,
         get_layout,
+        is_broadcast,
+        last_flops,
+        normalization,
+        observe_columns,
+        orthonormal_shortcut,
+        parts_layouts,
+        pin_columns,
+        provenance,
+        rescale_columns,
+        reuse_buffer,
+        reset_factors,
+        transpose_layout,
     }
 };
 
@@ -25,18 +62,101 @@ use crate::{
 ///
 /// Use this struct via the [`GramSchmidt` trait].
 ///
+/// By default the second orthogonalization is performed unconditionally. Setting a finite
+/// tolerance via [`set_reorthogonalization_tolerance`] only performs it if the first one reduced
+/// the norm of a column by more than that factor.
+///
 /// [Giraud et al.]: https://doi.org/10.1007/s00211-005-0615-4
 /// [`GramSchmidt` trait]: GramSchmidt
+/// [`set_reorthogonalization_tolerance`]: #method.set_reorthogonalization_tolerance
 #[derive(Clone, Debug)]
-pub struct Reorthogonalized {
-    q: Array2<f64>,
-    r: Array2<f64>,
-    work_vector: Array1<f64>,
-    memory_layout: cblas::Layout,
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    crate = "serde_crate",
+    try_from = "crate::serialization::ReorthogonalizedState<A>",
+    bound(serialize = "A: serde_crate::Serialize", deserialize = "A: GramSchmidtScalar + serde_crate::Deserialize<'de>"),
+))]
+pub struct Reorthogonalized<A = f64> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    work_vector: Array1<A>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::CblasLayout"))]
+    memory_layout: blas::Layout,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::CblasLayout"))]
+    r_layout: blas::Layout,
+    reorthogonalization_tolerance: f64,
+    pinned: usize,
+    tolerance: Option<f64>,
+    orthonormal_threshold: f64,
+    skip_finite_check: bool,
+    reorthogonalized: Vec<bool>,
+    sign_flipped: Vec<bool>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    computed: bool,
+    accept_any_layout: bool,
 }
 
-impl GramSchmidt for Reorthogonalized {
-    fn from_shape<T>(shape: T) -> Result<Self>
+impl<A: GramSchmidtScalar> Reorthogonalized<A> {
+    /// Reserves the memory for a QR decomposition like [`GramSchmidt::from_shape`], but uses the
+    /// externally owned `work_vector` as scratch space instead of allocating one.
+    ///
+    /// The work vector has to be contiguous and as long as the matrices to be factorized have
+    /// rows. Its contents are irrelevant and are overwritten by `compute`. Together with
+    /// [`into_workspace`], this allows to pass a single work vector from one factorization to the
+    /// next.
+    ///
+    /// Returns [`Error::LengthMismatch`] if the length of the work vector does not match the
+    /// number of rows, and [`Error::NonContiguous`] if it is not contiguous.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::{Array1, arr2};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let mut work_vector = Array1::zeros(3);
+    /// for shift in 1..4 {
+    ///     let a = arr2(&[[1.0, 0.0],
+    ///                    [1.0, 1.0],
+    ///                    [1.0, shift as f64]]);
+    ///     let mut cgs2 = Reorthogonalized::with_workspace(a.dim(), work_vector)?;
+    ///     cgs2.compute(&a)?;
+    ///     assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-14));
+    ///     work_vector = cgs2.into_workspace();
+    /// }
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`into_workspace`]: #method.into_workspace
+    /// [`Error::LengthMismatch`]: enum.Error.html#variant.LengthMismatch
+    /// [`Error::NonContiguous`]: enum.Error.html#variant.NonContiguous
+    pub fn with_workspace<T>(shape: T, work_vector: Array1<A>) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        Self::allocate(shape, false, Some(work_vector))
+    }
+
+    /// Returns the work vector, consuming `self`, e.g. to pass it on to the next factorization
+    /// via [`with_workspace`].
+    ///
+    /// [`with_workspace`]: #method.with_workspace
+    pub fn into_workspace(self) -> Array1<A> {
+        self.work_vector
+    }
+
+    /// Reserves the memory for a factorization like [`GramSchmidt::from_shape_with_r_transposed`],
+    /// using `work_vector` as the work vector if given.
+    ///
+    /// [`GramSchmidt::from_shape_with_r_transposed`]: trait.GramSchmidt.html#tymethod.from_shape_with_r_transposed
+    fn allocate<T>(shape: T, transpose_r: bool, work_vector: Option<Array1<A>>) -> Result<Self>
         where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
     {
         // Unfortunately we cannot check the shape itself to see if it's
@@ -47,185 +167,1087 @@ impl GramSchmidt for Reorthogonalized {
             Some(layout) => layout,
             None => Err(Error::NonContiguous)?,
         };
+        check_shape(q.dim())?;
         let (n_rows, n_cols) = q.dim();
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
         let r = Array2::zeros(
-            (n_cols, n_cols).set_f(memory_layout == cblas::Layout::ColumnMajor)
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
         );
 
         // Similarly to the layout, we don't have direct access to the array dimensions via
         // `Shape`, and thus need to go via `Dim::Pattern` of the already constructed arrays.
-        let work_vector = Array1::zeros(n_rows);
+        let work_vector = match work_vector {
+            Some(work_vector) if work_vector.len() != n_rows => {
+                Err(Error::LengthMismatch { expected: n_rows, found: work_vector.len() })?
+            },
+            // The blas calls expect a unit stride.
+            Some(work_vector) if work_vector.as_slice().is_none() => Err(Error::NonContiguous)?,
+            Some(work_vector) => work_vector,
+            None => Array1::zeros(n_rows),
+        };
+        let reorthogonalized = vec![false; n_cols];
 
         Ok(Self {
             q,
             r,
             work_vector,
             memory_layout,
+            r_layout,
+            reorthogonalization_tolerance: f64::INFINITY,
+            pinned: 0,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
+            reorthogonalized,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            accept_any_layout: false,
         })
     }
 
-    fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
-        where S: Data<Elem = f64>,
+    /// Sets the tolerance deciding whether a column is orthogonalized a second time.
+    ///
+    /// After the first orthogonalization of a column with norm `‖a‖` against the already
+    /// orthonormalized basis, the second orthogonalization is only performed if the norm of the
+    /// remaining vector `‖q‖` satisfies `‖q‖ < tol·‖a‖`. This is the criterion described by
+    /// [Giraud et al.]; a common choice is `tol = 1/√2`.
+    ///
+    /// The buffers of the struct are reused, so that `compute` can be called again with the new
+    /// tolerance without any allocation. The default, `f64::INFINITY`, always reorthogonalizes.
+    ///
+    /// [Giraud et al.]: https://doi.org/10.1007/s00211-005-0615-4
+    pub fn set_reorthogonalization_tolerance(&mut self, tol: f64) {
+        self.reorthogonalization_tolerance = tol;
+    }
+
+    /// Returns the tolerance deciding whether a column is orthogonalized a second time. See
+    /// [`set_reorthogonalization_tolerance`] for details.
+    ///
+    /// [`set_reorthogonalization_tolerance`]: #method.set_reorthogonalization_tolerance
+    pub fn reorthogonalization_tolerance(&self) -> f64 {
+        self.reorthogonalization_tolerance
+    }
+
+    /// Returns the number of columns which were orthogonalized a second time by the last
+    /// computation, which depends on the [`reorthogonalization_tolerance`]. The first column, and
+    /// pinned columns, are never reorthogonalized.
+    ///
+    /// [`reorthogonalization_tolerance`]: #method.set_reorthogonalization_tolerance
+    pub fn reorthogonalizations(&self) -> usize {
+        self.reorthogonalized.iter().filter(|&&reorthogonalized| reorthogonalized).count()
+    }
+
+    /// Sets the number of leading columns which are pinned.
+    ///
+    /// The columns `0..pinned` of the matrices passed to `compute` are then taken to be an
+    /// already orthonormal set: they are copied into Q unchanged, and only the remaining
+    /// columns are orthonormalized against them and each other. The corresponding block of R is
+    /// the identity. Computing returns [`Error::NotOrthonormal`] if the pinned columns deviate
+    /// from orthonormality by more than `1e-10`.
+    ///
+    /// Returns [`Error::TooManyPinned`] if `pinned` exceeds the number of columns, leaving the
+    /// number of pinned columns unchanged.
+    ///
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    /// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+    pub fn set_pinned(&mut self, pinned: usize) -> Result<()> {
+        check_pinned(self.q.cols(), pinned)?;
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// Returns the number of leading columns which are pinned. See [`set_pinned`] for details.
+    ///
+    /// [`set_pinned`]: #method.set_pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    /// Sets the threshold below which the input is considered to be orthonormal already, so
+    /// that the projections are skipped.
+    ///
+    /// If the loss of orthogonality `‖I - AᵀA‖_F` of the matrix A passed to [`compute`] is
+    /// below `threshold`, Q is set to the normalized columns of A and R to the diagonal matrix of
+    /// their norms. This is much cheaper than a full factorization, e.g. when restoring the
+    /// orthonormality of a slowly drifting basis, but the resulting Q is only orthonormal up to
+    /// about `threshold`. Otherwise, the factorization proceeds as usual. The shortcut is never
+    /// taken when columns are pinned, or when a computation is resumed with `continue_from`. The
+    /// default, `0.0`, disables it.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    pub fn set_orthonormal_threshold(&mut self, threshold: f64) {
+        self.orthonormal_threshold = threshold;
+    }
+
+    /// Returns the threshold below which the input is considered to be orthonormal already. See
+    /// [`set_orthonormal_threshold`] for details.
+    ///
+    /// [`set_orthonormal_threshold`]: #method.set_orthonormal_threshold
+    pub fn orthonormal_threshold(&self) -> f64 {
+        self.orthonormal_threshold
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped.
+    ///
+    /// By default, [`compute`] scans the input once before factorizing it, and returns
+    /// [`Error::NonFinite`] for the first NaN or infinite entry, which would otherwise silently
+    /// propagate into Q and R. Skipping the scan saves a pass over the input in hot loops whose
+    /// inputs are known to be finite. Resuming a computation with `continue_from` never scans.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Reserves the memory for a QR decomposition like [`GramSchmidt::from_shape`], but accepts
+    /// matrices in either memory layout in `compute`, instead of returning
+    /// [`Error::IncompatibleLayouts`] for the layout opposite to the one of `shape`.
+    ///
+    /// The columns of the input are copied into Q before they are orthonormalized, so that this
+    /// costs nothing but a strided copy for an input in the other layout.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::IncompatibleLayouts`]: enum.Error.html#variant.IncompatibleLayouts
+    pub fn from_shape_accepting_any_layout<T>(shape: T) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
     {
-        use cblas::Layout::*;
-        use Error::*;
+        let mut cgs2 = Self::from_shape(shape)?;
+        cgs2.accept_any_layout = true;
+        Ok(cgs2)
+    }
 
-        assert_eq!(a.shape(), self.q.shape());
+    /// Returns whether matrices in either memory layout are accepted. See
+    /// [`from_shape_accepting_any_layout`] for details.
+    ///
+    /// [`from_shape_accepting_any_layout`]: #method.from_shape_accepting_any_layout
+    pub fn accepts_any_layout(&self) -> bool {
+        self.accept_any_layout
+    }
 
+    /// Resizes the factorization for matrices of a new `shape` like [`GramSchmidt::from_shape`],
+    /// but reuses the memory of Q and R where possible, e.g. to factorize a stream of matrices of
+    /// varying sizes without allocating for every one of them.
+    ///
+    /// The memory layout is derived from `shape` anew, and R keeps being stored in the same or the
+    /// opposite layout. All settings are kept, except that the number of pinned columns is reduced
+    /// to the new number of columns if necessary. Returns [`Error::Underdetermined`] for a wide
+    /// shape and [`Error::InvalidShape`] for one without rows or columns, leaving the
+    /// factorization unchanged.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn reset_shape<T>(&mut self, shape: T) -> Result<()>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let transpose_r = self.r_layout != self.memory_layout;
+        let (memory_layout, r_layout) = reset_factors(&mut self.q, &mut self.r, shape, transpose_r)?;
+        self.memory_layout = memory_layout;
+        self.r_layout = r_layout;
         let (n_rows, n_cols) = self.q.dim();
+        reuse_buffer(&mut self.work_vector, Ix1(n_rows).into_shape());
+        self.reorthogonalized.clear();
+        self.reorthogonalized.resize(n_cols, false);
+        self.sign_flipped.clear();
+        self.sign_flipped.resize(self.q.cols(), false);
+        self.computed = false;
+        self.pinned = self.pinned.min(self.q.cols());
+        Ok(())
+    }
+
+    /// Restores whether matrices in either memory layout are accepted, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_accept_any_layout(&mut self, accept_any_layout: bool) {
+        self.accept_any_layout = accept_any_layout;
+    }
+
+    /// Restores which columns were orthogonalized a second time, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_reorthogonalized(&mut self, reorthogonalized: Vec<bool>) {
+        self.reorthogonalized = reorthogonalized;
+    }
+
+    /// Restores which columns had their signs flipped, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_sign_flipped(&mut self, sign_flipped: Vec<bool>) {
+        self.sign_flipped = sign_flipped;
+    }
+
+    /// Creates a factorization without any columns for vectors with `n_rows` entries in the
+    /// given memory layout, to which columns are appended by [`push_column`].
+    ///
+    /// Unlike [`GramSchmidt::from_shape`], which rejects shapes without columns, this allows to
+    /// build up a basis from scratch.
+    ///
+    /// Returns [`Error::InvalidShape`] if `n_rows` is zero.
+    ///
+    /// [`push_column`]: #method.push_column
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn empty(n_rows: usize, layout: Layout) -> Result<Self> {
+        if n_rows == 0 {
+            Err(Error::InvalidShape { rows: 0, cols: 0 })?;
+        }
+        let column_major = layout == Layout::ColumnMajor;
+        Self::from_parts(
+            Array2::zeros((n_rows, 0).set_f(column_major)),
+            Array2::zeros((0, 0).set_f(column_major)),
+        )
+    }
 
-        let a_slice = match (self.memory_layout, as_slice_with_layout(&a)) {
-            (a, Some((_, b))) if a != b => Err(IncompatibleLayouts)?,
-            (_, Some((a_slice, _))) => a_slice,
+    /// Extends the orthonormal columns of the contiguous matrix `q` to an orthonormal basis of the
+    /// whole space, returning a square matrix in the layout of `q` whose leading columns are `q`.
+    ///
+    /// The unit vectors are orthonormalized against the columns found so far one after the other,
+    /// skipping those whose norm after removing their projections is at most `1/(2√m)` for `m`
+    /// rows, until the basis is complete. Since the squared remaining norms of all `m` unit
+    /// vectors add up to the number of missing columns, and only shrink while the basis grows, a
+    /// unit vector above the threshold is always left as long as columns are missing.
+    pub(crate) fn complete_basis(q: &Array2<A>) -> Array2<A> {
+        let (n_rows, n_cols) = q.dim();
+        let column_major = get_layout(q) == Some(blas::Layout::ColumnMajor);
+        let mut full = Array2::zeros((n_rows, n_rows).set_f(column_major));
+        full.slice_mut(s![.., ..n_cols]).assign(q);
+
+        // NOTE: This unwrap is safe, because Q and R are square, contiguous and, for a tall `q`,
+        // not empty.
+        let mut cgs2 = Self::from_parts(full, Array2::zeros((n_rows, n_rows).set_f(column_major))).unwrap();
+        let threshold = 0.5 / (n_rows as f64).sqrt();
+        let mut next_col = n_cols;
+        for unit in 0..n_rows {
+            if next_col == n_rows {
+                break;
+            }
+            let mut column = cgs2.q.column_mut(next_col);
+            column.fill(A::zero());
+            column[unit] = A::one();
+            if cgs2.orthonormalize_column(next_col, threshold, A::Real::one).is_ok() {
+                next_col += 1;
+            }
+        }
+        cgs2.q
+    }
+
+    /// Orthonormalizes the vector `v` against the columns of Q and appends it as a new column,
+    /// growing Q and R by one column each.
+    ///
+    /// This allows to build up a basis one vector at a time, e.g. in greedy subspace methods,
+    /// without repeating the factorization of the previous columns. The vector is orthogonalized
+    /// twice just like the columns in `compute`, respecting the
+    /// [`reorthogonalization_tolerance`]. A factorization without any columns to start from is
+    /// created by [`empty`].
+    ///
+    /// Returns [`Error::RankDeficient`] if the norm of `v` after removing its projections onto Q
+    /// is at most [`tolerance`] times its original norm, which by default follows the shape of
+    /// the grown Q, and in particular if Q already has as many columns as rows. The factorization
+    /// is left unchanged in that case.
+    ///
+    /// Q and R are reallocated for every new column, copying the previous ones. This costs as
+    /// much as orthogonalizing the new column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Layout, Reorthogonalized};
+    /// use ndarray::arr1;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let mut cgs2: Reorthogonalized = Reorthogonalized::empty(3, Layout::RowMajor)?;
+    /// cgs2.push_column(&arr1(&[3.0, 0.0, 4.0]))?;
+    /// cgs2.push_column(&arr1(&[1.0, 1.0, 0.0]))?;
+    /// assert_eq!(cgs2.q().dim(), (3, 2));
+    /// assert!(cgs2.push_column(&arr1(&[4.0, 1.0, 4.0])).is_err());
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`reorthogonalization_tolerance`]: #method.set_reorthogonalization_tolerance
+    /// [`tolerance`]: trait.GramSchmidt.html#tymethod.set_tolerance
+    /// [`empty`]: #method.empty
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    pub fn push_column<S>(&mut self, v: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        let (n_rows, n_cols) = self.q.dim();
+        if v.len() != n_rows {
+            Err(Error::LengthMismatch { expected: n_rows, found: v.len() })?;
+        }
+        // The new column cannot be independent, and the work vector would be too short.
+        if n_cols >= n_rows {
+            Err(Error::RankDeficient { column: n_cols })?;
+        }
+
+        let mut q = Array2::zeros((n_rows, n_cols + 1).set_f(self.memory_layout == blas::Layout::ColumnMajor));
+        q.slice_mut(s![.., ..n_cols]).assign(&self.q);
+        q.column_mut(n_cols).assign(v);
+        let mut r = Array2::zeros((n_cols + 1, n_cols + 1).set_f(self.r_layout == blas::Layout::ColumnMajor));
+        r.slice_mut(s![..n_cols, ..n_cols]).assign(&self.r);
+
+        let previous_q = mem::replace(&mut self.q, q);
+        let previous_r = mem::replace(&mut self.r, r);
+        self.reorthogonalized.push(false);
+        self.sign_flipped.push(false);
+        self.computed = true;
+
+        let tolerance = self.tolerance();
+        let result = self.orthonormalize_column(n_cols, tolerance, || normalization(v));
+        if result.is_err() {
+            self.q = previous_q;
+            self.r = previous_r;
+            self.reorthogonalized.pop();
+            self.sign_flipped.pop();
+        }
+        result
+    }
+
+    /// Orthonormalizes the columns `start..` of `a`, assuming that the columns `0..start` have
+    /// already been processed. Returns early if the optional `deadline` has passed, after at least
+    /// one column has been processed, or once `observe` returns `false` for a processed column.
+    fn compute_from<S, O>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        start: usize,
+        deadline: Option<Instant>,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        use Error::*;
+
+        if a.dim() != self.q.dim() {
+            Err(ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        let n_cols = self.q.cols();
+
+        match (self.memory_layout, as_slice_with_layout(a)) {
+            (a, Some((_, b))) if a != b && !self.accept_any_layout => Err(IncompatibleLayouts)?,
+            (_, Some(_)) => {},
+            (_, None) if is_broadcast(a) => Err(BroadcastInput)?,
             (_, None) => Err(NonContiguous)?,
+        }
+
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        // The signs of the columns computed anew are determined by the procedure again.
+        clear_sign_flips(&mut self.sign_flipped, if start < self.pinned { 0 } else { start });
+        self.computed = true;
+
+        if start == 0 && self.pinned == 0
+            && orthonormal_shortcut(&mut self.q, &mut self.r, a, self.orthonormal_threshold)
+        {
+            self.reorthogonalized.iter_mut().for_each(|reorthogonalized| *reorthogonalized = false);
+            let status = observe_columns(&self.r, 0..n_cols, &mut observe);
+            return Ok(status.unwrap_or(ComputeStatus::Done));
+        }
+
+        // Pinned columns are not orthonormalized, but only copied.
+        let start = if start < self.pinned {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+            for reorthogonalized in &mut self.reorthogonalized[..self.pinned] {
+                *reorthogonalized = false;
+            }
+            if let Some(status) = observe_columns(&self.r, 0..self.pinned, &mut observe) {
+                return Ok(status);
+            }
+            self.pinned
+        } else {
+            start
         };
 
+        for i in start..n_cols {
+            if i > start && deadline_passed(deadline) {
+                return Ok(ComputeStatus::InProgress { next_col: i });
+            }
+
+            self.q.column_mut(i).assign(&a.column(i));
+            self.orthonormalize_column(i, self.tolerance(), || normalization(&a.column(i)))?;
+
+            if let Some(status) = observe_columns(&self.r, i..i + 1, &mut observe) {
+                return Ok(status);
+            }
+        }
+
+        Ok(ComputeStatus::Done)
+    }
+
+    /// Orthonormalizes the column `i` of Q against the columns `0..i`, assuming that it has been
+    /// set to the column of the matrix to be factorized, whose norm is computed by `column_norm`
+    /// if needed. Fills the column `i` of R.
+    fn orthonormalize_column<F>(&mut self, i: usize, tolerance: f64, column_norm: F) -> Result<()>
+        where F: FnOnce() -> A::Real
+    {
+        use blas::Layout::*;
+
+        let (n_rows, n_cols) = self.q.dim();
+
         // leading_dim: the number of elements in the leading dimension
         // next_elem: how many elements to jump to get to the next element in a column
-        // next_col: how many elements in the array to jump to get to the next column
-        let (leading_dim, next_elem, next_col) = match self.memory_layout {
-            ColumnMajor => (n_rows as i32, 1, n_rows),
-            RowMajor => (n_cols as i32, n_cols as i32, 1),
+        let (leading_dim, next_elem) = match self.memory_layout {
+            ColumnMajor => (n_rows as i32, 1),
+            RowMajor => (n_cols as i32, n_cols as i32),
         };
 
+        // r_next_elem: the same as next_elem, but for the matrix r, whose layout is allowed to
+        // differ from the one of q.
+        // r_next_col: how many elements in r to jump to get to the next column
+        let (r_next_elem, r_next_col) = match self.r_layout {
+            ColumnMajor => (1, n_cols),
+            RowMajor => (n_cols as i32, 1),
+        };
 
-        for i in 0..n_cols {
-            self.q.column_mut(i).assign(&a.column(i));
+        // work_inc: how many elements to jump to get to the next element of the work vector.
+        //
+        // NOTE: The work vector is either allocated by the procedure or checked to be contiguous
+        // by `with_workspace`, so that work_inc is 1. Since the blas calls below read the work
+        // vector as a slice in memory order, any other stride would be unsound, and is asserted
+        // against in release builds as well.
+        let work_inc = self.work_vector.strides()[0] as i32;
+        assert_eq!(work_inc, 1, "work vector is expected to be contiguous");
 
-            let len = self.q.len();
-            let q_ptr = self.q.as_mut_ptr();
-            let q_matrix = unsafe {
-                slice::from_raw_parts(q_ptr, len)
-            };
+        self.reorthogonalized[i] = false;
 
-            let q_column = match self.memory_layout {
-                ColumnMajor => {
-                    let offset = n_rows * i;
-                    unsafe {
-                        slice::from_raw_parts_mut(q_ptr.offset(offset as isize), len - offset)
-                    }
-                },
-
-                RowMajor => {
-                    let offset = i as isize;
-                    unsafe {
-                        slice::from_raw_parts_mut(q_ptr.offset(offset), len - i)
-                    }
-                },
+        let len = self.q.len();
+        let q_ptr = self.q.as_mut_ptr();
+        let q_matrix = unsafe {
+            slice::from_raw_parts(q_ptr, len)
+        };
 
-            };
+        let q_column = match self.memory_layout {
+            ColumnMajor => {
+                let offset = n_rows * i;
+                unsafe {
+                    slice::from_raw_parts_mut(q_ptr.add(offset), len - offset)
+                }
+            },
 
-            if i > 0 {
-                let a_column = &a_slice[next_col * i..];
+            RowMajor => {
+                let offset = i as isize;
+                unsafe {
+                    slice::from_raw_parts_mut(q_ptr.offset(offset), len - i)
+                }
+            },
 
-                // NOTE: This unwrap is save, because we have made sure at creation that r_slice is
-                // contiguous.
-                //
-                // NOTE: Unlike a_slice above which is defined outside the loop, we are mutating r at the
-                // end of the loop, which invalidates the mutable borrow. We thus have to pull the
-                // slice definition into the loop.
-                let r_slice = self.r.as_slice_memory_order_mut().unwrap();
-                let r_column = &mut r_slice[next_col * i..];
+        };
 
-                let work_slice = self.work_vector.as_slice_memory_order_mut().unwrap();
+        if i > 0 {
+            // NOTE: This unwrap is save, because we have made sure at creation that r_slice is
+            // contiguous.
+            //
+            // NOTE: We are mutating r at the end of this function, which invalidates the mutable
+            // borrow. We thus have to restrict the slice definition to this block.
+            let r_slice = self.r.as_slice_memory_order_mut().unwrap();
+            let r_column = &mut r_slice[r_next_col * i..];
 
-                unsafe {
-                    // First orthogonalization
-                    // =======================
-                    cblas::dgemv(
-                        self.memory_layout,
-                        cblas::Transpose::Ordinary,
-                        n_rows as i32,
-                        i as i32,
-                        1.0,
-                        q_matrix,
-                        leading_dim,
-                        a_column,
-                        next_elem,
-                        0.0,
-                        r_column,
-                        next_elem
-                    );
+            let work_slice = self.work_vector.as_slice_memory_order_mut().unwrap();
 
-                    cblas::dgemv(
-                        self.memory_layout,
-                        cblas::Transpose::None,
-                        n_rows as i32,
-                        i as i32,
-                        -1.0,
-                        q_matrix,
-                        leading_dim,
-                        r_column,
-                        next_elem,
-                        1.0,
-                        q_column,
-                        next_elem,
-                    );
+            // The norm of the column before the first orthogonalization is only needed if the
+            // second orthogonalization is not performed unconditionally.
+            let always_reorthogonalize = self.reorthogonalization_tolerance == f64::INFINITY;
+            // NOTE: This unwrap is safe, because any f64 can be cast to f32 and f64.
+            let tolerance = <A::Real as NumCast>::from(self.reorthogonalization_tolerance).unwrap();
+            let norm_before = if always_reorthogonalize {
+                A::Real::zero()
+            } else {
+                unsafe { A::nrm2(n_rows as i32, q_column, next_elem) }
+            };
 
-                    // Second orthogonalization
-                    // ========================
-                    cblas::dgemv(
+            unsafe {
+                // First orthogonalization
+                // =======================
+                A::gemv(
+                    self.memory_layout,
+                    A::ADJOINT,
+                    n_rows as i32,
+                    i as i32,
+                    A::one(),
+                    q_matrix,
+                    leading_dim,
+                    q_column,
+                    next_elem,
+                    A::zero(),
+                    r_column,
+                    r_next_elem
+                );
+
+                A::gemv(
+                    self.memory_layout,
+                    blas::Transpose::None,
+                    n_rows as i32,
+                    i as i32,
+                    -A::one(),
+                    q_matrix,
+                    leading_dim,
+                    r_column,
+                    r_next_elem,
+                    A::one(),
+                    q_column,
+                    next_elem,
+                );
+
+                let reorthogonalize = always_reorthogonalize
+                    || A::nrm2(n_rows as i32, q_column, next_elem) < tolerance * norm_before;
+
+                // Second orthogonalization
+                // ========================
+                self.reorthogonalized[i] = reorthogonalize;
+                if reorthogonalize {
+                    A::gemv(
                         self.memory_layout,
-                        cblas::Transpose::Ordinary,
+                        A::ADJOINT,
                         n_rows as i32,
                         i as i32,
-                        1.0,
+                        A::one(),
                         q_matrix,
                         leading_dim,
                         q_column,
                         next_elem,
-                        0.0,
+                        A::zero(),
                         work_slice,
-                        1 // Always 1 from the definition of the work_slice/work_vector
+                        work_inc,
                     );
 
-                    cblas::dgemv(
+                    A::gemv(
                         self.memory_layout,
-                        cblas::Transpose::None,
+                        blas::Transpose::None,
                         n_rows as i32,
                         i as i32,
-                        -1.0,
+                        -A::one(),
                         q_matrix,
                         leading_dim,
                         work_slice,
-                        1,
-                        1.0,
+                        work_inc,
+                        A::one(),
                         q_column,
                         next_elem,
                     );
 
-                    cblas::daxpy(
-                        n_rows as i32, // n
-                        1.0, // alpha
+                    A::axpy(
+                        i as i32, // n
+                        A::one(), // alpha
                         work_slice, // x
-                        1, // Always 1 from the definition of the work_slice/work_vector
+                        work_inc,
                         r_column,
-                        next_elem,
+                        r_next_elem,
                     );
-
                 }
-            };
 
-            let norm = unsafe {
-                cblas::dnrm2(n_rows as i32, q_column, next_elem)
-            };
+            }
+        };
 
-            let mut v = self.q.column_mut(i);
-            v /= norm;
-            self.r[(i,i)] = a.column(i).dot(&v);
-        }
+        let norm = unsafe {
+            A::nrm2(n_rows as i32, q_column, next_elem)
+        };
+
+        check_rank(i, norm, tolerance, column_norm)?;
+
+        // NOTE: The diagonal of R has to be the norm of the orthogonalized column. Computing
+        // it as the projection A_(i)ᵀ·Q_(i) instead is only equivalent if Q_(i) is exactly
+        // orthogonal to the previous columns, and breaks A = QR if orthogonality is lost.
+        let mut v = self.q.column_mut(i);
+        v /= A::from_real(norm);
+        self.r[(i,i)] = A::from_real(norm);
 
         Ok(())
     }
+}
+
+impl<A: GramSchmidtScalar> GramSchmidt for Reorthogonalized<A> {
+    type Scalar = A;
+
+    const STABILITY: StabilityClass = StabilityClass::High;
+    const COST_FACTOR: f64 = 2.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        Self::allocate(shape, transpose_r, None)
+    }
+
+    fn from_parts(q: Array2<A>, r: Array2<A>) -> Result<Self> {
+        let (memory_layout, r_layout) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        let work_vector = Array1::zeros(q.rows());
+        let reorthogonalized = vec![false; q.cols()];
+        Ok(Self {
+            q,
+            r,
+            work_vector,
+            memory_layout,
+            r_layout,
+            reorthogonalization_tolerance: f64::INFINITY,
+            pinned: 0,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
+            reorthogonalized,
+            sign_flipped,
+            computed: false,
+            accept_any_layout: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<A>(self.q.rows(), self.q.cols()))
+    }
+
+    fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, 0, None, |_, _| true).map(|_| ())
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, next_col, Some(deadline), |_, _| true)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, start, None, |_, _| true).map(|_| ())
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: FnMut(usize, A::Real) -> bool
+    {
+        self.compute_from(a, 0, None, observe)
+    }
 
-    fn q(&self) -> &Array2<f64> {
+    fn q(&self) -> &Array2<A> {
         &self.q
     }
 
-    fn r(&self) -> &Array2<f64> {
+    fn r(&self) -> &Array2<A> {
         &self.r
     }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(self.pinned, &self.sign_flipped, |j| self.reorthogonalized[j])
+    }
+
+    fn last_flops(&self) -> u64 {
+        // The second orthogonalizations are counted instead of scaling by the cost factor.
+        last_flops(self.computed, self.q.rows(), &self.provenance(), 1.0)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = A>,
+              T: Data<Elem = A>
+    {
+        givens::low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        givens::remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        self.reorthogonalized.remove(index);
+        if index < self.pinned {
+            self.pinned -= 1;
+        }
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+        self.reorthogonalized.iter_mut().skip(start).for_each(|reorthogonalized| *reorthogonalized = false);
+    }
+}
+
+/// Prints the dimensions and the layout of the factorization followed by Q and R. The precision
+/// of the entries can be set with the formatter, e.g. `{:.3}`.
+impl<A: GramSchmidtScalar + fmt::Display> fmt::Display for Reorthogonalized<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_factorization(f, "Reorthogonalized", self.memory_layout, &self.q, &self.r)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<A: GramSchmidtScalar> crate::ParallelGramSchmidt for Reorthogonalized<A> {
+    fn par_compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+        if is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        if !self.skip_finite_check {
+            check_finite(a)?;
+        }
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+        if self.pinned > 0 {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+        }
+
+        let always_reorthogonalize = self.reorthogonalization_tolerance == f64::INFINITY;
+        // NOTE: This unwrap is safe, because any f64 can be cast to f32 and f64.
+        let reorthogonalization_tolerance = <A::Real as NumCast>::from(self.reorthogonalization_tolerance).unwrap();
+        let tolerance = self.tolerance();
+        let reorthogonalized = &mut self.reorthogonalized;
+        for flag in reorthogonalized.iter_mut() {
+            *flag = false;
+        }
+
+        crate::parallel::compute_columns(
+            &mut self.q,
+            &mut self.r,
+            a,
+            self.pinned,
+            tolerance,
+            |i, norm_before, norm_after| {
+                reorthogonalized[i] = always_reorthogonalize || norm_after < reorthogonalization_tolerance * norm_before;
+                reorthogonalized[i]
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn mixed_layouts_are_accepted() {
+        crate::utils::check_mixed_layouts(Reorthogonalized::<f64>::from_shape_accepting_any_layout);
+    }
+}
+
+#[cfg(test)]
+generate_tests!(
+    Reorthogonalized,
+    1e-13,
+    precisions: [double: f64 => 1e-13, single: f32 => 1e-4]
+);
+
+#[cfg(test)]
+mod stability_tests {
+    extern crate openblas_src;
+
+    use crate::utils::{
+        check_conditioned_matrices,
+        check_hilbert_orthogonality,
+        check_identity_plus_noise_orthogonality,
+    };
+    use super::*;
+
+    // Reorthogonalized Gram Schmidt keeps orthogonality at the level of machine precision.
+    #[test]
+    fn hilbert_orthogonality() {
+        check_hilbert_orthogonality::<Reorthogonalized<f64>>(&[(6, 1e-14), (8, 1e-14), (10, 1e-14)]);
+    }
+
+    #[test]
+    fn identity_plus_noise_orthogonality() {
+        check_identity_plus_noise_orthogonality::<Reorthogonalized<f64>, _>(|_kappa| 10.0 * f64::EPSILON);
+    }
+
+    #[test]
+    fn random_conditioned_matrices() {
+        check_conditioned_matrices::<Reorthogonalized<f64>, _>(1e-13, |_kappa| 10.0 * f64::EPSILON);
+    }
+}
+
+#[cfg(test)]
+mod tolerance_tests {
+    extern crate openblas_src;
+
+    use crate::Classical;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn zero_tolerance_never_reorthogonalizes() {
+        let a = matrix(4, 4);
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.set_reorthogonalization_tolerance(0.0);
+        assert_eq!(cgs2.reorthogonalization_tolerance(), 0.0);
+
+        cgs.compute(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs.q(), cgs2.q());
+        assert_eq!(cgs.r(), cgs2.r());
+    }
+
+    #[test]
+    fn provenance_records_reorthogonalization() {
+        let a = matrix(4, 4);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+        let reorthogonalized: Vec<bool> = cgs2.provenance().iter().map(|p| p.reorthogonalized).collect();
+        assert_eq!(reorthogonalized, vec![false, true, true, true]);
+
+        cgs2.set_reorthogonalization_tolerance(0.0);
+        cgs2.compute(&a).unwrap();
+        assert!(cgs2.provenance().iter().all(|p| !p.reorthogonalized));
+    }
+
+    #[test]
+    fn flops_count_second_orthogonalizations() {
+        let a = matrix(4, 4);
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.compute(&a).unwrap();
+        // Σ_j (4·4·j + 3·4) for j = 0..4
+        assert_eq!(cgs.last_flops(), 144);
+
+        // All but the first column are orthogonalized twice.
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs2.last_flops(), 144 + 16 * (1 + 2 + 3));
+
+        cgs2.set_reorthogonalization_tolerance(0.0);
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs2.last_flops(), 144);
+
+        // The pinned first column costs nothing.
+        let mut pinned = Reorthogonalized::from_matrix(&Array2::<f64>::eye(4)).unwrap();
+        pinned.set_pinned(1).unwrap();
+        pinned.set_reorthogonalization_tolerance(0.0);
+        pinned.compute(&Array2::<f64>::eye(4)).unwrap();
+        assert_eq!(pinned.last_flops(), 144 - 12);
+
+        // Nothing has been computed in the new shape yet.
+        pinned.reset_shape((4, 4)).unwrap();
+        assert_eq!(pinned.last_flops(), 0);
+    }
+
+    /// The Lauchli matrix, whose columns become nearly parallel for small `epsilon`.
+    fn lauchli(n: usize, epsilon: f64) -> Array2<f64> {
+        let mut a = Array2::zeros((n + 1, n));
+        a.row_mut(0).fill(1.0);
+        a.slice_mut(s![1.., ..]).diag_mut().fill(epsilon);
+        a
+    }
+
+    #[test]
+    fn lauchli_is_orthogonalized_with_tolerance() {
+        let n = 10;
+        let a = lauchli(n, 1e-8);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs2.reorthogonalizations(), n - 1);
+
+        // The first orthogonalization cancels nearly all of every column after the first, so that
+        // all of them are still reorthogonalized.
+        cgs2.set_reorthogonalization_tolerance(0.5f64.sqrt());
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs2.reorthogonalizations(), n - 1);
+        assert!(crate::utils::orthogonality_loss(cgs2.q()) < 1e-14);
+        assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-14));
+
+        // The classical procedure instead loses orthogonality completely.
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.compute(&a).unwrap();
+        assert!(crate::utils::orthogonality_loss(cgs.q()) > 1.0);
+    }
+
+    #[test]
+    fn orthogonal_columns_are_not_reorthogonalized() {
+        let a = Array2::<f64>::eye(5);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.set_reorthogonalization_tolerance(0.5f64.sqrt());
+        cgs2.compute(&a).unwrap();
+        assert_eq!(cgs2.reorthogonalizations(), 0);
+    }
+
+    #[test]
+    fn tolerance_sweep_reuses_struct() {
+        let a = matrix(4, 4);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        assert_eq!(cgs2.reorthogonalization_tolerance(), f64::INFINITY);
+
+        for &tol in &[0.0, 0.5f64.sqrt(), 1.0, f64::INFINITY] {
+            cgs2.set_reorthogonalization_tolerance(tol);
+            cgs2.compute(&a).unwrap();
+            assert!(crate::diagnostics::is_orthogonal(cgs2.q(), 1e-13));
+            assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-13));
+        }
+    }
 }
 
 #[cfg(test)]
-generate_tests!(Reorthogonalized, 1e-13);
+mod push_column_tests {
+    extern crate openblas_src;
+
+    use ndarray::ShapeBuilder;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn incremental_basis_matches_batch() {
+        let a = matrix(8, 5);
+        let (q, r) = crate::cgs2_qr(&a).unwrap().into_tuple();
+
+        let mut cgs2: Reorthogonalized = Reorthogonalized::empty(8, Layout::RowMajor).unwrap();
+        for column in a.gencolumns() {
+            cgs2.push_column(&column).unwrap();
+        }
+        assert!(cgs2.q().all_close(&q, 1e-12));
+        assert!(cgs2.r().all_close(&r, 1e-12));
+
+        // Continue a column major factorization of the first two columns.
+        let mut f_a = Array2::zeros((8, 2).f());
+        f_a.assign(&a.slice(s![.., ..2]));
+        let mut cgs2 = Reorthogonalized::from_matrix(&f_a).unwrap();
+        cgs2.compute(&f_a).unwrap();
+        for column in a.slice(s![.., 2..]).gencolumns() {
+            cgs2.push_column(&column).unwrap();
+        }
+        assert_eq!(get_layout(cgs2.q()), Some(blas::Layout::ColumnMajor));
+        assert!(cgs2.q().all_close(&q, 1e-12));
+        assert!(cgs2.r().all_close(&r, 1e-12));
+    }
+
+    #[test]
+    fn dependent_column_is_rejected() {
+        let a = matrix(8, 5);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+
+        let dependent = &a.column(0) - &(&a.column(3) * 2.0);
+        match cgs2.push_column(&dependent) {
+            Err(Error::RankDeficient { column: 5 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+        assert_eq!(cgs2.q().dim(), (8, 5));
+        assert_eq!(cgs2.r().dim(), (5, 5));
+        assert_eq!(cgs2.provenance().len(), 5);
+        assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-12));
+
+        match cgs2.push_column(&Array1::zeros(3)) {
+            Err(Error::LengthMismatch { expected: 8, found: 3 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn full_basis_cannot_grow() {
+        let mut cgs2: Reorthogonalized = Reorthogonalized::empty(2, Layout::ColumnMajor).unwrap();
+        cgs2.push_column(&arr1(&[1.0, 1.0])).unwrap();
+        cgs2.push_column(&arr1(&[1.0, -2.0])).unwrap();
+        match cgs2.push_column(&arr1(&[3.0, 4.0])) {
+            Err(Error::RankDeficient { column: 2 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod workspace_tests {
+    extern crate openblas_src;
+
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+    use super::*;
+
+    #[test]
+    fn shared_workspace_matches_internal_one() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut work_vector = Array1::zeros(9);
+        for _ in 0..3 {
+            let a = Array2::random_using((9, 6), Normal::new(0.0, 1.0), &mut rng);
+            let mut internal = Reorthogonalized::from_matrix(&a).unwrap();
+            internal.compute(&a).unwrap();
+
+            let mut shared = Reorthogonalized::with_workspace(a.dim(), work_vector).unwrap();
+            shared.compute(&a).unwrap();
+            assert_eq!(shared.q(), internal.q());
+            assert_eq!(shared.r(), internal.r());
+            work_vector = shared.into_workspace();
+        }
+    }
+
+    #[test]
+    fn mismatched_workspace_is_rejected() {
+        match Reorthogonalized::with_workspace((9, 6), Array1::<f64>::zeros(6)) {
+            Err(Error::LengthMismatch { expected: 9, found: 6 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other.map(|_| ())),
+        }
+
+        let strided = Array1::<f64>::zeros(18).slice_move(s![..;2]);
+        match Reorthogonalized::with_workspace((9, 6), strided) {
+            Err(Error::NonContiguous) => {},
+            other => panic!("expected a non-contiguous work vector, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn display_shows_dimensions_layout_and_factors() {
+        let cgs2 = Reorthogonalized::<f64>::from_shape((3, 2)).unwrap();
+        assert_eq!(cgs2.to_string().lines().count(), 1 + 4 + 3);
+    }
+}
+
+#[cfg(test)]
+mod orthonormal_shortcut_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn orthonormal_shortcut_is_opt_in() {
+        assert_eq!(Reorthogonalized::<f64>::from_shape((8, 6)).unwrap().orthonormal_threshold(), 0.0);
+        crate::utils::check_orthonormal_shortcut(Reorthogonalized::set_orthonormal_threshold);
+    }
+}
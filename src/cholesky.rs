@@ -0,0 +1,142 @@
+use ndarray::{
+    Data,
+    ShapeBuilder,
+    s,
+};
+use ndarray::prelude::*;
+
+use crate::{
+    Error,
+    Result,
+};
+
+/// Computes the R factor of a QR decomposition `A = QR` from the Gram matrix `g = AᵀA` alone,
+/// via the Cholesky decomposition `g = RᵀR`.
+///
+/// This serves cases where `AᵀA` is accumulated without ever materializing `A`, e.g. when
+/// streaming its rows. Only the upper triangle of `g` is read and its symmetry is assumed, like
+/// LAPACK's `dpotrf` with `uplo = 'U'`. The diagonal of the returned R is positive, so that it
+/// agrees with the R computed by the Gram Schmidt procedures. The result is stored in column
+/// major layout if `g` is, and in row major layout otherwise.
+///
+/// Note that forming `AᵀA` squares the condition number of `A`, so R is less accurate than the
+/// one from an actual QR decomposition for ill-conditioned matrices.
+///
+/// Returns an error if `g` is not square, or if it is not (numerically) positive definite.
+///
+/// # Example
+///
+/// ```
+/// use gramschmidt::r_from_gram;
+/// use ndarray::arr2;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = arr2(&[[1.0, 2.0],
+///                [0.0, 1.0],
+///                [1.0, 0.0]]);
+/// let r = r_from_gram(&a.t().dot(&a))?;
+/// assert!(r.t().dot(&r).all_close(&a.t().dot(&a), 1e-14));
+/// assert_eq!(r[(1, 0)], 0.0);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn r_from_gram<S>(g: &ArrayBase<S, Ix2>) -> Result<Array2<f64>>
+    where S: Data<Elem = f64>
+{
+    let (n_rows, n_cols) = g.dim();
+    if n_rows != n_cols {
+        Err(Error::NotSquare)?;
+    }
+
+    let mut r = Array2::zeros((n_cols, n_cols).set_f(!g.is_standard_layout()));
+    cholesky_upper(g, &mut r, None)?;
+    Ok(r)
+}
+
+/// Writes the upper triangular Cholesky factor of the square matrix `g` into `r`, reading only
+/// the upper triangle of `g`, and returns the number of nonzero rows of `r`.
+///
+/// Without a `tolerance`, a pivot that is not positive results in an error. With a tolerance,
+/// `g` may be positive semi-definite: a pivot `d_j ≤ tolerance · g_jj` marks the `j`-th column as
+/// linearly dependent on the previous ones, and the `j`-th row of `r` is set to zero.
+pub(crate) fn cholesky_upper<S>(g: &ArrayBase<S, Ix2>, r: &mut Array2<f64>, tolerance: Option<f64>) -> Result<usize>
+    where S: Data<Elem = f64>
+{
+    let n_cols = g.cols();
+    let mut rank = 0;
+    r.fill(0.0);
+    for j in 0..n_cols {
+        let r_done = r.slice(s![..j, ..]);
+        let diagonal = g[(j, j)] - r_done.column(j).dot(&r_done.column(j));
+        match tolerance {
+            Some(tolerance) if diagonal <= tolerance * g[(j, j)] || diagonal.is_nan() => continue,
+            None if diagonal <= 0.0 || diagonal.is_nan() => Err(Error::NotPositiveDefinite)?,
+            _ => {},
+        }
+        let r_jj = diagonal.sqrt();
+
+        let row: Vec<f64> = ((j + 1)..n_cols)
+            .map(|i| (g[(j, i)] - r_done.column(j).dot(&r_done.column(i))) / r_jj)
+            .collect();
+        r[(j, j)] = r_jj;
+        r.slice_mut(s![j, (j + 1)..]).assign(&ArrayView1::from(&row[..]));
+        rank += 1;
+    }
+
+    Ok(rank)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::{
+        GramSchmidt,
+        Modified,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn agrees_with_gram_schmidt() {
+        let a = matrix(5, 4);
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+
+        let g = a.t().dot(&a);
+        let r = r_from_gram(&g).unwrap();
+        assert!(r.all_close(mgs.r(), 1e-13));
+        assert!(r.is_standard_layout());
+
+        let r_f = r_from_gram(&g.t()).unwrap();
+        assert!(r_f.all_close(mgs.r(), 1e-13));
+        assert!(!r_f.is_standard_layout());
+    }
+
+    #[test]
+    fn indefinite_matrix_is_rejected() {
+        let g = arr2(&[[1.0, 2.0], [2.0, 1.0]]);
+        match r_from_gram(&g) {
+            Err(Error::NotPositiveDefinite) => {},
+            other => panic!("expected a not positive definite matrix, got {:?}", other),
+        }
+
+        // A rank deficient Gram matrix is only positive semi-definite.
+        let g = Array2::<f64>::zeros((3, 3));
+        match r_from_gram(&g) {
+            Err(Error::NotPositiveDefinite) => {},
+            other => panic!("expected a not positive definite matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn non_square_matrix_is_rejected() {
+        match r_from_gram(&Array2::<f64>::zeros((3, 2))) {
+            Err(Error::NotSquare) => {},
+            other => panic!("expected a non-square matrix, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,664 @@
+use ndarray::{
+    Data,
+    Dim,
+    Ix,
+    ShapeBuilder,
+};
+use ndarray::prelude::*;
+use std::time::Instant;
+
+use crate::{
+    ColumnProvenance,
+    ComputeStatus,
+    Error,
+    GramSchmidt,
+    Result,
+    StabilityClass,
+    blas,
+    cholesky::cholesky_upper,
+    diagnostics::gram_upper,
+    givens,
+    utils::{
+        as_slice_with_layout,
+        check_finite,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        default_tolerance,
+        force_positive_diagonal,
+        get_layout,
+        is_broadcast,
+        last_flops,
+        observe_columns,
+        parts_layouts,
+        provenance,
+        rescale_columns,
+        transpose_layout,
+    },
+};
+
+/// A QR decomposition by the CholeskyQR algorithm, which obtains R from the Cholesky
+/// decomposition of the Gram matrix `AᵀA = RᵀR`, and Q by solving `Q·R = A`.
+///
+/// The Gram matrix is formed by a single symmetric rank k update (blas `dsyrk`), and Q by a
+/// single triangular solve (blas `dtrsm`), so that all of the work is done by matrix-matrix
+/// operations. The Cholesky decomposition itself only touches the small `n×n` Gram matrix. For
+/// tall and skinny matrices, this is considerably faster than the Gram Schmidt procedures, which
+/// process one column at a time.
+///
+/// Forming `AᵀA` squares the condition number of A though: the loss of orthogonality grows with
+/// the square of the condition number like for [`Classical`], and the Cholesky decomposition
+/// breaks down with [`Error::RankDeficient`] for condition numbers beyond about `1e8`. A second
+/// pass enabled by [`set_double`] restores the orthogonality below that, and a preceding pass
+/// with a shifted Gram matrix enabled by [`set_shifted`] extends it to condition numbers up to
+/// about `1e15`. For ill-conditioned matrices, fall back to [`Reorthogonalized`].
+///
+/// Since all columns are factorized at once, `continue_from` does not respect its deadline, and
+/// always completes the factorization. Pinned columns are not supported.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{CholeskyQr, GramSchmidt};
+/// use ndarray::Array2;
+/// use ndarray_rand::RandomExt;
+/// use rand::distributions::Normal;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = Array2::random((1000, 20), Normal::new(0.0, 1.0));
+/// let mut cholesky_qr = CholeskyQr::from_matrix(&a)?;
+/// cholesky_qr.compute(&a)?;
+/// assert!(a.all_close(&cholesky_qr.q().dot(cholesky_qr.r()), 1e-12));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Classical`]: struct.Classical.html
+/// [`Reorthogonalized`]: struct.Reorthogonalized.html
+/// [`set_double`]: #method.set_double
+/// [`set_shifted`]: #method.set_shifted
+/// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+#[derive(Clone, Debug)]
+pub struct CholeskyQr {
+    q: Array2<f64>,
+    r: Array2<f64>,
+    memory_layout: blas::Layout,
+    double: bool,
+    shifted: bool,
+    sign_flipped: Vec<bool>,
+    computed: bool,
+    tolerance: Option<f64>,
+    skip_finite_check: bool,
+}
+
+impl GramSchmidt for CholeskyQr {
+    type Scalar = f64;
+
+    const STABILITY: StabilityClass = StabilityClass::Low;
+    const COST_FACTOR: f64 = 1.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let shape = shape.into_shape();
+        let q = Array2::zeros(shape);
+        let memory_layout = match get_layout(&q) {
+            Some(layout) => layout,
+            None => Err(Error::NonContiguous)?,
+        };
+        check_shape(q.dim())?;
+
+        let (_, n_cols) = q.dim();
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
+        let r = Array2::zeros(
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
+        );
+
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            double: false,
+            shifted: false,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+        })
+    }
+
+    fn from_parts(q: Array2<f64>, r: Array2<f64>) -> Result<Self> {
+        let (memory_layout, _) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            double: false,
+            shifted: false,
+            sign_flipped,
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<f64>(self.q.rows(), self.q.cols()))
+    }
+
+    fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        self.compute_from(a, 0)
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, _deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = f64>
+    {
+        self.compute_from(a, next_col).map(|_| ComputeStatus::Done)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        self.compute_from(a, start)
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, mut observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = f64>,
+              F: FnMut(usize, f64) -> bool
+    {
+        // All columns are orthonormalized at once, so that they can only be observed afterwards.
+        self.compute_from(a, 0)?;
+        Ok(observe_columns(&self.r, 0..self.r.cols(), &mut observe).unwrap_or(ComputeStatus::Done))
+    }
+
+    fn q(&self) -> &Array2<f64> {
+        &self.q
+    }
+
+    fn r(&self) -> &Array2<f64> {
+        &self.r
+    }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, f64> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, f64> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(0, &self.sign_flipped, |_| false)
+    }
+
+    fn last_flops(&self) -> u64 {
+        last_flops(self.computed, self.q.rows(), &self.provenance(), Self::COST_FACTOR)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = f64>,
+              T: Data<Elem = f64>
+    {
+        givens::low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        givens::remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+    }
+}
+
+impl CholeskyQr {
+    /// Sets whether the factorization is computed by CholeskyQR2, i.e. whether the Q obtained by
+    /// the first pass is factorized by CholeskyQR once more.
+    ///
+    /// Since the Q of the first pass is much better conditioned than A, the second pass restores
+    /// the orthogonality lost by squaring the condition number, comparable to Householder QR for
+    /// condition numbers up to about `1e8`, at twice the cost. The default, `false`, performs a
+    /// single pass.
+    ///
+    /// # Breakdown
+    ///
+    /// Beyond a condition number of about `1e8`, the Gram matrix of the first pass is not
+    /// numerically positive definite, and its Cholesky decomposition breaks down just like
+    /// without the second pass. Since the decomposition is the crate's own and not LAPACK's
+    /// `dpotrf`, there is no backend error code to report. Instead, the breakdown is reported
+    /// as [`Error::RankDeficient`] with the column at which it occurred. The columns of Q and R
+    /// before it are computed by a single pass, and those from it onward are set to zero. Enable
+    /// [`set_shifted`] to factorize such matrices.
+    ///
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    /// [`set_shifted`]: #method.set_shifted
+    pub fn set_double(&mut self, double: bool) {
+        self.double = double;
+    }
+
+    /// Returns whether the factorization is computed by CholeskyQR2. See [`set_double`] for
+    /// details.
+    ///
+    /// [`set_double`]: #method.set_double
+    pub fn is_double(&self) -> bool {
+        self.double
+    }
+
+    /// Sets whether the factorization is preceded by a pass of shifted CholeskyQR, which
+    /// factorizes the Gram matrix `AᵀA + s·I` with a small shift `s` relative to the squared
+    /// Frobenius norm of A.
+    ///
+    /// The shift keeps the Cholesky decomposition from breaking down for condition numbers up
+    /// to about `1e15`. The resulting Q₀ is far from orthonormal, but its condition number is
+    /// reduced to below `1e8`, so that the subsequent passes succeed. Together with
+    /// [`set_double`], this is the shifted CholeskyQR3 algorithm, which attains orthogonality
+    /// comparable to Householder QR at three times the cost of a single pass. The default,
+    /// `false`, performs no shifted pass.
+    ///
+    /// Linearly dependent columns remain linearly dependent in Q₀, and are detected by the
+    /// subsequent pass as [`Error::RankDeficient`]. The rank threshold then applies to the
+    /// columns of Q₀ instead of those of A.
+    ///
+    /// [`set_double`]: #method.set_double
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    pub fn set_shifted(&mut self, shifted: bool) {
+        self.shifted = shifted;
+    }
+
+    /// Returns whether the factorization is preceded by a pass of shifted CholeskyQR. See
+    /// [`set_shifted`] for details.
+    ///
+    /// [`set_shifted`]: #method.set_shifted
+    pub fn is_shifted(&self) -> bool {
+        self.shifted
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped. See
+    /// [`Classical::set_skip_finite_check`] for details.
+    ///
+    /// [`Classical::set_skip_finite_check`]: struct.Classical.html#method.set_skip_finite_check
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Factorizes all columns of `a`. Since the factorization is not computed column by column,
+    /// `start` only decides whether the input is checked for entries which are not finite.
+    ///
+    /// If a column turns out to be linearly dependent on the previous ones, or the Cholesky
+    /// decomposition breaks down at it, the columns of Q and R before it are still computed, and
+    /// those from it onward are set to zero. Both are reported as [`Error::RankDeficient`].
+    ///
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    fn compute_from<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        use Error::*;
+
+        if a.dim() != self.q.dim() {
+            Err(ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        match (self.memory_layout, as_slice_with_layout(a)) {
+            (a, Some((_, b))) if a != b => Err(IncompatibleLayouts)?,
+            (_, Some(_)) => {},
+            (_, None) if is_broadcast(a) => Err(BroadcastInput)?,
+            (_, None) => Err(NonContiguous)?,
+        };
+
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        let n_cols = self.q.cols();
+        self.q.assign(a);
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+
+        let r_shifted = if self.shifted {
+            Some(self.shifted_pass()?)
+        } else {
+            None
+        };
+
+        // A diagonal entry of R is the remaining norm of its column, and the corresponding
+        // diagonal entry of the Gram matrix the squared original norm, so that the tolerance
+        // applies to their squares. Dependent columns are skipped, leaving zero rows in R.
+        let tolerance = self.tolerance();
+        let gram = gram_upper(&self.q);
+        cholesky_upper(&gram, &mut self.r, Some(tolerance * tolerance))?;
+        let rank = (0..n_cols).find(|&j| self.r[(j, j)] == 0.0).unwrap_or(n_cols);
+
+        clear_columns_from(&mut self.q, &mut self.r, rank);
+        solve_right(&mut self.q, &self.r, rank);
+
+        if self.double && rank == n_cols {
+            // The second pass factorizes Q₁ = Q₂·R₂, which is well-conditioned unless the first
+            // pass broke down, so that A = Q₂·(R₂·R₁).
+            let mut r_second = self.r.clone();
+            if cholesky_upper(&gram_upper(&self.q), &mut r_second, Some(0.0))? < n_cols {
+                let column = (0..n_cols).find(|&j| r_second[(j, j)] == 0.0).unwrap_or(0);
+                Err(RankDeficient { column })?;
+            }
+            solve_right(&mut self.q, &r_second, n_cols);
+            let r = r_second.dot(&self.r);
+            self.r.assign(&r);
+        }
+
+        if let Some(r_shifted) = r_shifted {
+            // A = Q₀·R₀ by the shifted pass, so that A = Q·(R·R₀). The product fills the columns
+            // of R beyond a dependent column, which are cleared again.
+            let r = self.r.dot(&r_shifted);
+            self.r.assign(&r);
+            clear_columns_from(&mut self.q, &mut self.r, rank);
+        }
+
+        if rank < n_cols {
+            Err(RankDeficient { column: rank })?;
+        }
+        Ok(())
+    }
+
+    /// Factorizes Q by a CholeskyQR pass with a shifted Gram matrix `QᵀQ + s·I`, overwriting Q
+    /// with the preconditioned Q₀ and returning the factor R₀ of `Q = Q₀·R₀`.
+    ///
+    /// The shift `s = 11·(mn + n(n+1))·u·‖Q‖²_F` with the unit roundoff `u` is the one proposed
+    /// by Fukaya et al., large enough for the shifted Gram matrix to be numerically positive
+    /// definite for condition numbers up to about `1/u`. Q₀ is then conditioned well enough
+    /// for the unshifted passes, even though it is not orthonormal itself.
+    fn shifted_pass(&mut self) -> Result<Array2<f64>> {
+        let (n_rows, n_cols) = self.q.dim();
+        let mut gram = gram_upper(&self.q);
+        let frobenius_squared: f64 = gram.diag().sum();
+        let unit_roundoff = f64::EPSILON / 2.0;
+        let shift = 11.0 * (n_rows * n_cols + n_cols * (n_cols + 1)) as f64 * unit_roundoff * frobenius_squared;
+        gram.diag_mut().map_inplace(|g_jj| *g_jj += shift);
+
+        // The shifted Gram matrix is positive definite unless A is zero, in which case its first
+        // column is the dependent one.
+        let mut r_shifted = self.r.clone();
+        if cholesky_upper(&gram, &mut r_shifted, Some(0.0))? < n_cols {
+            let column = (0..n_cols).find(|&j| r_shifted[(j, j)] == 0.0).unwrap_or(0);
+            Err(Error::RankDeficient { column })?;
+        }
+        solve_right(&mut self.q, &r_shifted, n_cols);
+        Ok(r_shifted)
+    }
+}
+
+/// Solves `X·R = Q` for the leading `rank` columns of the contiguous matrix `q`, overwriting them
+/// with X. The upper triangular matrix `r` may be stored in either layout.
+fn solve_right(q: &mut Array2<f64>, r: &Array2<f64>, rank: usize) {
+    use blas::Layout::*;
+
+    if rank == 0 {
+        return;
+    }
+
+    let (n_rows, n_cols) = q.dim();
+    // NOTE: These unwraps are safe, because Q and R are allocated contiguously.
+    let (r_slice, r_layout) = as_slice_with_layout(r).unwrap();
+    let layout = get_layout(q).unwrap();
+    let leading_dim = match layout {
+        ColumnMajor => n_rows,
+        RowMajor => n_cols,
+    };
+    // R stored in the opposite layout is read as Rᵀ, which is lower triangular.
+    let (part, transpose) = if r_layout == layout {
+        (blas::Part::Upper, blas::Transpose::None)
+    } else {
+        (blas::Part::Lower, blas::Transpose::Ordinary)
+    };
+    let q_slice = q.as_slice_memory_order_mut().unwrap();
+
+    unsafe {
+        blas::dtrsm(
+            layout,
+            blas::Side::Right,
+            part,
+            transpose,
+            blas::Diagonal::Generic,
+            n_rows as i32,
+            rank as i32,
+            1.0,
+            r_slice,
+            n_cols as i32,
+            q_slice,
+            leading_dim as i32,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::s;
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+    use crate::{
+        Classical,
+        Reorthogonalized,
+        utils::{
+            hilbert,
+            orthogonality_loss,
+        },
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn tall_skinny_matrix_is_reconstructed() {
+        let a = matrix(1000, 20);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            let mut cgs = Classical::from_matrix(*a).unwrap();
+            cgs.compute(*a).unwrap();
+
+            for &transpose_r in &[false, true] {
+                let column_major = get_layout(*a) == Some(blas::Layout::ColumnMajor);
+                let mut cholesky_qr = CholeskyQr::from_shape_with_r_transposed(a.dim().set_f(column_major), transpose_r).unwrap();
+                cholesky_qr.compute(*a).unwrap();
+                assert!(a.all_close(&cholesky_qr.q().dot(cholesky_qr.r()), 1e-12));
+                assert!(orthogonality_loss(cholesky_qr.q()) < 1e-12);
+                assert!(cholesky_qr.r().slice(s![1.., ..1]).iter().all(|&r_ij| r_ij == 0.0));
+                assert!(cholesky_qr.r().all_close(cgs.r(), 1e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn sign_flips_are_recorded() {
+        let a = matrix(1000, 20);
+        let mut cholesky_qr = CholeskyQr::from_matrix(&a).unwrap();
+        cholesky_qr.compute(&a).unwrap();
+        let mut weights = Array1::ones(a.cols());
+        weights[0] = -1.0;
+        cholesky_qr.rescale_columns(&weights).unwrap();
+        cholesky_qr.force_positive_diagonal();
+        let sign_flipped: Vec<_> = cholesky_qr.provenance().iter().map(|column| column.sign_flipped).collect();
+        assert!(sign_flipped[0]);
+        assert!(sign_flipped[1..].iter().all(|&flipped| !flipped));
+
+        cholesky_qr.compute(&a).unwrap();
+        assert!(cholesky_qr.provenance().iter().all(|column| !column.sign_flipped));
+    }
+
+    #[test]
+    fn flops_are_counted_once_computed() {
+        let a = matrix(1000, 20);
+        let mut cholesky_qr = CholeskyQr::from_matrix(&a).unwrap();
+        assert_eq!(cholesky_qr.last_flops(), 0);
+
+        cholesky_qr.compute(&a).unwrap();
+        assert!(cholesky_qr.last_flops() > 0);
+    }
+
+    #[test]
+    fn dependent_column_is_rank_deficient() {
+        let mut a = matrix(1000, 20);
+        let dependent = &a.column(3) - &a.column(7);
+        a.column_mut(12).assign(&dependent);
+
+        let mut cholesky_qr = CholeskyQr::from_matrix(&a).unwrap().with_tolerance(1e-6);
+        match cholesky_qr.compute(&a) {
+            Err(Error::RankDeficient { column: 12 }) => {},
+            other => panic!("expected a rank deficient matrix, got {:?}", other),
+        }
+        let leading = cholesky_qr.q().slice(s![.., ..12]).dot(&cholesky_qr.r().slice(s![..12, ..12]));
+        assert!(leading.all_close(&a.slice(s![.., ..12]), 1e-12));
+        assert!(cholesky_qr.q().slice(s![.., 12..]).iter().all(|&q_ij| q_ij == 0.0));
+
+        // The truncated factorization stops at the dependent column as well.
+        assert_eq!(cholesky_qr.compute_truncated(&a, 20, 0.0).unwrap(), 12);
+    }
+
+    #[test]
+    fn ill_conditioned_matrix_breaks_down() {
+        // The Hilbert matrix of size 12 has a condition number of about 1e16.
+        let a = hilbert(12);
+        let mut cholesky_qr = CholeskyQr::from_matrix(&a).unwrap();
+        match cholesky_qr.compute(&a) {
+            Err(Error::RankDeficient { .. }) => {},
+            other => panic!("expected a rank deficient matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn second_pass_restores_orthogonality() {
+        // A = U·Σ·Vᵀ with singular values between 1 and 1e-6, whose squared condition number
+        // leaves a single pass far from orthonormal.
+        let mut rng = StdRng::seed_from_u64(17);
+        let u = Reorthogonalized::compute_once(&matrix(1000, 20)).unwrap().0;
+        let v = Reorthogonalized::compute_once(&Array2::random_using((20, 20), Normal::new(0.0, 1.0), &mut rng)).unwrap().0;
+        let sigma = Array1::from_shape_fn(20, |j| 10f64.powf(-6.0 * j as f64 / 19.0));
+        let a = (u * &sigma).dot(&v.t());
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            let mut single = CholeskyQr::from_matrix(*a).unwrap();
+            single.compute(*a).unwrap();
+            assert!(orthogonality_loss(single.q()) > 1e-6);
+
+            for &transpose_r in &[false, true] {
+                let column_major = get_layout(*a) == Some(blas::Layout::ColumnMajor);
+                let mut double = CholeskyQr::from_shape_with_r_transposed(a.dim().set_f(column_major), transpose_r).unwrap();
+                double.set_double(true);
+                assert!(double.is_double());
+                double.compute(*a).unwrap();
+                assert!(orthogonality_loss(double.q()) < 1e-13);
+                assert!(a.all_close(&double.q().dot(double.r()), 1e-12));
+                assert!(double.r().slice(s![1.., ..1]).iter().all(|&r_ij| r_ij == 0.0));
+            }
+        }
+
+        let a = hilbert(12);
+        let mut double = CholeskyQr::from_matrix(&a).unwrap();
+        double.set_double(true);
+        match double.compute(&a) {
+            Err(Error::RankDeficient { .. }) => {},
+            other => panic!("expected a rank deficient matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shifted_pass_prevents_breakdown() {
+        // A = U·Σ·Vᵀ with singular values between 1 and 1e-12, whose Gram matrix is not
+        // numerically positive definite.
+        let mut rng = StdRng::seed_from_u64(19);
+        let u = Reorthogonalized::compute_once(&matrix(1000, 20)).unwrap().0;
+        let v = Reorthogonalized::compute_once(&Array2::random_using((20, 20), Normal::new(0.0, 1.0), &mut rng)).unwrap().0;
+        let sigma = Array1::from_shape_fn(20, |j| 10f64.powf(-12.0 * j as f64 / 19.0));
+        let a = (u * &sigma).dot(&v.t());
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            let mut double = CholeskyQr::from_matrix(*a).unwrap();
+            double.set_double(true);
+            match double.compute(*a) {
+                Err(Error::RankDeficient { .. }) => {},
+                other => panic!("expected a rank deficient matrix, got {:?}", other),
+            }
+
+            for &transpose_r in &[false, true] {
+                let column_major = get_layout(*a) == Some(blas::Layout::ColumnMajor);
+                let mut shifted = CholeskyQr::from_shape_with_r_transposed(a.dim().set_f(column_major), transpose_r).unwrap();
+                shifted.set_double(true);
+                shifted.set_shifted(true);
+                assert!(shifted.is_shifted());
+                shifted.compute(*a).unwrap();
+                assert!(orthogonality_loss(shifted.q()) < 1e-13);
+                assert!(a.all_close(&shifted.q().dot(shifted.r()), 1e-12));
+                assert!(shifted.r().slice(s![1.., ..1]).iter().all(|&r_ij| r_ij == 0.0));
+            }
+        }
+
+        // Dependent columns are still detected by the unshifted pass.
+        let mut a = matrix(1000, 20);
+        let dependent = &a.column(3) - &a.column(7);
+        a.column_mut(12).assign(&dependent);
+        let mut shifted = CholeskyQr::from_matrix(&a).unwrap().with_tolerance(1e-6);
+        shifted.set_shifted(true);
+        match shifted.compute(&a) {
+            Err(Error::RankDeficient { column: 12 }) => {},
+            other => panic!("expected a rank deficient matrix, got {:?}", other),
+        }
+        let leading = shifted.q().slice(s![.., ..12]).dot(&shifted.r().slice(s![..12, ..12]));
+        assert!(leading.all_close(&a.slice(s![.., ..12]), 1e-12));
+        assert!(shifted.q().slice(s![.., 12..]).iter().all(|&q_ij| q_ij == 0.0));
+        assert!(shifted.r().slice(s![.., 12..]).iter().all(|&r_ij| r_ij == 0.0));
+    }
+
+    #[test]
+    fn incompatible_layout_is_rejected() {
+        let a = matrix(1000, 20);
+        let mut cholesky_qr = CholeskyQr::from_shape(a.dim().f()).unwrap();
+        match cholesky_qr.compute(&a) {
+            Err(Error::IncompatibleLayouts) => {},
+            other => panic!("expected incompatible layouts, got {:?}", other),
+        }
+    }
+}
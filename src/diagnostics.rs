@@ -0,0 +1,231 @@
+//! Checks of the results of a factorization, which are useful to validate matrices computed
+//! elsewhere as well.
+
+use ndarray::{
+    Data,
+    ShapeBuilder,
+};
+use ndarray::prelude::*;
+
+use crate::{
+    GramSchmidt,
+    PreparedInput,
+    Reorthogonalized,
+    blas,
+    utils,
+};
+
+/// Returns whether the columns of `q` are orthonormal, i.e. whether every entry of `QᵀQ` differs
+/// from the corresponding entry of the identity by at most `tol`.
+///
+/// The Gram matrix `QᵀQ` is computed by a single symmetric rank k update (blas `dsyrk`). Matrices
+/// which are not stored contiguously are copied first.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::diagnostics::is_orthogonal;
+/// use ndarray::{arr2, Array2};
+///
+/// assert!(is_orthogonal(&Array2::<f64>::eye(4), 1e-15));
+///
+/// // A rotation by 90 degrees, stored in column major order.
+/// assert!(is_orthogonal(&arr2(&[[0.0, 1.0], [-1.0, 0.0]]).t(), 1e-15));
+///
+/// assert!(!is_orthogonal(&arr2(&[[1.0, 1.0], [0.0, 1.0]]), 1e-15));
+/// assert!(!is_orthogonal(&arr2(&[[1.0, 0.0], [1.0, 1.0]]).t(), 1e-15));
+/// ```
+pub fn is_orthogonal<S>(q: &ArrayBase<S, Ix2>, tol: f64) -> bool
+    where S: Data<Elem = f64>
+{
+    gram_upper(q).indexed_iter()
+        .filter(|&((i, j), _)| i <= j)
+        .all(|((i, j), &g_ij)| {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            (g_ij - expected).abs() <= tol
+        })
+}
+
+/// Returns the Gram matrix `AᵀA` of `a`, whose entries are the inner products of the columns of
+/// `a`, e.g. to analyze their conditioning.
+///
+/// Only the upper triangle is computed by a single symmetric rank k update (blas `dsyrk`), which
+/// takes half the operations of the general product `a.t().dot(&a)`, and then mirrored onto the
+/// lower one. The result is stored in the layout of `a`. Matrices which are not stored
+/// contiguously are copied first.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::diagnostics::gram_matrix;
+/// use ndarray::arr2;
+///
+/// let a = arr2(&[[1.0, 2.0],
+///                [0.0, 1.0],
+///                [1.0, 0.0]]);
+/// assert_eq!(gram_matrix(&a), arr2(&[[2.0, 2.0], [2.0, 5.0]]));
+/// ```
+pub fn gram_matrix<S>(a: &ArrayBase<S, Ix2>) -> Array2<f64>
+    where S: Data<Elem = f64>
+{
+    let mut gram = gram_upper(a);
+    for j in 0..gram.cols() {
+        for i in (j + 1)..gram.rows() {
+            gram[(i, j)] = gram[(j, i)];
+        }
+    }
+    gram
+}
+
+/// Returns whether the columns of `a` are linearly independent, i.e. whether `a` has full column
+/// rank numerically.
+///
+/// The matrix is factorized by [`Reorthogonalized`], and its columns are considered independent
+/// if the smallest diagonal entry of R exceeds its default [`tolerance`], `max(m, n)·ε`, times
+/// the largest one, just like the [`Qr::rank`] of [`qr`]. Matrices with fewer rows than columns, and those with
+/// entries which are not finite, are never of full column rank. Matrices which are not stored
+/// contiguously are copied first.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::diagnostics::is_full_rank;
+/// use ndarray::arr2;
+///
+/// assert!(is_full_rank(&arr2(&[[1.0, 2.0], [0.0, 1.0], [1.0, 0.0]])));
+/// // The second column is twice the first one.
+/// assert!(!is_full_rank(&arr2(&[[1.0, 2.0], [0.0, 0.0], [1.0, 2.0]])));
+/// ```
+///
+/// [`Reorthogonalized`]: ../struct.Reorthogonalized.html
+/// [`tolerance`]: ../trait.GramSchmidt.html#tymethod.set_tolerance
+/// [`Qr::rank`]: ../struct.Qr.html#method.rank
+/// [`qr`]: ../fn.qr.html
+pub fn is_full_rank<S>(a: &ArrayBase<S, Ix2>) -> bool
+    where S: Data<Elem = f64>
+{
+    let (n_rows, n_cols) = a.dim();
+    if n_rows < n_cols {
+        return false;
+    }
+
+    let input = PreparedInput::new(a);
+    let mut cgs2 = match Reorthogonalized::from_matrix(input.as_array()) {
+        Ok(cgs2) => cgs2,
+        Err(_) => return false,
+    };
+    // An exactly dependent column is rejected as rank deficient by the factorization itself.
+    if cgs2.compute(input.as_array()).is_err() {
+        return false;
+    }
+
+    let diagonal = cgs2.r().diag();
+    let largest = diagonal.fold(0.0, |acc: f64, r_jj| acc.max(r_jj.abs()));
+    let smallest = diagonal.fold(f64::INFINITY, |acc: f64, r_jj| acc.min(r_jj.abs()));
+    smallest > cgs2.tolerance() * largest
+}
+
+/// Returns the upper triangle of the Gram matrix `QᵀQ` in the layout of `q`, with the strict lower
+/// triangle set to zero.
+pub(crate) fn gram_upper<S>(q: &ArrayBase<S, Ix2>) -> Array2<f64>
+    where S: Data<Elem = f64>
+{
+    use blas::Layout::*;
+
+    let prepared;
+    let (q_slice, layout) = match utils::as_slice_with_layout(q) {
+        Some(q) => q,
+        None => {
+            prepared = PreparedInput::new(q);
+            // NOTE: This unwrap is safe, because the prepared copy is contiguous.
+            utils::as_slice_with_layout(prepared.as_array()).unwrap()
+        },
+    };
+
+    let (n_rows, n_cols) = q.dim();
+    let leading_dim = match layout {
+        ColumnMajor => n_rows,
+        RowMajor => n_cols,
+    };
+
+    // The Gram matrix shares the layout of q, which blas uses for both.
+    let mut gram = Array2::<f64>::zeros((n_cols, n_cols).set_f(layout == ColumnMajor));
+    if n_rows == 0 || n_cols == 0 {
+        return gram;
+    }
+    // NOTE: This unwrap is safe, because the Gram matrix is freshly allocated contiguously.
+    let gram_slice = gram.as_slice_memory_order_mut().unwrap();
+    unsafe {
+        blas::dsyrk(
+            layout,
+            blas::Part::Upper,
+            blas::Transpose::Ordinary,
+            n_cols as i32,
+            n_rows as i32,
+            1.0,
+            q_slice,
+            leading_dim as i32,
+            0.0,
+            gram_slice,
+            n_cols as i32,
+        );
+    }
+    gram
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::s;
+    use super::*;
+
+    fn small() -> Array2<f64> {
+        arr2(
+            &[[2.0, 0.5, 0.0, 0.0],
+              [0.0, 0.3, 0.0, 0.0],
+              [0.0, 1.0, 0.7, 0.0],
+              [0.0, 0.0, 0.0, 3.0]]
+        )
+    }
+
+    #[test]
+    fn gram_matrix_matches_product() {
+        let a = small();
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        for a in &[a, f_a] {
+            let gram = gram_matrix(a);
+            assert!(gram.all_close(&a.t().dot(a), 1e-15));
+            assert_eq!(gram, gram.t());
+            assert_eq!(gram.is_standard_layout(), a.is_standard_layout());
+        }
+
+        // Every second column, which is not contiguous.
+        let view = small();
+        let view = view.slice(s![.., ..;2]);
+        assert!(gram_matrix(&view).all_close(&view.t().dot(&view), 1e-15));
+    }
+
+    #[test]
+    fn rank_deficiency_is_detected() {
+        let mut a = small();
+        assert!(is_full_rank(&a));
+        assert!(is_full_rank(&a.t()));
+
+        let nearly_dependent = &a.column(0) * 3.0 + &a.column(1) * (1.0 + 1e-17);
+        a.column_mut(3).assign(&nearly_dependent);
+        assert!(!is_full_rank(&a));
+
+        assert!(!is_full_rank(&Array2::<f64>::zeros((2, 3))));
+        let mut non_finite = small();
+        non_finite[(1, 1)] = f64::NAN;
+        assert!(!is_full_rank(&non_finite));
+    }
+}
@@ -0,0 +1,178 @@
+use ndarray::{
+    Data,
+    Dim,
+    Ix,
+    ShapeBuilder,
+};
+use ndarray::prelude::*;
+
+use crate::{
+    Error,
+    GramSchmidt,
+    Modified,
+    Result,
+};
+
+/// Extends a borrowed orthonormal basis B by orthonormalizing the columns of matrices against
+/// it and against each other.
+///
+/// For a matrix A this computes the factorization `A = B·C + Q·R`, where the columns of Q are
+/// orthonormal and orthogonal to the basis, R is upper triangular, and `C = BᵀA` contains the
+/// coefficients of the projections onto the basis. The basis is borrowed instead of copied, so
+/// that a large basis can be shared by many factorizations without duplicating it.
+///
+/// The projections onto the basis are removed twice, which makes Q orthogonal to B to working
+/// precision, before the remainder is orthonormalized with a [`Modified`] Gram Schmidt.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::Extension;
+/// use ndarray::{arr2, Array2};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let basis = arr2(&[[1.0], [0.0], [0.0]]);
+/// let mut extension = Extension::against_basis(basis.view(), (3, 2))?;
+///
+/// let a = arr2(&[[1.0, 2.0],
+///                [1.0, 0.0],
+///                [0.0, 3.0]]);
+/// extension.compute(&a)?;
+///
+/// assert!(basis.t().dot(extension.q()).all_close(&Array2::zeros((1, 2)), 1e-15));
+/// let reconstructed = basis.dot(extension.basis_coefficients()) + extension.q().dot(extension.r());
+/// assert!(a.all_close(&reconstructed, 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Modified`]: struct.Modified.html
+#[derive(Clone, Debug)]
+pub struct Extension<'a> {
+    basis: ArrayView2<'a, f64>,
+    basis_coefficients: Array2<f64>,
+    projected: Array2<f64>,
+    inner: Modified,
+}
+
+impl<'a> Extension<'a> {
+    /// Reserves the memory for factorizations of matrices with the given `shape` against the
+    /// orthonormal columns of `basis`, which is assumed but not checked to be orthonormal.
+    ///
+    /// Returns an error if the number of rows of the basis differs from the one of `shape`.
+    pub fn against_basis<T>(basis: ArrayView2<'a, f64>, shape: T) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let projected = Array2::zeros(shape);
+        let (n_rows, n_cols) = projected.dim();
+        if basis.rows() != n_rows {
+            Err(Error::ShapeMismatch { expected: (n_rows, basis.cols()), found: basis.dim() })?;
+        }
+
+        let inner = Modified::from_matrix(&projected)?;
+        let basis_coefficients = Array2::zeros((basis.cols(), n_cols));
+
+        Ok(Self {
+            basis,
+            basis_coefficients,
+            projected,
+            inner,
+        })
+    }
+
+    /// Computes the factorization `A = B·C + Q·R` of the matrix `a`, see the [type level
+    /// documentation](struct.Extension.html).
+    ///
+    /// Returns an error if the shape of `a` differs from the configured one, or if the
+    /// orthonormalization of the remainder fails.
+    pub fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = f64>,
+    {
+        if a.dim() != self.projected.dim() {
+            Err(Error::ShapeMismatch { expected: self.projected.dim(), found: a.dim() })?;
+        }
+
+        self.projected.assign(a);
+        self.basis_coefficients.fill(0.0);
+        for _ in 0..2 {
+            let coefficients = self.basis.t().dot(&self.projected);
+            self.projected -= &self.basis.dot(&coefficients);
+            self.basis_coefficients += &coefficients;
+        }
+
+        self.inner.compute(&self.projected)
+    }
+
+    /// Return a reference to the borrowed basis B.
+    pub fn basis(&self) -> &ArrayView2<'a, f64> {
+        &self.basis
+    }
+
+    /// Return a reference to the coefficients `C = BᵀA` of the projections onto the basis.
+    pub fn basis_coefficients(&self) -> &Array2<f64> {
+        &self.basis_coefficients
+    }
+
+    /// Return a reference to the matrix q.
+    pub fn q(&self) -> &Array2<f64> {
+        self.inner.q()
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<f64> {
+        self.inner.r()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use ndarray::s;
+    use crate::{
+        GramSchmidt,
+        Reorthogonalized,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn extension_continues_full_factorization() {
+        // Orthonormalizing the last columns against the Q of the first ones gives the same
+        // result as orthonormalizing all columns at once.
+        let a = matrix(5, 5);
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.compute(&a).unwrap();
+
+        let full_q = cgs2.q();
+        let basis = full_q.slice(s![.., ..2]);
+        let rest = a.slice(s![.., 2..]);
+        let mut extension = Extension::against_basis(basis, rest.dim()).unwrap();
+        extension.compute(&rest).unwrap();
+
+        assert!(extension.q().all_close(&full_q.slice(s![.., 2..]), 1e-13));
+        assert!(extension.r().all_close(&cgs2.r().slice(s![2.., 2..]), 1e-13));
+        assert!(extension.basis_coefficients().all_close(&cgs2.r().slice(s![..2, 2..]), 1e-13));
+    }
+
+    #[test]
+    fn mismatched_shapes_are_rejected() {
+        let basis = Array2::<f64>::eye(4);
+        match Extension::against_basis(basis.view(), (5, 2)) {
+            Err(Error::ShapeMismatch { expected: (5, 4), found: (4, 4) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+
+        let basis = Array2::<f64>::eye(5);
+        let mut extension = Extension::against_basis(basis.slice(s![.., ..1]), (5, 2)).unwrap();
+        match extension.compute(&matrix(5, 5)) {
+            Err(Error::ShapeMismatch { expected: (5, 2), found: (5, 5) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+    }
+}
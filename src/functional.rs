@@ -0,0 +1,210 @@
+use ndarray::{
+    Data,
+    Dim,
+    Ix,
+    ShapeBuilder,
+};
+use ndarray::prelude::*;
+use std::fmt;
+
+use crate::{
+    Error,
+    Result,
+};
+
+/// A modified Gram Schmidt factorization with respect to an arbitrary, user supplied inner
+/// product `⟨x, y⟩`. The norm of a vector is taken to be `√⟨x, x⟩`.
+///
+/// The resulting Q is orthonormal with respect to the supplied inner product, i.e.
+/// `⟨q_i, q_j⟩ = δ_ij`, and `A = QR` holds as usual. This allows to orthonormalize vectors
+/// representing functions, e.g. with an inner product computed by quadrature.
+///
+/// Since the inner product is an opaque closure, this procedure does not use blas and is thus
+/// considerably slower than the others. For the same reason it can't be constructed from a
+/// shape alone and thus does not implement the [`GramSchmidt` trait].
+///
+/// # Example
+///
+/// ```
+/// use gramschmidt::Functional;
+/// use ndarray::{arr1, arr2, Array2, Axis};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let weights = arr1(&[1.0, 2.0, 0.5]);
+/// let mut functional = Functional::new((3, 3), |x, y| (&x * &y * &weights).sum())?;
+///
+/// let a = arr2(&[[1.0, 1.0, 0.0],
+///                [0.0, 1.0, 1.0],
+///                [1.0, 0.0, 1.0]]);
+/// functional.compute(&a)?;
+///
+/// let q = functional.q();
+/// let weighted_q = q * &weights.view().insert_axis(Axis(1));
+/// assert!(q.t().dot(&weighted_q).all_close(&Array2::eye(3), 1e-14));
+/// assert!(a.all_close(&q.dot(functional.r()), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`GramSchmidt` trait]: GramSchmidt
+#[derive(Clone)]
+pub struct Functional<F> {
+    q: Array2<f64>,
+    r: Array2<f64>,
+    inner_product: F,
+}
+
+impl<F> Functional<F>
+    where F: Fn(ArrayView1<f64>, ArrayView1<f64>) -> f64
+{
+    /// Reserves the memory for a QR decomposition of matrices with the given `shape`, which are
+    /// to be orthonormalized with respect to `inner_product`.
+    pub fn new<T>(shape: T, inner_product: F) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let q = Array2::zeros(shape);
+        let (_, n_cols) = q.dim();
+        let r = Array2::zeros((n_cols, n_cols).set_f(!q.is_standard_layout()));
+
+        Ok(Self {
+            q,
+            r,
+            inner_product,
+        })
+    }
+
+    /// Computes a QR decomposition of the matrix `a`, orthonormalizing its columns with respect
+    /// to the configured inner product.
+    ///
+    /// Returns an error if the shape of `a` differs from the configured one, or if a column has
+    /// a non-positive norm after removing its projections onto the previous columns.
+    pub fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = f64>,
+    {
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        self.r.fill(0.0);
+        let n_cols = a.cols();
+        for i in 0..n_cols {
+            let (q_done, mut q_todo) = self.q.view_mut().split_at(Axis(1), i);
+            let mut q_todo_column = q_todo.column_mut(0);
+            q_todo_column.assign(&a.column(i));
+
+            for (j, q_done_column) in q_done.gencolumns().into_iter().enumerate() {
+                let projection_factor = (self.inner_product)(q_done_column.view(), q_todo_column.view());
+                self.r[(j, i)] = projection_factor;
+                q_todo_column.scaled_add(-projection_factor, &q_done_column);
+            }
+
+            let norm_squared = (self.inner_product)(q_todo_column.view(), q_todo_column.view());
+            if norm_squared <= 0.0 || norm_squared.is_nan() {
+                Err(Error::RankDeficient { column: i })?;
+            }
+            let norm = norm_squared.sqrt();
+            self.r[(i, i)] = norm;
+            q_todo_column /= norm;
+        }
+
+        Ok(())
+    }
+
+    /// Return a reference to the matrix q.
+    pub fn q(&self) -> &Array2<f64> {
+        &self.q
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<f64> {
+        &self.r
+    }
+}
+
+impl<F> fmt::Debug for Functional<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Functional")
+            .field("q", &self.q)
+            .field("r", &self.r)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::{
+        GramSchmidt,
+        Modified,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn euclidean_inner_product_equals_modified() {
+        let a = matrix(4, 4);
+        let mut functional = Functional::new(a.dim(), |x, y| x.dot(&y)).unwrap();
+        functional.compute(&a).unwrap();
+
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+
+        assert!(mgs.q().all_close(functional.q(), 1e-14));
+        assert!(mgs.r().all_close(functional.r(), 1e-14));
+    }
+
+    #[test]
+    fn quadrature_inner_product_orthonormalizes_monomials() {
+        // Monomials 1, x, x² sampled on a grid over [-1, 1], orthonormalized with respect to the
+        // trapezoidal rule, yield (discretized) normalized Legendre polynomials.
+        let n = 201;
+        let h = 2.0 / (n - 1) as f64;
+        let xs = Array1::<f64>::linspace(-1.0, 1.0, n);
+        let a = Array2::from_shape_fn((n, 3), |(i, j)| xs[i].powi(j as i32));
+        let mut weights = Array1::from_elem(n, h);
+        weights[0] /= 2.0;
+        weights[n - 1] /= 2.0;
+
+        let mut functional = Functional::new(a.dim(), |x, y| (&x * &y * &weights).sum()).unwrap();
+        functional.compute(&a).unwrap();
+
+        let q = functional.q();
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                let product = (&q.column(i) * &q.column(j) * &weights).sum();
+                assert!((product - expected).abs() < 1e-12);
+            }
+        }
+        assert!(a.all_close(&q.dot(functional.r()), 1e-12));
+
+        // The second basis vector is x scaled to unit L² norm, i.e. √(3/2)·x.
+        assert!(q.column(1).all_close(&(&xs * 1.5f64.sqrt()), 1e-3));
+    }
+
+    #[test]
+    fn dependent_column_is_rank_deficient() {
+        // Without a tolerance, only a dependence that cancels exactly is detected.
+        let mut a = Array2::eye(4);
+        a[(0, 2)] = 1.0;
+        a[(2, 2)] = 0.0;
+        let mut functional = Functional::new(a.dim(), |x, y| x.dot(&y)).unwrap();
+        match functional.compute(&a) {
+            Err(Error::RankDeficient { column: 2 }) => {},
+            _ => panic!("expected a rank deficient column"),
+        }
+    }
+
+    #[test]
+    fn mismatched_shape_is_rejected() {
+        let mut functional = Functional::new((3, 3), |x, y| x.dot(&y)).unwrap();
+        match functional.compute(&matrix(4, 4)) {
+            Err(Error::ShapeMismatch { expected: (3, 3), found: (4, 4) }) => {},
+            _ => panic!("expected a shape mismatch"),
+        }
+    }
+}
@@ -0,0 +1,779 @@
+use ndarray::{
+    Data,
+    Dim,
+    Ix,
+    ShapeBuilder,
+    Zip,
+    s,
+};
+use ndarray::prelude::*;
+use num_traits::Float;
+use std::time::Instant;
+
+use crate::{
+    ColumnProvenance,
+    ComputeStatus,
+    Error,
+    GramSchmidt,
+    GramSchmidtScalar,
+    Result,
+    StabilityClass,
+    blas,
+    utils::{
+        check_finite,
+        check_pinned,
+        check_rank,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        deadline_passed,
+        default_tolerance,
+        force_positive_diagonal,
+        get_layout,
+        is_broadcast,
+        last_flops,
+        normalization,
+        observe_columns,
+        parts_layouts,
+        pin_columns,
+        provenance,
+        rescale_columns,
+        reset_factors,
+        reuse_buffer,
+        transpose_layout,
+    },
+};
+
+/// Returns `(c, s, ρ)` such that the plane rotation `[[c̄, s̄], [-s, c]]` maps the vector `(a, b)`
+/// onto `(ρ, 0)`, with `ρ = √(|a|² + |b|²) ≥ 0`. For real scalars, the conjugations are void.
+pub(crate) fn rotation<T: GramSchmidtScalar>(a: T, b: T) -> (T, T, T) {
+    let rho = T::from_real(a.modulus().hypot(b.modulus()));
+    if rho.is_zero() {
+        (T::one(), T::zero(), T::zero())
+    } else {
+        (a / rho, b / rho, rho)
+    }
+}
+
+/// Applies the plane rotation `(c, s)` to the pair of vectors `(x, y)`, i.e. sets
+/// `x ← c̄·x + s̄·y` and `y ← -s·x + c·y`.
+pub(crate) fn rotate<T: GramSchmidtScalar>(mut x: ArrayViewMut1<T>, mut y: ArrayViewMut1<T>, c: T, s: T) {
+    Zip::from(&mut x).and(&mut y).apply(|x, y| {
+        let (x_old, y_old) = (*x, *y);
+        *x = c.conj() * x_old + s.conj() * y_old;
+        *y = -s * x_old + c * y_old;
+    });
+}
+
+/// Rotates the rows `i` and `i+1` of `r` and the columns `i` and `i+1` of `q` such that the
+/// product `QR` stays invariant.
+fn rotate_pair<T: GramSchmidtScalar>(q: &mut Array2<T>, r: &mut Array2<T>, i: usize, c: T, s: T) {
+    {
+        let (mut upper, mut lower) = r.view_mut().split_at(Axis(0), i + 1);
+        rotate(upper.row_mut(i), lower.row_mut(0), c, s);
+    }
+    {
+        let (mut left, mut right) = q.view_mut().split_at(Axis(1), i + 1);
+        // Q is multiplied by the adjoint of the rotation applied to R.
+        rotate(left.column_mut(i), right.column_mut(0), c.conj(), s.conj());
+    }
+}
+
+/// Updates the factorization `A = QR` with a square, orthogonal `q` to the factorization of
+/// `A + u·vᵀ` using `2(n-1)` plane rotations, see Golub and Van Loan, *Matrix Computations*,
+/// section 12.5.1.
+pub(crate) fn rank_one_update<S, T, A>(
+    q: &mut Array2<A>,
+    r: &mut Array2<A>,
+    u: &ArrayBase<S, Ix1>,
+    v: &ArrayBase<T, Ix1>,
+)
+    where S: Data<Elem=A>,
+          T: Data<Elem=A>,
+          A: GramSchmidtScalar,
+{
+    let n = r.rows();
+    let mut w = A::adjoint_dot(q.view(), u.view());
+
+    // Reduce w to a multiple of the first unit vector, rotating R into upper Hessenberg form.
+    for i in (0..n.saturating_sub(1)).rev() {
+        let (c, s, rho) = rotation(w[i], w[i + 1]);
+        w[i] = rho;
+        w[i + 1] = A::zero();
+        rotate_pair(q, r, i, c, s);
+    }
+
+    r.row_mut(0).scaled_add(w[0], v);
+
+    // Restore the upper triangular form of the Hessenberg matrix R.
+    for i in 0..n.saturating_sub(1) {
+        let (c, s, _) = rotation(r[(i, i)], r[(i + 1, i)]);
+        rotate_pair(q, r, i, c, s);
+        r[(i + 1, i)] = A::zero();
+    }
+}
+
+/// Updates the factorization `A = QR` with a square, orthogonal `q` to the factorization of
+/// `A + U·Vᵀ` by successive rank one updates. See
+/// [`GramSchmidt::low_rank_update`](crate::GramSchmidt::low_rank_update).
+pub(crate) fn low_rank_update<S, T, A>(
+    q: &mut Array2<A>,
+    r: &mut Array2<A>,
+    u: &ArrayBase<S, Ix2>,
+    v: &ArrayBase<T, Ix2>,
+) -> Result<()>
+    where S: Data<Elem=A>,
+          T: Data<Elem=A>,
+          A: GramSchmidtScalar,
+{
+    let (n_rows, n_cols) = q.dim();
+    if n_rows != n_cols {
+        Err(Error::NotSquare)?;
+    }
+    if u.rows() != n_rows {
+        Err(Error::ShapeMismatch { expected: (n_rows, u.cols()), found: u.dim() })?;
+    }
+    if v.dim() != (n_cols, u.cols()) {
+        Err(Error::ShapeMismatch { expected: (n_cols, u.cols()), found: v.dim() })?;
+    }
+
+    for (u_column, v_column) in u.gencolumns().into_iter().zip(v.gencolumns()) {
+        rank_one_update(q, r, &u_column, &v_column);
+    }
+
+    Ok(())
+}
+
+/// Turns the factorization `A = QR` into the one of `A` without its column `index`, see Golub and
+/// Van Loan, *Matrix Computations*, section 12.5.2.
+///
+/// Deleting the column of R leaves an upper Hessenberg matrix, whose triangular form is restored
+/// by `n - index - 1` plane rotations. Its last row and the last column of Q then vanish from the
+/// product and are dropped. Both matrices keep their memory layouts.
+///
+/// Returns [`Error::ColumnOutOfBounds`] for an `index` which is not smaller than the number of
+/// columns, and [`Error::InvalidShape`] if the only column would be removed. Q and R are left
+/// unchanged in both cases.
+pub(crate) fn remove_column<A>(q: &mut Array2<A>, r: &mut Array2<A>, index: usize) -> Result<()>
+    where A: GramSchmidtScalar,
+{
+    use blas::Layout::ColumnMajor;
+
+    let (n_rows, n_cols) = q.dim();
+    if index >= n_cols {
+        Err(Error::ColumnOutOfBounds { index, cols: n_cols })?;
+    }
+    if n_cols == 1 {
+        Err(Error::InvalidShape { rows: n_rows, cols: 0 })?;
+    }
+
+    let r_column_major = crate::utils::get_layout(r) == Some(ColumnMajor);
+    let mut hessenberg = Array2::zeros((n_cols, n_cols - 1).set_f(r_column_major));
+    hessenberg.slice_mut(s![.., ..index]).assign(&r.slice(s![.., ..index]));
+    hessenberg.slice_mut(s![.., index..]).assign(&r.slice(s![.., index + 1..]));
+
+    for i in index..n_cols - 1 {
+        let (c, s, _) = rotation(hessenberg[(i, i)], hessenberg[(i + 1, i)]);
+        rotate_pair(q, &mut hessenberg, i, c, s);
+        hessenberg[(i + 1, i)] = A::zero();
+    }
+
+    let q_column_major = crate::utils::get_layout(q) == Some(ColumnMajor);
+    let mut shrunk_q = Array2::zeros((n_rows, n_cols - 1).set_f(q_column_major));
+    shrunk_q.assign(&q.slice(s![.., ..n_cols - 1]));
+    let mut shrunk_r = Array2::zeros((n_cols - 1, n_cols - 1).set_f(r_column_major));
+    shrunk_r.assign(&hessenberg.slice(s![..n_cols - 1, ..]));
+    *q = shrunk_q;
+    *r = shrunk_r;
+
+    Ok(())
+}
+
+/// A QR decomposition by plane (Givens) rotations, which zero the subdiagonal entries of the
+/// matrix one at a time and are accumulated into Q. See its [Wikipedia entry] for more
+/// information.
+///
+/// Like Householder reflections, the rotations are orthogonal, so that Q is orthonormal up to
+/// machine precision regardless of the condition number of the input. Dense matrices take about
+/// three times as many operations as the classical Gram Schmidt procedure, but entries which are
+/// already zero are skipped: an upper Hessenberg matrix, which only has a single nonzero entry
+/// below the diagonal of every column, is factorized with `n - 1` rotations in `O(n²)`
+/// operations instead of `O(n³)`.
+///
+/// Use this struct via the [`GramSchmidt` trait].
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{Givens, GramSchmidt};
+/// use ndarray::Array2;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// // An upper Hessenberg matrix.
+/// let a = Array2::from_shape_fn((50, 50), |(i, j)| if i <= j + 1 { 1.0 / (1 + i + j) as f64 } else { 0.0 });
+/// let mut givens = Givens::from_matrix(&a)?;
+/// givens.compute(&a)?;
+/// assert_eq!(givens.rotations(), 49);
+/// assert!(a.all_close(&givens.q().dot(givens.r()), 1e-12));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [Wikipedia entry]: https://en.wikipedia.org/wiki/Givens_rotation
+/// [`GramSchmidt` trait]: GramSchmidt
+#[derive(Clone, Debug)]
+pub struct Givens<A = f64> {
+    q: Array2<A>,
+    r: Array2<A>,
+    memory_layout: blas::Layout,
+    /// The column currently being rotated into upper triangular form.
+    work: Array1<A>,
+    /// The rotations `(i, c, s)` of the rows `i` and `i+1` applied so far, in order.
+    rotations: Vec<(usize, A, A)>,
+    /// For every rotated column after the pinned ones, the number of rotations applied before it,
+    /// and the phase moved from its diagonal entry of R into Q.
+    columns: Vec<(usize, A)>,
+    pinned: usize,
+    sign_flipped: Vec<bool>,
+    computed: bool,
+    tolerance: Option<f64>,
+    skip_finite_check: bool,
+}
+
+impl<A: GramSchmidtScalar> GramSchmidt for Givens<A> {
+    type Scalar = A;
+
+    const STABILITY: StabilityClass = StabilityClass::High;
+    const COST_FACTOR: f64 = 3.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let shape = shape.into_shape();
+        let q = Array2::zeros(shape);
+        let memory_layout = match get_layout(&q) {
+            Some(layout) => layout,
+            None => Err(Error::NonContiguous)?,
+        };
+        check_shape(q.dim())?;
+
+        let (n_rows, n_cols) = q.dim();
+
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
+        let r = Array2::zeros(
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
+        );
+
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            work: Array1::zeros(n_rows),
+            rotations: Vec::new(),
+            columns: Vec::new(),
+            pinned: 0,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+        })
+    }
+
+    fn from_parts(q: Array2<A>, r: Array2<A>) -> Result<Self> {
+        let (memory_layout, _) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        let work = Array1::zeros(q.rows());
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            work,
+            rotations: Vec::new(),
+            columns: Vec::new(),
+            pinned: 0,
+            sign_flipped,
+            computed: false,
+            tolerance: None,
+            skip_finite_check: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<A>(self.q.rows(), self.q.cols()))
+    }
+
+    fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, 0, None, |_, _| true).map(|_| ())
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, next_col, Some(deadline), |_, _| true)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, start, None, |_, _| true).map(|_| ())
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: FnMut(usize, A::Real) -> bool
+    {
+        self.compute_from(a, 0, None, observe)
+    }
+
+    fn q(&self) -> &Array2<A> {
+        &self.q
+    }
+
+    fn r(&self) -> &Array2<A> {
+        &self.r
+    }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(self.pinned, &self.sign_flipped, |_| false)
+    }
+
+    fn last_flops(&self) -> u64 {
+        last_flops(self.computed, self.q.rows(), &self.provenance(), Self::COST_FACTOR)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = A>,
+              T: Data<Elem = A>
+    {
+        low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        if index < self.pinned {
+            self.pinned -= 1;
+        }
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+    }
+}
+
+impl<A: GramSchmidtScalar> Givens<A> {
+    /// Returns the number of plane rotations applied by the last factorization.
+    ///
+    /// Only nonzero subdiagonal entries are rotated away, so that this is at most
+    /// `mn - n(n+1)/2` for an `m×n` matrix, and `n - 1` for a square upper Hessenberg matrix.
+    pub fn rotations(&self) -> usize {
+        self.rotations.len()
+    }
+
+    /// Sets the number of leading columns which are pinned.
+    ///
+    /// The columns `0..pinned` of the matrices passed to `compute` are then taken to be an
+    /// already orthonormal set: they are copied into Q unchanged, and the remaining columns are
+    /// projected onto their orthogonal complement before being rotated. The corresponding block of
+    /// R is the identity. Computing returns [`Error::NotOrthonormal`] if the pinned columns
+    /// deviate from orthonormality by more than `1e-10`.
+    ///
+    /// Returns [`Error::TooManyPinned`] if `pinned` exceeds the number of columns, leaving the
+    /// number of pinned columns unchanged.
+    ///
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    /// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+    pub fn set_pinned(&mut self, pinned: usize) -> Result<()> {
+        check_pinned(self.q.cols(), pinned)?;
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// Returns the number of leading columns which are pinned. See [`set_pinned`] for details.
+    ///
+    /// [`set_pinned`]: #method.set_pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped.
+    ///
+    /// By default, [`compute`] scans the input once before factorizing it, and returns
+    /// [`Error::NonFinite`] for the first NaN or infinite entry, which would otherwise silently
+    /// propagate into Q and R. Skipping the scan saves a pass over the input in hot loops whose
+    /// inputs are known to be finite. Resuming a computation with `continue_from` never scans.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Resizes the factorization for matrices of a new `shape` like [`GramSchmidt::from_shape`],
+    /// but reuses the memory of Q and R where possible.
+    ///
+    /// The memory layout is derived from `shape` anew, and R keeps being stored in the same or the
+    /// opposite layout. All settings are kept, except that the number of pinned columns is reduced
+    /// to the new number of columns if necessary. Returns [`Error::Underdetermined`] for a wide
+    /// shape and [`Error::InvalidShape`] for one without rows or columns, leaving the
+    /// factorization unchanged.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn reset_shape<T>(&mut self, shape: T) -> Result<()>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        let transpose_r = get_layout(&self.r) != Some(self.memory_layout);
+        let (memory_layout, _) = reset_factors(&mut self.q, &mut self.r, shape, transpose_r)?;
+        self.memory_layout = memory_layout;
+        reuse_buffer(&mut self.work, self.q.rows().into_shape());
+        self.sign_flipped.clear();
+        self.sign_flipped.resize(self.q.cols(), false);
+        self.computed = false;
+        self.pinned = self.pinned.min(self.q.cols());
+        Ok(())
+    }
+
+    /// Triangularizes the columns `start..` of `a`, assuming that the columns `0..start` have
+    /// already been processed. Returns early if the optional `deadline` has passed, after at least
+    /// one column has been processed, or once `observe` returns `false` for a processed column.
+    ///
+    /// Every column is rotated by all previous rotations when it is reached, so that the later
+    /// columns of `a` may change between calls.
+    fn compute_from<S, O>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        start: usize,
+        deadline: Option<Instant>,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        if is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        let (n_rows, n_cols) = a.dim();
+        let pinned = self.pinned;
+
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        // The signs of the columns computed anew are determined by the procedure again.
+        clear_sign_flips(&mut self.sign_flipped, if start < pinned { 0 } else { start });
+        self.computed = true;
+
+        // Pinned columns are not rotated, but only copied.
+        let start = if start < pinned {
+            pin_columns(&mut self.q, &mut self.r, a, pinned)?;
+            if let Some(status) = observe_columns(&self.r, 0..pinned, &mut observe) {
+                return Ok(status);
+            }
+            pinned
+        } else {
+            start
+        };
+
+        // Discard the rotations of the columns which are processed anew.
+        let done = start - pinned;
+        if let Some(&(offset, _)) = self.columns.get(done) {
+            self.rotations.truncate(offset);
+        }
+        self.columns.truncate(done);
+
+        for j in start..n_cols {
+            if j > start && deadline_passed(deadline) {
+                return Ok(ComputeStatus::InProgress { next_col: j });
+            }
+
+            // The component orthogonal to the pinned columns is rotated from the first row on.
+            // Projecting twice keeps it orthogonal to machine precision.
+            let k = j - pinned;
+            self.work.assign(&a.column(j));
+            self.r.column_mut(j).fill(A::zero());
+            for _ in 0..2 {
+                for i in 0..pinned {
+                    let projection_factor = A::dotc(self.q.column(i), self.work.view());
+                    self.r[(i, j)] += projection_factor;
+                    self.work.scaled_add(-projection_factor, &self.q.column(i));
+                }
+            }
+
+            let offset = self.rotations.len();
+            for &(i, c, s) in &self.rotations {
+                let (x, y) = (self.work[i], self.work[i + 1]);
+                self.work[i] = c.conj() * x + s.conj() * y;
+                self.work[i + 1] = -s * x + c * y;
+            }
+
+            // Zero the subdiagonal entries from the bottom up, skipping those which already are.
+            for i in (k + 1..n_rows).rev() {
+                if self.work[i].is_zero() {
+                    continue;
+                }
+                let (c, s, rho) = rotation(self.work[i - 1], self.work[i]);
+                self.work[i - 1] = rho;
+                self.work[i] = A::zero();
+                self.rotations.push((i - 1, c, s));
+            }
+
+            let norm = self.work[k].modulus();
+            check_rank(j, norm, self.tolerance(), || normalization(&a.column(j)))?;
+
+            // The phase of the diagonal entry is moved into Q to keep the diagonal of R real and
+            // positive, which is why the entries above it are divided by the phases of their rows.
+            let phase = self.work[k] / A::from_real(norm);
+            self.columns.push((offset, phase));
+            for (l, &(_, row_phase)) in self.columns[..k].iter().enumerate() {
+                self.r[(pinned + l, j)] = row_phase.conj() * self.work[l];
+            }
+            self.r[(j, j)] = A::from_real(norm);
+
+            // The column j of Q is the product of the adjoints of all rotations applied to the
+            // unit vector e_k. Later rotations act on rows below k and leave it invariant.
+            let mut q_column = self.q.column_mut(j);
+            q_column.fill(A::zero());
+            q_column[k] = phase;
+            for &(i, c, s) in self.rotations.iter().rev() {
+                let (x, y) = (q_column[i], q_column[i + 1]);
+                q_column[i] = c * x - s.conj() * y;
+                q_column[i + 1] = s * x + c.conj() * y;
+            }
+
+            if let Some(status) = observe_columns(&self.r, j..j + 1, &mut observe) {
+                return Ok(status);
+            }
+        }
+
+        Ok(ComputeStatus::Done)
+    }
+}
+
+#[cfg(test)]
+generate_tests!(
+    Givens,
+    1e-13,
+    precisions: [double: f64 => 1e-13, single: f32 => 1e-4]
+);
+
+#[cfg(test)]
+mod stability_tests {
+    extern crate openblas_src;
+
+    use crate::utils::{
+        check_conditioned_matrices,
+        check_hilbert_orthogonality,
+        check_identity_plus_noise_orthogonality,
+    };
+    use super::*;
+
+    // Rotations preserve orthogonality regardless of the condition number.
+    #[test]
+    fn hilbert_orthogonality() {
+        check_hilbert_orthogonality::<Givens<f64>>(&[(6, 1e-14), (8, 1e-14), (10, 1e-14)]);
+    }
+
+    #[test]
+    fn identity_plus_noise_orthogonality() {
+        check_identity_plus_noise_orthogonality::<Givens<f64>, _>(|_kappa| 100.0 * f64::EPSILON);
+    }
+
+    #[test]
+    fn random_conditioned_matrices() {
+        check_conditioned_matrices::<Givens<f64>, _>(1e-13, |_kappa| 100.0 * f64::EPSILON);
+    }
+}
+
+#[cfg(test)]
+mod hessenberg_tests {
+    extern crate openblas_src;
+
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+    use super::*;
+
+    /// A random `n×n` upper Hessenberg matrix.
+    fn hessenberg(n: usize, rng: &mut StdRng) -> Array2<f64> {
+        let mut a = Array2::random_using((n, n), Normal::new(0.0, 1.0), rng);
+        for ((i, j), a_ij) in a.indexed_iter_mut() {
+            if i > j + 1 {
+                *a_ij = 0.0;
+            }
+        }
+        a
+    }
+
+    #[test]
+    fn hessenberg_is_factorized_with_linear_rotation_count() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for &n in [4, 16, 64].iter() {
+            let a = hessenberg(n, &mut rng);
+            let mut givens = Givens::from_matrix(&a).unwrap();
+            givens.compute(&a).unwrap();
+            assert_eq!(givens.rotations(), n - 1);
+            assert!(crate::utils::orthogonality_loss(givens.q()) < 1e-13);
+            assert!(a.all_close(&givens.q().dot(givens.r()), 1e-12));
+            let r = givens.r();
+            assert!(r.indexed_iter().all(|((i, j), &x)| i <= j || x == 0.0));
+            assert!(r.diag().iter().all(|&r_ii| r_ii > 0.0));
+        }
+    }
+
+    #[test]
+    fn dense_matrix_rotates_every_subdiagonal_entry() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let a = Array2::random_using((8, 5), Normal::new(0.0, 1.0), &mut rng);
+        let mut givens = Givens::from_matrix(&a).unwrap();
+        givens.compute(&a).unwrap();
+        assert_eq!(givens.rotations(), 8 * 5 - 5 * 6 / 2);
+        assert!(a.all_close(&givens.q().dot(givens.r()), 1e-12));
+    }
+}
+
+#[cfg(test)]
+mod update_tests {
+    extern crate openblas_src;
+
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+    use crate::Classical;
+    use super::*;
+
+    /// A random `6×6` matrix and a copy of it in column major layout.
+    fn matrices() -> [Array2<f64>; 2] {
+        let mut rng = StdRng::seed_from_u64(7);
+        let a = Array2::random_using((6, 6), Normal::new(0.0, 1.0), &mut rng);
+        let mut f_a = Array2::zeros((6, 6).f());
+        f_a.assign(&a);
+        [a, f_a]
+    }
+
+    fn factorized(a: &Array2<f64>) -> Classical<f64> {
+        let mut cgs = Classical::from_matrix(a).unwrap();
+        cgs.compute(a).unwrap();
+        cgs
+    }
+
+    #[test]
+    fn low_rank_update_matches_updated_matrix() {
+        let u = Array2::from_shape_fn((6, 2), |(i, j)| ((i + 2 * j) as f64).sin());
+        let v = Array2::from_shape_fn((6, 2), |(i, j)| ((3 * i + j) as f64).cos());
+
+        for a in &matrices() {
+            let updated = a + &u.dot(&v.t());
+            let mut cgs = factorized(a);
+            cgs.low_rank_update(&u, &v).unwrap();
+            assert!(crate::utils::orthogonality_loss(cgs.q()) < 1e-12);
+            assert!(updated.all_close(&cgs.q().dot(cgs.r()), 1e-10));
+            let r = cgs.r();
+            assert!(r.indexed_iter().all(|((i, j), &x)| i <= j || x == 0.0));
+        }
+    }
+
+    #[test]
+    fn low_rank_update_rejects_mismatched_shapes() {
+        let mut cgs = factorized(&matrices()[0]);
+        match cgs.low_rank_update(&Array2::zeros((5, 2)), &Array2::zeros((6, 2))) {
+            Err(Error::ShapeMismatch { expected: (6, 2), found: (5, 2) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+        match cgs.low_rank_update(&Array2::zeros((6, 2)), &Array2::zeros((6, 1))) {
+            Err(Error::ShapeMismatch { expected: (6, 2), found: (6, 1) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn remove_column_matches_batch() {
+        let [a, f_a] = matrices();
+        for index in 0..a.cols() {
+            let mut cgs = factorized(&a);
+            cgs.remove_column(index).unwrap();
+
+            let kept: Vec<usize> = (0..a.cols()).filter(|&j| j != index).collect();
+            let batch = factorized(&a.select(Axis(1), &kept));
+            assert!(cgs.q().all_close(batch.q(), 1e-12));
+            assert!(cgs.r().all_close(batch.r(), 1e-12));
+        }
+
+        // The memory layouts are kept.
+        let mut cgs = factorized(&f_a);
+        cgs.remove_column(2).unwrap();
+        assert_eq!(cgs.q().dim(), (6, 5));
+        assert!(cgs.q().t().is_standard_layout());
+        assert!(f_a.select(Axis(1), &[0, 1, 3, 4, 5]).all_close(&cgs.q().dot(cgs.r()), 1e-12));
+    }
+
+    #[test]
+    fn remove_column_rejects_invalid_index_and_last_column() {
+        let mut cgs = factorized(&matrices()[0].slice(s![.., ..2]).to_owned());
+        match cgs.remove_column(2) {
+            Err(Error::ColumnOutOfBounds { index: 2, cols: 2 }) => {},
+            other => panic!("expected a column out of bounds, got {:?}", other),
+        }
+        cgs.remove_column(0).unwrap();
+        let (q, r) = (cgs.q().clone(), cgs.r().clone());
+        match cgs.remove_column(0) {
+            Err(Error::InvalidShape { rows: 6, cols: 0 }) => {},
+            other => panic!("expected an invalid shape, got {:?}", other),
+        }
+        assert_eq!(&q, cgs.q());
+        assert_eq!(&r, cgs.r());
+    }
+}
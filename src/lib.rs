@@ -6,7 +6,25 @@
 //! + the [modified or stabilized Gram Schmidt] procedure, `[mgs]`;
 //! + the [reorthogonalized Gram Schmidt procedure], `[cgs2]`.
 //!
+//! For large matrices, [`BlockClassical`] performs the classical procedure on panels of columns
+//! with matrix-matrix products. [`Givens`] factorizes by plane rotations instead, which is
+//! particularly cheap for upper Hessenberg matrices.
+//!
+//! Additionally, [`Functional`] performs a modified Gram Schmidt procedure with respect to a user
+//! supplied inner product, and [`Extension`] orthonormalizes matrices against a borrowed,
+//! already orthonormal basis.
+//!
+//! For one-off factorizations, [`qr`] returns a self-contained [`Qr`] with methods to solve
+//! least squares problems and project onto the column space. The procedure structs implementing
+//! the [`GramSchmidt`] trait instead reuse their buffers for repeated factorizations. A
+//! [`GramSchmidtBuilder`] selects the procedure and memory layout at runtime.
+//!
+//! The procedures operate on `f64` by default, and on `f32` as well as the complex numbers of the
+//! [num-complex crate], e.g. `Modified<f32>` or `Classical<Complex<f64>>`; see
+//! [`GramSchmidtScalar`].
+//!
 //! [ndarray]: https://github.com/rust-ndarray/ndarray
+//! [num-complex crate]: https://docs.rs/num-complex
 //! [classical Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process
 //! [modified or stabilized Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process#Numerical_stabilty
 //! [reorthogonalized Gram Schmidt procedure]: https://doi.org/10.1007/s00211-005-0615-4
@@ -14,31 +32,93 @@
 
 use ndarray::{
     ArrayBase,
+    Array1,
     Array2,
+    Array3,
+    ArrayView1,
+    ArrayViewMut2,
+    Axis,
     Data,
     Dim,
     Ix,
+    Ix1,
     Ix2,
+    Ix3,
     ShapeBuilder,
+    linalg::general_mat_mul,
+    s,
 };
+use num_traits::{
+    Float,
+    One,
+    Zero,
+};
+use scalar::private::Sealed as _;
 use std::error;
+use std::io;
+use std::panic;
 use std::result;
 use std::fmt;
+use std::time::Instant;
 
 #[cfg(test)]
 #[macro_use]
 mod test_macros;
 
+mod bcgs;
+mod blas;
+mod builder;
 mod cgs;
 mod cgs2;
+mod cholesky;
+mod cholesky_qr;
+mod extension;
+mod functional;
+mod givens;
 mod mgs;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pivoted;
+mod qr;
+mod scalar;
+#[cfg(feature = "serde")]
+mod serialization;
+mod streaming;
+mod weighted;
 
+pub mod diagnostics;
+pub mod matrix_market;
 pub(crate) mod utils;
 
 // Reexports
+pub use bcgs::BlockClassical;
+pub use builder::{
+    DynGramSchmidt,
+    GramSchmidtBuilder,
+};
 pub use cgs::Classical;
 pub use cgs2::Reorthogonalized;
+pub use cholesky::r_from_gram;
+pub use cholesky_qr::CholeskyQr;
+pub use extension::Extension;
+pub use functional::Functional;
+pub use givens::Givens;
 pub use mgs:: Modified;
+#[cfg(feature = "rayon")]
+pub use parallel::ParallelGramSchmidt;
+pub use pivoted::ColumnPivoted;
+pub use qr::{
+    Options,
+    Procedure,
+    ProcedureOptions,
+    QMode,
+    Qr,
+    factorize,
+    qr,
+};
+pub use scalar::GramSchmidtScalar;
+pub use streaming::StreamingR;
+pub use weighted::Weighted;
 
 /// Errors that occur during a initialization of a Gram Schmidt factorization.
 #[derive(Debug)]
@@ -51,31 +131,275 @@ pub enum Error {
     /// The array to be factorized is not contiguous. At the moment, all arrays to be factorized
     /// have to be contiguous.
     NonContiguous,
+
+    /// The array to be factorized is a broadcast view, i.e. it has a zero stride along an axis
+    /// and thus repeats the same elements. Factorizing such an array is almost always a mistake.
+    BroadcastInput,
+
+    /// The upper triangular matrix R has a zero on its diagonal and can thus not be inverted.
+    Singular,
+
+    /// The length of a vector does not match the corresponding dimension of the factorization.
+    LengthMismatch { expected: usize, found: usize },
+
+    /// A weight is zero or not finite, and can thus not be used to rescale a column.
+    InvalidWeight { index: usize },
+
+    /// The shape of a matrix does not match the one expected from the factorization.
+    ShapeMismatch { expected: (usize, usize), found: (usize, usize) },
+
+    /// The operation requires a square matrix Q, i.e. a complete orthonormal basis.
+    NotSquare,
+
+    /// Reading or writing a factorization failed.
+    Io(io::Error),
+
+    /// The column with the given index is linearly dependent on the previous columns, so that
+    /// no new basis vector can be formed from it.
+    RankDeficient { column: usize },
+
+    /// The pinned column with the given index is not normalized or not orthogonal to the
+    /// previous pinned columns.
+    NotOrthonormal { column: usize },
+
+    /// The matrix is not symmetric positive definite, so that its Cholesky decomposition does
+    /// not exist.
+    NotPositiveDefinite,
+
+    /// The matrix has fewer rows than columns, so that at most `rows` of its columns can be
+    /// orthonormalized.
+    Underdetermined { rows: usize, cols: usize },
+
+    /// The name could not be parsed as a [`Procedure`](enum.Procedure.html).
+    UnknownProcedure { name: String },
+
+    /// The matrix is not symmetric, e.g. a metric defining an inner product.
+    NotSymmetric,
+
+    /// The entry of the input in the given row and column is NaN or infinite.
+    NonFinite { row: usize, col: usize },
+
+    /// The shape has no rows or no columns, so that there is nothing to factorize.
+    InvalidShape { rows: usize, cols: usize },
+
+    /// More columns are to be pinned than the matrix has.
+    TooManyPinned { pinned: usize, cols: usize },
+
+    /// The column index is not smaller than the number of columns of the factorization.
+    ColumnOutOfBounds { index: usize, cols: usize },
 }
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// The state of a factorization that can be interrupted, see
+/// [`GramSchmidt::compute_budgeted`].
+///
+/// [`GramSchmidt::compute_budgeted`]: trait.GramSchmidt.html#method.compute_budgeted
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ComputeStatus {
+    /// All columns have been orthonormalized.
+    Done,
+
+    /// The computation was interrupted. The columns `0..next_col` have been orthonormalized; the
+    /// computation can be resumed by passing `next_col` to [`GramSchmidt::continue_from`].
+    ///
+    /// [`GramSchmidt::continue_from`]: trait.GramSchmidt.html#tymethod.continue_from
+    InProgress { next_col: usize },
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Error::*;
         match self {
             IncompatibleLayouts => write!(f, "The arrays representing the matrices don't have the same layouts."),
             NonContiguous => write!(f, "Array shape is not contiguous"),
+            BroadcastInput => write!(f, "The array is a broadcast view with a zero stride."),
+            Singular => write!(f, "The triangular matrix R is singular."),
+            LengthMismatch { expected, found } => write!(f, "Expected a vector of length {}, found length {}.", expected, found),
+            InvalidWeight { index } => write!(f, "The weight at index {} is zero or not finite.", index),
+            ShapeMismatch { expected, found } => write!(f, "Expected a matrix of shape {:?}, found shape {:?}.", expected, found),
+            NotSquare => write!(f, "The operation requires a square matrix Q."),
+            Io(err) => write!(f, "IO error: {}", err),
+            RankDeficient { column } => write!(f, "Column {} is linearly dependent on the previous columns.", column),
+            NotOrthonormal { column } => write!(f, "Pinned column {} is not orthonormal to the previous columns.", column),
+            NotPositiveDefinite => write!(f, "The matrix is not positive definite."),
+            Underdetermined { rows, cols } => write!(f, "A matrix with {} rows cannot have {} orthonormal columns.", rows, cols),
+            UnknownProcedure { name } => write!(f, "Unknown Gram Schmidt procedure {:?}.", name),
+            NotSymmetric => write!(f, "The matrix is not symmetric."),
+            NonFinite { row, col } => write!(f, "The entry ({}, {}) is not finite.", row, col),
+            InvalidShape { rows, cols } => write!(f, "A matrix of shape ({}, {}) has no entries to factorize.", rows, cols),
+            TooManyPinned { pinned, cols } => write!(f, "Cannot pin {} columns of a matrix with {} columns.", pinned, cols),
+            ColumnOutOfBounds { index, cols } => write!(f, "The column index {} is out of bounds for {} columns.", index, cols),
         }
     }
 }
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Records how a column of Q was derived during a factorization, see
+/// [`GramSchmidt::provenance`].
+///
+/// [`GramSchmidt::provenance`]: trait.GramSchmidt.html#tymethod.provenance
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColumnProvenance {
+    /// The index of the column of the factorized matrix from which the column of Q was derived.
+    pub input_column: usize,
+
+    /// Whether the column was pinned, i.e. copied unchanged from the input.
+    pub pinned: bool,
+
+    /// Whether the column was orthogonalized a second time against the previous basis vectors.
+    pub reorthogonalized: bool,
+
+    /// Whether the sign of the column, or its phase for complex scalars, was changed after it was
+    /// computed, by [`rescale_columns`] with a weight that is not real and positive or by
+    /// [`force_positive_diagonal`]. The procedures themselves compute a positive diagonal of R,
+    /// so that this is cleared whenever the column is computed anew.
+    ///
+    /// [`rescale_columns`]: trait.GramSchmidt.html#tymethod.rescale_columns
+    /// [`force_positive_diagonal`]: trait.GramSchmidt.html#tymethod.force_positive_diagonal
+    pub sign_flipped: bool,
+}
+
+/// The memory layout of a matrix.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Layout {
+    /// Row major or C layout: the elements of a row are adjacent in memory.
+    RowMajor,
+
+    /// Column major or Fortran layout: the elements of a column are adjacent in memory.
+    ColumnMajor,
+}
+
+impl From<blas::Layout> for Layout {
+    fn from(layout: blas::Layout) -> Self {
+        match layout {
+            blas::Layout::RowMajor => Layout::RowMajor,
+            blas::Layout::ColumnMajor => Layout::ColumnMajor,
+        }
     }
 }
 
+impl From<Layout> for blas::Layout {
+    fn from(layout: Layout) -> Self {
+        match layout {
+            Layout::RowMajor => blas::Layout::RowMajor,
+            Layout::ColumnMajor => blas::Layout::ColumnMajor,
+        }
+    }
+}
+
+/// An owned copy of a matrix, stored contiguously in the memory layout expected by the Gram
+/// Schmidt procedure it was prepared for. See [`GramSchmidt::prepared`].
+///
+/// [`GramSchmidt::prepared`]: trait.GramSchmidt.html#method.prepared
+#[derive(Clone, Debug)]
+pub struct PreparedInput<T = f64> {
+    a: Array2<T>,
+}
+
+impl<T: GramSchmidtScalar> PreparedInput<T> {
+    /// Copies `a` into a contiguous array. Column major inputs keep their layout, all others are
+    /// stored in row major layout.
+    fn new<S>(a: &ArrayBase<S, Ix2>) -> Self
+        where S: Data<Elem = T>
+    {
+        let column_major = utils::get_layout(a) == Some(blas::Layout::ColumnMajor);
+        let mut prepared = Array2::zeros(a.dim().set_f(column_major));
+        prepared.assign(a);
+        Self { a: prepared }
+    }
+
+    /// Returns a reference to the prepared matrix.
+    pub fn as_array(&self) -> &Array2<T> {
+        &self.a
+    }
+
+    /// Returns the prepared matrix, consuming `self`.
+    pub fn into_inner(self) -> Array2<T> {
+        self.a
+    }
+}
+
+/// A coarse classification of the numerical stability of a Gram Schmidt procedure, measured by
+/// how well the orthogonality of Q is preserved for ill-conditioned matrices. The classes are
+/// ordered from least to most stable.
+///
+/// # Example
+///
+/// Selecting a procedure by its stability class and cost:
+///
+/// ```
+/// use gramschmidt::{
+///     Classical,
+///     GramSchmidt,
+///     Modified,
+///     Reorthogonalized,
+///     StabilityClass,
+/// };
+///
+/// let procedures = [
+///     ("cgs", <Classical>::STABILITY, <Classical>::COST_FACTOR),
+///     ("mgs", <Modified>::STABILITY, <Modified>::COST_FACTOR),
+///     ("cgs2", <Reorthogonalized>::STABILITY, <Reorthogonalized>::COST_FACTOR),
+/// ];
+///
+/// let cheapest_stable = procedures.iter()
+///     .filter(|(_, stability, _)| *stability >= StabilityClass::High)
+///     .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap())
+///     .map(|(name, _, _)| *name);
+///
+/// assert_eq!(cheapest_stable, Some("cgs2"));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum StabilityClass {
+    /// The loss of orthogonality grows with the square of the condition number of the input,
+    /// like for the classical Gram Schmidt procedure.
+    Low,
+
+    /// The loss of orthogonality grows linearly with the condition number of the input, like for
+    /// the modified Gram Schmidt procedure.
+    Medium,
+
+    /// Orthogonality is preserved up to machine precision for numerically nonsingular inputs, like
+    /// for the reorthogonalized Gram Schmidt procedure.
+    High,
+}
+
 pub trait GramSchmidt: Sized {
+    /// The element type of the factorized matrices, see [`GramSchmidtScalar`].
+    ///
+    /// [`GramSchmidtScalar`]: trait.GramSchmidtScalar.html
+    type Scalar: GramSchmidtScalar;
+
+    /// The numerical stability of the procedure.
+    const STABILITY: StabilityClass;
+
+    /// The number of floating point operations of the procedure relative to the classical Gram
+    /// Schmidt procedure, which takes about `2mn²` operations for an `m×n` matrix.
+    const COST_FACTOR: f64;
+
     /// Reserves the memory for a QR decomposition via a classical Gram Schmidt orthogonalization
     /// using a shape.
     ///
-    /// The resulting object can be used to orthogonalize matrices of the same dimensions.
+    /// The resulting object can be used to orthogonalize matrices of the same dimensions. For a
+    /// tall `m×n` shape with `m ≥ n`, the thin factorization is computed: Q is `m×n` and R is `n×n`.
+    /// A wide shape with `m < n` is rejected with [`Error::Underdetermined`], because at most `m`
+    /// columns can be orthonormal. A shape without rows or columns is rejected with
+    /// [`Error::InvalidShape`].
     ///
     /// # Example
     ///
@@ -88,18 +412,124 @@ pub trait GramSchmidt: Sized {
     ///
     /// # fn main() -> Result<()> {
     ///
-    /// let mut cgs = Classical::from_shape((10,10))?;
+    /// let mut cgs: Classical = Classical::from_shape((10,10))?;
     ///
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
     fn from_shape<T>(shape: T) -> Result<Self>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>
+    {
+        Self::from_shape_with_r_transposed(shape, false)
+    }
+
+    /// Reserves the memory for a QR decomposition like [`from_shape`], but allows to store the
+    /// upper triangular matrix R in the memory layout opposite to the one of Q.
+    ///
+    /// By default R is stored in the same layout as Q. If `transpose_r` is set, a column major Q
+    /// is accompanied by a row major R and vice versa. This is useful if a downstream solver
+    /// accesses R by rows instead of by columns (or the other way around). The values of R are
+    /// not affected by this flag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ndarray::ShapeBuilder;
+    /// use gramschmidt::{
+    ///     Classical,
+    ///     GramSchmidt,
+    ///     Result,
+    /// };
+    ///
+    /// # fn main() -> Result<()> {
+    ///
+    /// let cgs: Classical = Classical::from_shape_with_r_transposed((10,10).f(), true)?;
+    /// assert!(cgs.r().is_standard_layout());
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`from_shape`]: trait.GramSchmidt.html#method.from_shape
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
         where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>;
 
+    /// Constructs the factorization from an already computed matrix `q` and upper triangular
+    /// matrix `r`, e.g. one that was read from disk with [`read_matrix_market`].
+    ///
+    /// The memory layout is taken from `q`. Both matrices have to be contiguous, and `r` has to be
+    /// a square matrix with as many columns as `q`, which may not have more columns than rows. The
+    /// values themselves are not checked.
+    ///
+    /// [`read_matrix_market`]: trait.GramSchmidt.html#method.read_matrix_market
+    fn from_parts(q: Array2<Self::Scalar>, r: Array2<Self::Scalar>) -> Result<Self>;
+
+    /// Sets the tolerance below which a column is considered to be linearly dependent on the
+    /// previous ones.
+    ///
+    /// Computing returns [`Error::RankDeficient`] for the first column whose norm after removing
+    /// its projections onto the previous columns is at most `tol` times its original norm,
+    /// instead of normalizing it by a (nearly) vanishing norm. The same tolerance decides the
+    /// [`Qr::rank`] of the convenience functions and [`diagnostics::is_full_rank`].
+    ///
+    /// The default is `max(m, n)·ε` for an `m×n` matrix and the machine epsilon `ε` of the
+    /// scalar type, so that only columns which are dependent up to rounding errors are rejected.
+    /// It follows the shape until a tolerance is set. A tolerance of `0.0` only rejects columns
+    /// whose remaining norm is exactly zero.
+    ///
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    /// [`Qr::rank`]: struct.Qr.html#method.rank
+    /// [`diagnostics::is_full_rank`]: diagnostics/fn.is_full_rank.html
+    fn set_tolerance(&mut self, tol: f64);
+
+    /// Returns the tolerance below which a column is considered to be linearly dependent on the
+    /// previous ones. See [`set_tolerance`] for details.
+    ///
+    /// [`set_tolerance`]: trait.GramSchmidt.html#tymethod.set_tolerance
+    fn tolerance(&self) -> f64;
+
+    /// Sets the tolerance below which a column is considered to be linearly dependent on the
+    /// previous ones, so that it can be configured once when creating the procedure. See
+    /// [`set_tolerance`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Error, GramSchmidt, Modified};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// // The third column deviates from the sum of the first two by 1e-8.
+    /// let a = arr2(&[[1.0, 0.0, 1.0],
+    ///                [1.0, 1.0, 2.0 + 1e-8],
+    ///                [1.0, 2.0, 3.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?.with_tolerance(1e-6);
+    /// match mgs.compute(&a) {
+    ///     Err(Error::RankDeficient { column }) => assert_eq!(column, 2),
+    ///     other => panic!("expected a rank deficient matrix, got {:?}", other),
+    /// }
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`set_tolerance`]: trait.GramSchmidt.html#tymethod.set_tolerance
+    fn with_tolerance(mut self, tol: f64) -> Self {
+        self.set_tolerance(tol);
+        self
+    }
+
     /// Computes a QR decomposition using a Gram Schmidt orthonormalization of the matrix `a`.
     ///
     /// The input matrix `a` has to have exactly the same dimension and memory layout as was
-    /// previously configured. Returns an error otherwise.
+    /// previously configured. Returns an error otherwise. Procedures created with e.g.
+    /// [`Classical::from_shape_accepting_any_layout`] accept either memory layout.
     ///
     /// ```
     /// extern crate openblas_src;
@@ -117,113 +547,2554 @@ pub trait GramSchmidt: Sized {
     ///
     /// # }
     /// ```
+    ///
+    /// [`Classical::from_shape_accepting_any_layout`]: struct.Classical.html#method.from_shape_accepting_any_layout
     fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
-        where S: Data<Elem = f64>;
+        where S: Data<Elem = Self::Scalar>;
 
-    /// Return a reference to the matrix q.
-    fn q(&self) -> &Array2<f64>;
+    /// Resumes an interrupted computation of the QR decomposition of the matrix `a`, starting
+    /// with the column `next_col`. The columns `0..next_col` of `a` must have been processed by
+    /// previous calls to [`compute_budgeted`] or `continue_from` for the same `a`.
+    ///
+    /// Columns are processed until either all of them are orthonormalized, in which case
+    /// [`ComputeStatus::Done`] is returned, or until `deadline` has passed, in which case
+    /// [`ComputeStatus::InProgress`] is returned. At least one column is processed per call, so
+    /// that the computation always makes progress.
+    ///
+    /// [`compute_budgeted`]: trait.GramSchmidt.html#method.compute_budgeted
+    /// [`ComputeStatus::Done`]: enum.ComputeStatus.html#variant.Done
+    /// [`ComputeStatus::InProgress`]: enum.ComputeStatus.html#variant.InProgress
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = Self::Scalar>;
 
-    /// Return a reference to the matrix q.
-    fn r(&self) -> &Array2<f64>;
+    /// Orthonormalizes the columns `start..` of the matrix `a`, whose columns `0..start` must have
+    /// been processed already, like [`continue_from`] without a deadline.
+    ///
+    /// [`continue_from`]: trait.GramSchmidt.html#tymethod.continue_from
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = Self::Scalar>;
 
-    // Blanket impls
-    /// One-off version of [`compute`]. Takes the matrix `a` to be factorized, allocates a type
-    /// implementing the `GramSchmidt` trait, computes the QR decomposition, and returns clones of
-    /// the Q and R matrices.
+    /// Computes the QR decomposition of the matrix `a` like [`compute`], calling `observe` with
+    /// the index of every column and its diagonal entry of R as soon as it is orthonormalized.
     ///
-    /// [`compute`]: trait.GramSchmidt.html#method.compute
-    fn compute_once<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
-        where S: Data<Elem=f64>,
+    /// Pinned columns are observed with a diagonal entry of one. The computation stops after the
+    /// first column for which `observe` returns `false`, in which case
+    /// [`ComputeStatus::InProgress`] is returned with the next column, and the remaining columns
+    /// of Q and R are left in an unspecified state. Procedures which orthonormalize all columns at
+    /// once, like [`CholeskyQr`], observe them afterwards. This is the building block of
+    /// [`compute_with_callback`] and [`compute_truncated`].
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`ComputeStatus::InProgress`]: enum.ComputeStatus.html#variant.InProgress
+    /// [`CholeskyQr`]: struct.CholeskyQr.html
+    /// [`compute_with_callback`]: trait.GramSchmidt.html#method.compute_with_callback
+    /// [`compute_truncated`]: trait.GramSchmidt.html#method.compute_truncated
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = Self::Scalar>,
+              F: FnMut(usize, <Self::Scalar as GramSchmidtScalar>::Real) -> bool;
+
+    /// Computes a QR decomposition like [`compute`], but returns once `deadline` has passed. The
+    /// computation can then be resumed with [`continue_from`].
+    ///
+    /// This allows to spread a factorization over several time slices, e.g. in an interactive
+    /// application that must not block for too long.
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{ComputeStatus, GramSchmidt, Classical};
+    /// use ndarray::Array2;
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # fn main() {
+    ///
+    /// let matrix = Array2::random((100,100), Normal::new(0.0, 1.0));
+    /// let mut cgs = Classical::from_matrix(&matrix).unwrap();
+    ///
+    /// let budget = Duration::from_micros(100);
+    /// let mut status = cgs.compute_budgeted(&matrix, Instant::now() + budget).unwrap();
+    /// while let ComputeStatus::InProgress { next_col } = status {
+    ///     // Do some other work here.
+    ///     status = cgs.continue_from(&matrix, next_col, Instant::now() + budget).unwrap();
+    /// }
+    ///
+    /// assert!(matrix.all_close(&cgs.q().dot(cgs.r()), 1e-10));
+    /// # }
+    /// ```
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`continue_from`]: trait.GramSchmidt.html#tymethod.continue_from
+    fn compute_budgeted<S>(&mut self, a: &ArrayBase<S, Ix2>, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = Self::Scalar>
     {
-        let mut gram_schmidt = Self::from_matrix(a)?;
-        gram_schmidt.compute(a)?;
-        Ok((gram_schmidt.q().clone(), gram_schmidt.r().clone()))
+        self.continue_from(a, 0, deadline)
     }
 
-    /// Uses a matrix to reserve memory for a QR decomposition via a classical Gram Schmidt.
+    /// Computes the QR decomposition of the matrix `a` like [`compute`], and writes Q and R into
+    /// the caller provided buffers `q_out` and `r_out`.
     ///
-    /// The resulting object can be used to orthogonalize matrices of the same dimensions.
+    /// The buffers must have the shapes and memory layouts of [`q`] and [`r`]. Otherwise,
+    /// [`Error::ShapeMismatch`] or [`Error::IncompatibleLayouts`] is returned before anything is
+    /// computed. Since neither the procedure nor the buffers are reallocated, repeated
+    /// factorizations don't allocate, while the results can be kept independently of the
+    /// procedure.
     ///
     /// # Example
     ///
     /// ```
-    /// use ndarray::Array;
-    /// use gramschmidt::{
-    ///     Classical,
-    ///     GramSchmidt,
-    ///     Result,
-    /// };
+    /// extern crate openblas_src;
     ///
-    /// # fn main() -> Result<()> {
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{arr2, Array2};
     ///
-    /// let a = Array::zeros((10, 10));
-    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [1.0, 2.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// let mut q = Array2::zeros((3, 2));
+    /// let mut r = Array2::zeros((2, 2));
+    /// mgs.compute_into(&a, &mut q, &mut r)?;
+    /// assert!(a.all_close(&q.dot(&r), 1e-14));
     ///
     /// # Ok(())
     /// # }
     /// ```
-    fn from_matrix<S>(a: &ArrayBase<S, Ix2>) -> Result<Self>
-        where S: Data<Elem = f64>
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`q`]: trait.GramSchmidt.html#tymethod.q
+    /// [`r`]: trait.GramSchmidt.html#tymethod.r
+    /// [`Error::ShapeMismatch`]: enum.Error.html#variant.ShapeMismatch
+    /// [`Error::IncompatibleLayouts`]: enum.Error.html#variant.IncompatibleLayouts
+    fn compute_into<S>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        q_out: &mut Array2<Self::Scalar>,
+        r_out: &mut Array2<Self::Scalar>,
+    ) -> Result<()>
+        where S: Data<Elem = Self::Scalar>
     {
-        use cblas::Layout::*;
-        let dim = a.dim();
-        let shape = match utils::get_layout(a) {
-            Some(ColumnMajor) => dim.f(),
-            Some(RowMajor) => dim.into_shape(),
-            None => Err(Error::NonContiguous)?,
-        };
+        for (out, own) in [(&*q_out, self.q()), (&*r_out, self.r())].iter() {
+            if out.dim() != own.dim() {
+                Err(Error::ShapeMismatch { expected: own.dim(), found: out.dim() })?;
+            }
+            if utils::get_layout(*out) != utils::get_layout(*own) {
+                Err(Error::IncompatibleLayouts)?;
+            }
+        }
 
-        Self::from_shape(shape)
+        self.compute(a)?;
+        q_out.assign(self.q());
+        r_out.assign(self.r());
+        Ok(())
     }
 
-}
+    /// Computes the QR decomposition of the matrix `a` like [`compute`], calling `on_column` with
+    /// the index and the norm of every column as soon as it has been orthonormalized.
+    ///
+    /// The norm passed to `on_column` is the diagonal entry `r_jj`, i.e. the norm of column `j`
+    /// after removing its projections onto the previous basis vectors. This allows to monitor the
+    /// progress of a long running factorization, or how quickly the remaining columns lose rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Classical};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[3.0, 1.0],
+    ///                [4.0, 1.0]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// let mut norms = Vec::new();
+    /// cgs.compute_with_callback(&a, |j, norm| norms.push((j, norm)))?;
+    /// assert_eq!(norms.len(), 2);
+    /// assert_eq!(norms[0], (0, 5.0));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    fn compute_with_callback<S, F>(&mut self, a: &ArrayBase<S, Ix2>, mut on_column: F) -> Result<()>
+        where S: Data<Elem = Self::Scalar>,
+              F: FnMut(usize, f64),
+    {
+        self.compute_observed(a, |j, norm| {
+            on_column(j, Self::Scalar::to_f64(norm));
+            true
+        }).map(|_| ())
+    }
 
-/// Convenience function that calculates a [Classical Gram Schmidt] QR factorization, returning a
-/// tuple `(Q,R)`.
-///
-/// If you want to repeatedly calculate QR factorizations, then prefer constructing a [`Classical`]
-/// struct and calling its [`GramSchmidt::compute`] method implemented through the [`GramSchmidt`] trait.
-///
-/// [Classical Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process
-/// [`Classical`]: Classical
-/// [GramSchmidt]: GramSchmidt
-/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
-pub fn cgs<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
-    where S: Data<Elem=f64>
-{
-    Classical::compute_once(a)
-}
+    /// Computes a truncated QR decomposition of the matrix `a`, which stops after `max_cols`
+    /// columns, or at the first column whose norm after removing its projections onto the
+    /// previous basis vectors is smaller than `min_norm`. Returns the achieved rank, i.e. the
+    /// number of orthonormalized columns.
+    ///
+    /// The columns of Q and R from the achieved rank onward are set to zero. A column rejected as
+    /// [`Error::RankDeficient`], see [`set_tolerance`], stops the factorization as well instead of
+    /// returning an error. Since the columns are taken in their given order, this is a greedy
+    /// selection of a basis for the column space of `a`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// // The third column is the sum of the first two.
+    /// let a = arr2(&[[1.0, 0.0, 1.0],
+    ///                [1.0, 1.0, 2.0],
+    ///                [1.0, 2.0, 3.0],
+    ///                [1.0, 3.0, 4.0]]);
+    /// let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+    /// assert_eq!(cgs2.compute_truncated(&a, 3, 1e-10)?, 2);
+    /// assert!(cgs2.q().column(2).iter().all(|&q_i| q_i == 0.0));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    /// [`set_tolerance`]: trait.GramSchmidt.html#tymethod.set_tolerance
+    fn compute_truncated<S>(&mut self, a: &ArrayBase<S, Ix2>, max_cols: usize, min_norm: f64) -> Result<usize>
+        where S: Data<Elem = Self::Scalar>
+    {
+        let max_cols = max_cols.min(a.cols());
+        let mut rank = max_cols;
+        let observed = self.compute_observed(a, |j, norm| {
+            if Self::Scalar::to_f64(norm) < min_norm {
+                rank = j;
+            }
+            rank == max_cols && j + 1 < max_cols
+        });
+        match observed {
+            Ok(_) => {},
+            Err(Error::RankDeficient { column }) => rank = rank.min(column),
+            Err(err) => return Err(err),
+        }
 
-/// Convenience function that calculates a Reorthogonalized Gram Schmmidt QR factorization (see
-/// [Giraud et al.] for details), returning a tuple `(Q,R)`.
-///
-/// If you want to repeatedly calculate QR factorizations, then prefer constructing a
-/// [`Reorthogonalized`] struct and calling its [`GramSchmidt::compute`] method implemented through
-/// the [`GramSchmidt`] trait.
-///
-/// [Giraud et al.]: https://doi.org/10.1007/s00211-005-0615-4
-/// [`Reorthogonalized`]: Reorthogonalized
-/// [`GramSchmidt`]: GramSchmidt
-/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
-pub fn cgs2<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
-    where S: Data<Elem=f64>
-{
-    Reorthogonalized::compute_once(a)
-}
+        self.clear_columns_from(rank);
+        Ok(rank)
+    }
 
-/// Convenience function that calculates a [Modified Gram Schmidt] QR factorization, returning a
-/// tuple `(Q,R)`.
-///
-/// If you want to repeatedly calculate QR factorizations, then prefer constructing a
-/// [`Modified`] struct and calling its [`GramSchmidt::compute`] method implemented through
-/// the [`GramSchmidt`] trait.
-///
-/// [Modified Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process#Numerical_stabilty
-/// [`Modified`]: Modified
-/// [`GramSchmidt`]: GramSchmidt
-/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
-pub fn mgs<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
-    where S: Data<Elem=f64>
-{
-    Modified::compute_once(a)
+    /// Return a reference to the matrix q.
+    fn q(&self) -> &Array2<Self::Scalar>;
+
+    /// Return a reference to the matrix r.
+    ///
+    /// The diagonal entries of R are the norms of the columns after removing their projections
+    /// onto the previous basis vectors, and are thus never negative. The signs of the columns of
+    /// A are carried by Q: a single column `a` is factorized as `q = a/‖a‖` and `r = ‖a‖`.
+    fn r(&self) -> &Array2<Self::Scalar>;
+
+    /// Returns a mutable view of the matrix q, e.g. to post-process it in place.
+    ///
+    /// Mutating Q breaks the invariants of the factorization unless R is adjusted to match: the
+    /// product `QR` no longer reproduces the factorized matrix, and the columns of Q are no longer
+    /// orthonormal in general. Methods relying on them, like [`solve`] or the updates, then return
+    /// meaningless results. A view instead of the array itself is returned, so that the shape and
+    /// the memory layout, on which the blas calls rely, cannot be changed. For the common cases,
+    /// prefer [`rescale_columns`] and [`force_positive_diagonal`], which keep the invariants.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [1.0, 2.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    ///
+    /// // Flipping the sign of a column of Q and the corresponding row of R keeps A = QR.
+    /// mgs.q_mut().column_mut(1).mapv_inplace(|q_i| -q_i);
+    /// mgs.r_mut().row_mut(1).mapv_inplace(|r_i| -r_i);
+    /// assert!(a.all_close(&mgs.q().dot(mgs.r()), 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`solve`]: trait.GramSchmidt.html#method.solve
+    /// [`rescale_columns`]: trait.GramSchmidt.html#tymethod.rescale_columns
+    /// [`force_positive_diagonal`]: trait.GramSchmidt.html#tymethod.force_positive_diagonal
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, Self::Scalar>;
+
+    /// Returns a mutable view of the matrix r, e.g. to post-process it in place. See [`q_mut`]
+    /// for the invariants which are broken by mutating it.
+    ///
+    /// [`q_mut`]: trait.GramSchmidt.html#tymethod.q_mut
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, Self::Scalar>;
+
+    /// Returns an iterator over the columns of Q, i.e. the orthonormal basis of the column space
+    /// of the factorized matrix, in order and without copying them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::{arr1, arr2, Array1};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [0.0, 1.0]]);
+    /// let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+    /// cgs2.compute(&a)?;
+    ///
+    /// // Summing the projections onto the basis vectors projects onto the column space.
+    /// let v = arr1(&[1.0, 2.0, 3.0]);
+    /// let projection = cgs2.basis_vectors()
+    ///     .fold(Array1::zeros(3), |acc, q_i| acc + &(&q_i * q_i.dot(&v)));
+    /// assert!(projection.all_close(&cgs2.project(&v)?, 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn basis_vectors(&self) -> impl Iterator<Item = ArrayView1<'_, Self::Scalar>> {
+        self.q().gencolumns().into_iter()
+    }
+
+    /// Returns for every column of Q a record of how it was derived by the last computation.
+    ///
+    /// Since none of the procedures pivots, the `j`-th column of Q always originates from the
+    /// `j`-th input column. Whether a column was pinned reflects the current configuration, see
+    /// e.g. [`Classical::set_pinned`]. Only [`Reorthogonalized`] records whether a column was
+    /// orthogonalized a second time, which depends on its tolerance; for a factorization
+    /// constructed by [`from_parts`] no reorthogonalization is recorded.
+    ///
+    /// [`Classical::set_pinned`]: struct.Classical.html#method.set_pinned
+    /// [`Reorthogonalized`]: struct.Reorthogonalized.html
+    /// [`from_parts`]: trait.GramSchmidt.html#tymethod.from_parts
+    fn provenance(&self) -> Vec<ColumnProvenance>;
+
+    /// Returns an estimate of the number of floating point operations of the last computation,
+    /// e.g. to compare the cost of the procedures without profiling them.
+    ///
+    /// Orthogonalizing the `j`-th column of an `m×n` matrix against the previous ones takes `4mj`
+    /// operations, and normalizing it `3m`, summing to about `2mn²` for the classical procedure.
+    /// Pinned columns are only copied and cost nothing. The sum is scaled by [`COST_FACTOR`],
+    /// except for [`Reorthogonalized`], which counts the second orthogonalizations recorded by
+    /// [`provenance`] instead, so that its estimate ranges from `2mn²` to `4mn²`. The estimate
+    /// assumes that the last computation completed. Before the first computation, and after the
+    /// shape was reset, it is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::Array2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = Array2::from_shape_fn((100, 10), |(i, j)| if i == j { 2.0 } else { 1.0 / (i + j + 1) as f64 });
+    /// let mut cgs = Classical::<f64>::from_shape((100, 10))?;
+    /// assert_eq!(cgs.last_flops(), 0);
+    ///
+    /// cgs.compute(&a)?;
+    /// // Σ_j (4·100·j + 3·100) for j = 0..10
+    /// assert_eq!(cgs.last_flops(), 21_000);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`COST_FACTOR`]: #associatedconstant.COST_FACTOR
+    /// [`Reorthogonalized`]: struct.Reorthogonalized.html
+    /// [`provenance`]: trait.GramSchmidt.html#tymethod.provenance
+    fn last_flops(&self) -> u64;
+
+    /// Rescales the columns of Q by `weights` while preserving the product `QR`.
+    ///
+    /// The `j`-th column of Q is multiplied by `weights[j]`, and the `j`-th row of R is divided by
+    /// it, so that `A = (QD)(D⁻¹R)` with `D = diag(weights)` still holds. Note that the columns of
+    /// Q are no longer normalized afterwards (unless all weights are `±1`). Weights which are not
+    /// real and positive are recorded as sign flips in [`provenance`].
+    ///
+    /// Returns an error if the length of `weights` does not match the number of columns, or if a
+    /// weight is zero or not finite. Q and R are left untouched in that case.
+    ///
+    /// [`provenance`]: trait.GramSchmidt.html#tymethod.provenance
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = Self::Scalar>;
+
+    /// Flips the signs of the columns of Q and the corresponding rows of R such that the diagonal
+    /// of R is nonnegative, while preserving the product `QR`. For complex scalars, the phases of
+    /// the diagonal entries are moved into Q instead, leaving a real and nonnegative diagonal.
+    ///
+    /// The factorizations computed by the procedures already have a positive diagonal, which makes
+    /// them unique and comparable across tools. This restores that convention after operations
+    /// which do not preserve it, like [`rescale_columns`] with negative weights and
+    /// [`low_rank_update`], or for factors from other libraries passed to [`from_parts`], e.g.
+    /// LAPACK's Householder QR. Columns with a zero diagonal entry are left unchanged. The flipped
+    /// columns are recorded in [`provenance`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{arr1, arr2};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0],
+    ///                [1.0, 1.0],
+    ///                [1.0, 2.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    /// let (q, r) = (mgs.q().clone(), mgs.r().clone());
+    ///
+    /// mgs.rescale_columns(&arr1(&[-1.0, 1.0]))?;
+    /// assert!(mgs.r()[(0, 0)] < 0.0);
+    /// mgs.force_positive_diagonal();
+    /// assert!(mgs.q().all_close(&q, 1e-15));
+    /// assert!(mgs.r().all_close(&r, 1e-15));
+    /// assert!(mgs.provenance()[0].sign_flipped);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`rescale_columns`]: trait.GramSchmidt.html#tymethod.rescale_columns
+    /// [`low_rank_update`]: trait.GramSchmidt.html#tymethod.low_rank_update
+    /// [`from_parts`]: trait.GramSchmidt.html#tymethod.from_parts
+    /// [`provenance`]: trait.GramSchmidt.html#tymethod.provenance
+    fn force_positive_diagonal(&mut self);
+
+    /// Updates the factorization `A = QR` to the factorization of `A + U·Vᵀ`, where `U` is an
+    /// `m×k` and `V` an `n×k` matrix.
+    ///
+    /// The update is performed as `k` successive rank one updates, each taking `2(n-1)` plane
+    /// (Givens) rotations of the stored Q and R. This costs `O(kn²)` operations instead of the
+    /// `O(n³)` of a full refactorization, which pays off if `k` is small. Note that the diagonal
+    /// of the updated R is not guaranteed to be positive, see [`force_positive_diagonal`].
+    ///
+    /// Since the update requires a complete orthonormal basis, Q has to be square. Returns an
+    /// error if it is not, or if the shapes of `u` and `v` don't fit the factorization.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::Array2;
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    ///
+    /// # fn main() {
+    ///
+    /// let a = Array2::random((10,10), Normal::new(0.0, 1.0));
+    /// let u = Array2::random((10,2), Normal::new(0.0, 1.0));
+    /// let v = Array2::random((10,2), Normal::new(0.0, 1.0));
+    ///
+    /// let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+    /// cgs2.compute(&a).unwrap();
+    /// cgs2.low_rank_update(&u, &v).unwrap();
+    ///
+    /// let updated = &a + &u.dot(&v.t());
+    /// assert!(updated.all_close(&cgs2.q().dot(cgs2.r()), 1e-10));
+    /// # }
+    /// ```
+    ///
+    /// [`force_positive_diagonal`]: trait.GramSchmidt.html#tymethod.force_positive_diagonal
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = Self::Scalar>,
+              T: Data<Elem = Self::Scalar>;
+
+    /// Removes the column `index` from the factorized matrix, shrinking Q and R by one column.
+    ///
+    /// The column of R is deleted and its triangular form restored by plane rotations, which are
+    /// applied to the trailing columns of Q as well, so that Q stays orthonormal. This costs
+    /// `O((m + n)·(n - index))` operations for an `m×n` matrix instead of a new factorization.
+    /// The result agrees with a factorization of the remaining columns up to rounding errors.
+    /// Removing a pinned column reduces the number of pinned columns by one.
+    ///
+    /// Returns [`Error::ColumnOutOfBounds`] if `index` is not smaller than the number of columns,
+    /// and [`Error::InvalidShape`] if the factorized matrix only has a single column. The
+    /// factorization is left unchanged in both cases.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::{Axis, arr2};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0, 2.0],
+    ///                [1.0, 1.0, 0.0],
+    ///                [1.0, 2.0, 1.0],
+    ///                [1.0, 3.0, 5.0]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute(&a)?;
+    /// cgs.remove_column(1)?;
+    ///
+    /// let remaining = a.select(Axis(1), &[0, 2]);
+    /// assert!(remaining.all_close(&cgs.q().dot(cgs.r()), 1e-14));
+    /// assert_eq!(cgs.q().dim(), (4, 2));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Error::ColumnOutOfBounds`]: enum.Error.html#variant.ColumnOutOfBounds
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    fn remove_column(&mut self, index: usize) -> Result<()>;
+
+    /// Sets the columns `start..` of Q and R to zero, keeping their shapes. The leading columns,
+    /// and thus the factorization of the leading columns of the factorized matrix, are kept.
+    ///
+    /// Nothing happens if `start` is not smaller than the number of columns. See
+    /// [`compute_truncated`] for a factorization which stops early and clears the remaining
+    /// columns.
+    ///
+    /// [`compute_truncated`]: trait.GramSchmidt.html#method.compute_truncated
+    fn clear_columns_from(&mut self, start: usize);
+
+    /// Replaces the column `index` of the factorized matrix by `new_col`, and orthonormalizes the
+    /// columns from `index` onward anew, keeping the leading columns of Q and R.
+    ///
+    /// Since only Q and R are stored, the columns of the factorized matrix after `index` are
+    /// reconstructed as `Q·R` first, which costs about as much as orthonormalizing them. The result
+    /// agrees with a factorization of the modified matrix from scratch up to rounding errors. The
+    /// settings of the procedure, like pinned columns, are respected.
+    ///
+    /// Returns [`Error::ColumnOutOfBounds`] if `index` is not smaller than the number of columns,
+    /// and [`Error::LengthMismatch`] if `new_col` does not have as many elements as Q has rows.
+    /// If the orthonormalization fails, e.g. with [`Error::RankDeficient`], the factorization has
+    /// to be recomputed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{arr1, arr2};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let mut a = arr2(&[[1.0, 0.0, 2.0],
+    ///                    [1.0, 1.0, 0.0],
+    ///                    [1.0, 2.0, 1.0],
+    ///                    [1.0, 3.0, 5.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    ///
+    /// let predictor = arr1(&[0.5, 0.0, 4.0, 1.0]);
+    /// mgs.update_column(1, &predictor)?;
+    /// a.column_mut(1).assign(&predictor);
+    /// assert!(a.all_close(&mgs.q().dot(mgs.r()), 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Error::ColumnOutOfBounds`]: enum.Error.html#variant.ColumnOutOfBounds
+    /// [`Error::LengthMismatch`]: enum.Error.html#variant.LengthMismatch
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    fn update_column<S>(&mut self, index: usize, new_col: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = Self::Scalar>
+    {
+        let (n_rows, n_cols) = self.q().dim();
+        if index >= n_cols {
+            Err(Error::ColumnOutOfBounds { index, cols: n_cols })?;
+        }
+        if new_col.len() != n_rows {
+            Err(Error::LengthMismatch { expected: n_rows, found: new_col.len() })?;
+        }
+
+        // Replacing a pinned column copies all pinned columns from the input again.
+        let pinned = self.provenance().iter().take_while(|column| column.pinned).count();
+        let first = if index < pinned { 0 } else { index };
+
+        let column_major = utils::get_layout(self.q()) == Some(blas::Layout::ColumnMajor);
+        let mut a = Array2::zeros((n_rows, n_cols).set_f(column_major));
+        a.slice_mut(s![.., first..]).assign(&self.q().dot(&self.r().slice(s![.., first..])));
+        a.column_mut(index).assign(new_col);
+
+        self.compute_from_column(&a, index)
+    }
+
+    /// Checks whether the stored factorization still corresponds to the matrix `a`, i.e. whether
+    /// `‖A - QR‖_F < tol`.
+    ///
+    /// This is cheaper than recomputing the factorization and can be used to decide whether a
+    /// cached factorization has to be recomputed after `a` was (possibly) mutated. Returns `false`
+    /// if the dimensions of `a` don't match the stored factorization.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Classical};
+    /// use ndarray::Array2;
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    ///
+    /// # fn main() {
+    ///
+    /// let mut matrix = Array2::random((10,10), Normal::new(0.0, 1.0));
+    /// let mut cgs = Classical::from_matrix(&matrix).unwrap();
+    /// cgs.compute(&matrix).unwrap();
+    /// assert!(cgs.matches(&matrix, 1e-12));
+    ///
+    /// matrix[(0,0)] += 1.0;
+    /// assert!(!cgs.matches(&matrix, 1e-12));
+    ///
+    /// # }
+    /// ```
+    fn matches<S>(&self, a: &ArrayBase<S, Ix2>, tol: f64) -> bool
+        where S: Data<Elem = Self::Scalar>
+    {
+        match self.reconstruction_error(a) {
+            Ok(error) => error < tol,
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the product `Q·R`, i.e. the factorized matrix up to rounding errors, stored in the
+    /// memory layout of Q.
+    ///
+    /// Since the arithmetic operators of `ndarray` act elementwise, this reads better than
+    /// `q().dot(r())` and matches the configured layout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[2.0, 0.5], [0.0, 0.3]]);
+    /// let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+    /// cgs2.compute(&a)?;
+    /// assert!(a.all_close(&cgs2.reconstruct(), 1e-15));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn reconstruct(&self) -> Array2<Self::Scalar> {
+        let column_major = utils::get_layout(self.q()) == Some(blas::Layout::ColumnMajor);
+        let mut a = Array2::zeros((self.q().rows(), self.r().cols()).set_f(column_major));
+        general_mat_mul(Self::Scalar::one(), self.q(), self.r(), Self::Scalar::zero(), &mut a);
+        a
+    }
+
+    /// Returns the reconstruction error `‖A - QR‖_F` of the stored factorization with respect to
+    /// the matrix `a`, measured in the Frobenius norm.
+    ///
+    /// As the factorized matrix itself is not stored, it has to be passed in again. The product
+    /// `QR` is computed into a scratch buffer. Returns an error if the dimensions of `a` don't
+    /// match the ones of the factorization.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[2.0, 0.5], [0.0, 0.3]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    /// assert!(mgs.reconstruction_error(&a)? < 1e-15);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn reconstruction_error<S>(&self, a: &ArrayBase<S, Ix2>) -> Result<f64>
+        where S: Data<Elem = Self::Scalar>
+    {
+        let expected = (self.q().rows(), self.r().cols());
+        if a.dim() != expected {
+            Err(Error::ShapeMismatch { expected, found: a.dim() })?;
+        }
+        let mut residual = self.q().dot(self.r());
+        residual -= a;
+        Ok(Self::Scalar::to_f64(utils::frobenius_norm(&residual)))
+    }
+
+    /// Returns the arguments describing the stored R to LAPACK routines operating on triangular
+    /// matrices, like `dtrtrs` or `dtrtri`: the memory layout, the `uplo` flag, and the leading
+    /// dimension `lda`.
+    ///
+    /// These are to be used together with the slice `r().as_slice_memory_order()`. Since R is
+    /// stored in its own layout (which differs from the one of Q if it was constructed with
+    /// [`from_shape_with_r_transposed`]), `uplo` is always `b'U'`. To operate on `Rᵀ`, pass
+    /// `trans = b'T'` to the LAPACK routine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gramschmidt::{Classical, GramSchmidt, Layout};
+    /// use ndarray::ShapeBuilder;
+    ///
+    /// let cgs: Classical = Classical::from_shape_with_r_transposed((10, 4).f(), true).unwrap();
+    /// assert_eq!(cgs.r_lapack_args(), (Layout::RowMajor, b'U', 4));
+    /// ```
+    ///
+    /// [`from_shape_with_r_transposed`]: trait.GramSchmidt.html#tymethod.from_shape_with_r_transposed
+    fn r_lapack_args(&self) -> (Layout, u8, i32) {
+        let r = self.r();
+        // NOTE: This unwrap is safe, because R is always allocated contiguously.
+        let layout = utils::get_layout(r).unwrap();
+        (layout.into(), b'U', r.cols() as i32)
+    }
+
+    /// Returns the upper triangle of R in the packed format of LAPACK, e.g. to store many R
+    /// factors in about half the memory, or to pass it to routines like `dtptrs`.
+    ///
+    /// The columns of the upper triangle are stored one after another, so that the entry `(i, j)`
+    /// with `i ≤ j` is found at the index `i + j(j+1)/2` of the `n(n+1)/2` packed entries. This
+    /// corresponds to `uplo = b'U'` for a column major matrix, irrespective of the layout R is
+    /// stored in.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 1.0],
+    ///                [0.0, 2.0]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute(&a)?;
+    /// assert_eq!(cgs.r_packed().to_vec(), vec![1.0, 1.0, 2.0]);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn r_packed(&self) -> Array1<Self::Scalar> {
+        let r = self.r();
+        let n_cols = r.cols();
+        let mut packed = Vec::with_capacity(n_cols * (n_cols + 1) / 2);
+        for (j, column) in r.gencolumns().into_iter().enumerate() {
+            packed.extend(column.iter().take(j + 1).cloned());
+        }
+        Array1::from(packed)
+    }
+
+    /// Returns the projection coefficients of the factorization, i.e. the strict upper triangle
+    /// of R with a zero diagonal.
+    ///
+    /// The entry `(i, j)` with `i < j` is the coefficient `⟨q_i, a_j⟩` with which the `i`-th basis
+    /// vector was removed from the `j`-th column of A. For [`Reorthogonalized`], it is the sum of
+    /// the coefficients of both orthogonalization passes. Together with [`norms`], this separates
+    /// the two kinds of quantities merged into R: `R = projections + diag(norms)`.
+    ///
+    /// [`Reorthogonalized`]: struct.Reorthogonalized.html
+    /// [`norms`]: trait.GramSchmidt.html#method.norms
+    fn projections(&self) -> Array2<Self::Scalar> {
+        let mut projections = self.r().clone();
+        projections.diag_mut().fill(Self::Scalar::zero());
+        projections
+    }
+
+    /// Returns the norms of the columns of A after removing their projections onto the previous
+    /// basis vectors, i.e. the diagonal of R. See [`projections`] for the off-diagonal entries.
+    ///
+    /// [`projections`]: trait.GramSchmidt.html#method.projections
+    fn norms(&self) -> Array1<Self::Scalar> {
+        self.r().diag().to_owned()
+    }
+
+    /// Estimates the numerical rank of the factorized matrix from the largest gap in the
+    /// diagonal of R.
+    ///
+    /// The absolute values of the diagonal entries are sorted in decreasing order, and the rank
+    /// is taken to be the number of entries before the largest ratio between two consecutive
+    /// ones. Unlike counting the entries above a fixed tolerance, this adapts to the scale of the
+    /// matrix, which makes it more reliable for matrices with a clear gap in their spectrum. Note
+    /// that it always reports a gap: for a matrix of full numerical rank without a distinct gap,
+    /// the estimate is not meaningful. A zero diagonal entry counts as an infinite gap, and a
+    /// diagonal without any decrease yields the full rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// // The third column is almost the difference of the second and the first one.
+    /// let a = arr2(&[[1.0, 1.0, 1e-9],
+    ///                [0.0, 1.0, 1.0],
+    ///                [1.0, 0.0, -1.0],
+    ///                [1.0, 2.0, 1.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    /// assert_eq!(mgs.estimated_rank_by_gap(), 2);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn estimated_rank_by_gap(&self) -> usize {
+        let mut diagonal: Vec<<Self::Scalar as GramSchmidtScalar>::Real> =
+            self.r().diag().iter().map(|r_ii| r_ii.modulus()).collect();
+        diagonal.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        if diagonal.is_empty() || diagonal[0].is_zero() {
+            return 0;
+        }
+
+        let mut rank = diagonal.len();
+        let mut largest_gap = <Self::Scalar as GramSchmidtScalar>::Real::one();
+        for (k, pair) in diagonal.windows(2).enumerate() {
+            let gap = pair[0] / pair[1];
+            if gap > largest_gap {
+                largest_gap = gap;
+                rank = k + 1;
+            }
+        }
+        rank
+    }
+
+    /// Returns the orthogonal projection `Pv = Q(Qᵀv)` of the vector `v` onto the span of the
+    /// columns of Q, i.e. the column space of the factorized matrix.
+    ///
+    /// The projector `P = QQᵀ` is never formed: `v` is multiplied by `Qᵀ` and the result by `Q`.
+    /// See [`project_orthogonal`] for the complementary component `v - Pv`.
+    ///
+    /// Returns an error if the length of `v` differs from the number of rows of Q.
+    ///
+    /// [`project_orthogonal`]: trait.GramSchmidt.html#method.project_orthogonal
+    fn project<S>(&self, v: &ArrayBase<S, Ix1>) -> Result<Array1<Self::Scalar>>
+        where S: Data<Elem = Self::Scalar>
+    {
+        let q = self.q();
+        if v.len() != q.rows() {
+            Err(Error::LengthMismatch { expected: q.rows(), found: v.len() })?;
+        }
+
+        let coefficients = Self::Scalar::adjoint_dot(q.view(), v.view());
+        Ok(q.dot(&coefficients))
+    }
+
+    /// Returns the component `v - QQᵀv` of the vector `v` that is orthogonal to the span of the
+    /// columns of Q, i.e. the column space of the factorized matrix.
+    ///
+    /// Returns an error if the length of `v` differs from the number of rows of Q.
+    fn project_orthogonal<S>(&self, v: &ArrayBase<S, Ix1>) -> Result<Array1<Self::Scalar>>
+        where S: Data<Elem = Self::Scalar>
+    {
+        Ok(v - &self.project(v)?)
+    }
+
+    /// Returns the component `v - QQᵀv` of the vector `v` that is orthogonal to the span of the
+    /// columns of Q.
+    #[deprecated(since = "0.6.0", note = "renamed to `project_orthogonal`")]
+    fn project_complement<S>(&self, v: &ArrayBase<S, Ix1>) -> Result<Array1<Self::Scalar>>
+        where S: Data<Elem = Self::Scalar>
+    {
+        self.project_orthogonal(v)
+    }
+
+    /// Returns the angle `θ ∈ [0, π/2]` between the vector `v` and the column space of the
+    /// factorized matrix, with `sin θ = ‖v - QQᵀv‖ / ‖v‖`.
+    ///
+    /// An angle close to zero means that `v` lies almost within the column space, and is thus
+    /// redundant, while an angle close to `π/2` means that it is almost orthogonal to it. The
+    /// angle is computed from both the orthogonal component and the projection, so that it is
+    /// accurate for small and large angles alike. A zero vector has an angle of zero.
+    ///
+    /// Returns an error if the length of `v` differs from the number of rows of Q.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{arr1, arr2};
+    /// use std::f64::consts::FRAC_PI_4;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0], [0.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    ///
+    /// let angle = mgs.angle_to_span(&arr1(&[1.0, 1.0]))?;
+    /// assert!((angle - FRAC_PI_4).abs() < 1e-15);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn angle_to_span<S>(&self, v: &ArrayBase<S, Ix1>) -> Result<f64>
+        where S: Data<Elem = Self::Scalar>
+    {
+        let projection = self.project(v)?;
+        let complement = v - &projection;
+        let complement_norm = utils::normalization(&complement);
+        let projection_norm = utils::normalization(&projection);
+        let angle = complement_norm.atan2(projection_norm);
+        Ok(Self::Scalar::to_f64(angle))
+    }
+
+    /// Returns the largest ratio `|r_ij| / |r_jj|` over the strict upper triangle `i < j` of R.
+    ///
+    /// The off-diagonal entries of the `j`-th column of R are the projections of the `j`-th column
+    /// of A onto the previous basis vectors, while `r_jj` is the norm of what remains after
+    /// removing them. A large ratio thus signals a column that is strongly correlated with the
+    /// preceding ones, which makes it a cheap indicator of ill-conditioning. Returns `0.0` for a
+    /// diagonal R, and infinity if a column with nonzero projections has a zero diagonal entry.
+    fn max_offdiagonal_ratio(&self) -> f64 {
+        let r = self.r();
+        r.indexed_iter()
+            .filter(|&((i, j), _)| i < j)
+            .map(|((_, j), &r_ij)| Self::Scalar::to_f64(r_ij.modulus() / r[(j, j)].modulus()))
+            .filter(|ratio| !ratio.is_nan())
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns the loss of orthogonality `‖I - QᴴQ‖_F` of the computed Q, measured in the
+    /// Frobenius norm, in the real type underlying the scalar.
+    ///
+    /// This allows to judge the quality of a factorization at runtime, e.g. to fall back from
+    /// [`Classical`] to [`Reorthogonalized`] if the error is too large. The Gram matrix `QᴴQ` is
+    /// computed from the stored Q with the conjugate transpose, so that the error of a complex
+    /// factorization is measured as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 1.0], [0.0, 1e-10]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute(&a)?;
+    /// assert!(cgs.orthogonality_error() < 1e-5);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Classical`]: struct.Classical.html
+    /// [`Reorthogonalized`]: struct.Reorthogonalized.html
+    fn orthogonality_error(&self) -> <Self::Scalar as GramSchmidtScalar>::Real {
+        let gram = Self::Scalar::gram(self.q().view());
+        let mut squared_error = <Self::Scalar as GramSchmidtScalar>::Real::zero();
+        for ((i, j), &g_ij) in gram.indexed_iter() {
+            let expected = if i == j { Self::Scalar::one() } else { Self::Scalar::zero() };
+            let deviation = (expected - g_ij).modulus();
+            squared_error += deviation * deviation;
+        }
+        squared_error.sqrt()
+    }
+
+    /// Returns the matrix `Q·R⁻ᵀ`, which for a factorization `A = QR` is equal to `A·(AᵀA)⁻¹` and
+    /// thus contains the rows of the pseudo-inverse of `A` as its columns.
+    ///
+    /// This quantity appears in leverage and influence computations (it is closely related to the
+    /// hat matrix `QQᵀ`). It is calculated by a single triangular solve with the stored R, without
+    /// explicitly inverting it. Returns an error if R is singular.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Classical};
+    /// use ndarray::Array2;
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    ///
+    /// # fn main() {
+    ///
+    /// let matrix = Array2::random((10,10), Normal::new(0.0, 1.0));
+    /// let mut cgs = Classical::from_matrix(&matrix).unwrap();
+    /// cgs.compute(&matrix).unwrap();
+    ///
+    /// let x = cgs.q_rinv_transpose().unwrap();
+    /// assert!(x.dot(&cgs.r().t()).all_close(cgs.q(), 1e-10));
+    ///
+    /// # }
+    /// ```
+    fn q_rinv_transpose(&self) -> Result<Array2<f64>>
+        where Self: GramSchmidt<Scalar = f64>
+    {
+        use blas::Layout::*;
+
+        let r = self.r();
+        if r.diag().iter().any(|&r_ii| r_ii == 0.0) {
+            Err(Error::Singular)?;
+        }
+
+        let mut x = self.q().clone();
+        let (n_rows, n_cols) = x.dim();
+        let layout = match utils::get_layout(&x) {
+            Some(layout) => layout,
+            None => Err(Error::NonContiguous)?,
+        };
+        let (r_slice, uplo, trans) = match utils::as_triangular_operand(r, layout, blas::Transpose::Ordinary) {
+            Some(operand) => operand,
+            None => Err(Error::NonContiguous)?,
+        };
+        let leading_dim = match layout {
+            ColumnMajor => n_rows as i32,
+            RowMajor => n_cols as i32,
+        };
+
+        // NOTE: This unwrap is safe, because x is a freshly cloned, contiguous array.
+        let x_slice = x.as_slice_memory_order_mut().unwrap();
+
+        // Solve X · Rᵀ = Q for X, overwriting the copy of Q.
+        unsafe {
+            blas::dtrsm(
+                layout,
+                blas::Side::Right,
+                uplo,
+                trans,
+                blas::Diagonal::Generic,
+                n_rows as i32,
+                n_cols as i32,
+                1.0,
+                r_slice,
+                n_cols as i32,
+                x_slice,
+                leading_dim,
+            );
+        }
+
+        Ok(x)
+    }
+
+    /// Returns the matrix `(AᵀA)⁻¹ = R⁻¹R⁻ᵀ` for a factorization `A = QR`, which is the unscaled
+    /// covariance matrix of the coefficients of a linear regression with the design matrix `A`.
+    ///
+    /// R is inverted by a triangular solve against the identity, so that neither `AᵀA` is formed
+    /// nor inverted explicitly. Returns an error if R is singular.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::Array2;
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = Array2::random((20, 4), Normal::new(0.0, 1.0));
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    ///
+    /// let covariance = mgs.normal_equations_inverse()?;
+    /// assert!(covariance.dot(&a.t().dot(&a)).all_close(&Array2::eye(4), 1e-10));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn normal_equations_inverse(&self) -> Result<Array2<f64>>
+        where Self: GramSchmidt<Scalar = f64>
+    {
+        let r = self.r();
+        if r.diag().iter().any(|&r_ii| r_ii == 0.0) {
+            Err(Error::Singular)?;
+        }
+
+        let n = r.rows();
+        let (r_slice, layout) = match utils::as_slice_with_layout(r) {
+            Some(r) => r,
+            None => Err(Error::NonContiguous)?,
+        };
+        let mut r_inv = Array2::<f64>::eye(n);
+        if layout == blas::Layout::ColumnMajor {
+            r_inv = r_inv.reversed_axes();
+        }
+
+        // NOTE: This unwrap is safe, because the identity is a freshly allocated, contiguous array.
+        let r_inv_slice = r_inv.as_slice_memory_order_mut().unwrap();
+
+        // Solve R · X = I for X, overwriting the identity.
+        unsafe {
+            blas::dtrsm(
+                layout,
+                blas::Side::Left,
+                blas::Part::Upper,
+                blas::Transpose::None,
+                blas::Diagonal::Generic,
+                n as i32,
+                n as i32,
+                1.0,
+                r_slice,
+                n as i32,
+                r_inv_slice,
+                n as i32,
+            );
+        }
+
+        Ok(r_inv.dot(&r_inv.t()))
+    }
+
+    /// Returns the determinant of the factorized square matrix A.
+    ///
+    /// With the diagonal of R being positive, the determinant is `±∏ r_ii`, where the sign is the
+    /// determinant of the orthogonal Q. The sign is found by an LU decomposition of Q with
+    /// partial pivoting, which is stable because Q is perfectly conditioned. Use [`ln_abs_det`]
+    /// if the product might overflow or underflow.
+    ///
+    /// Returns [`Error::NotSquare`] if A is not square.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{Classical, GramSchmidt};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[0.0, 2.0], [3.0, 1.0]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute(&a)?;
+    /// assert!((cgs.det()? + 6.0).abs() < 1e-14);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ln_abs_det`]: trait.GramSchmidt.html#method.ln_abs_det
+    /// [`Error::NotSquare`]: enum.Error.html#variant.NotSquare
+    fn det(&self) -> Result<f64>
+        where Self: GramSchmidt<Scalar = f64>
+    {
+        let q = self.q();
+        if q.rows() != q.cols() {
+            Err(Error::NotSquare)?;
+        }
+        let product: f64 = self.r().diag().iter().product();
+        if product == 0.0 {
+            return Ok(0.0);
+        }
+        Ok(utils::orthogonal_det_sign(q) * product)
+    }
+
+    /// Returns the logarithm of the absolute value of the determinant of the factorized matrix,
+    /// `∑ ln |r_ii|`, which unlike [`det`] neither overflows nor underflows for large matrices.
+    ///
+    /// For a tall matrix A, this is the logarithm of the volume spanned by its columns,
+    /// `½ ln det(AᴴA)`. Returns negative infinity if R is singular.
+    ///
+    /// [`det`]: trait.GramSchmidt.html#method.det
+    fn ln_abs_det(&self) -> f64 {
+        self.r().diag().iter()
+            .map(|r_ii| Self::Scalar::to_f64(r_ii.modulus()).ln())
+            .sum()
+    }
+
+    /// Solves the linear least squares problem `min ‖Ax - b‖₂` for a factorization `A = QR`.
+    ///
+    /// The right hand side `Qᵀb` is computed by a matrix vector product (blas `dgemv`), and the
+    /// system `Rx = Qᵀb` is then solved by back substitution (blas `dtrsv`). Returns an error if
+    /// the length of `b` differs from the number of rows of Q, or if R is singular.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::{arr1, arr2};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// // Fit a line through the points (0, 1), (1, 2), and (2, 4).
+    /// let a = arr2(&[[1.0, 0.0], [1.0, 1.0], [1.0, 2.0]]);
+    /// let b = arr1(&[1.0, 2.0, 4.0]);
+    /// let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+    /// cgs2.compute(&a)?;
+    ///
+    /// let x = cgs2.solve(&b)?;
+    /// assert!(x.all_close(&arr1(&[5.0 / 6.0, 1.5]), 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn solve<S>(&self, b: &ArrayBase<S, Ix1>) -> Result<Array1<f64>>
+        where S: Data<Elem = f64>,
+              Self: GramSchmidt<Scalar = f64>,
+    {
+        use blas::Layout::*;
+
+        let q = self.q();
+        let r = self.r();
+        let (n_rows, n_cols) = q.dim();
+        if b.len() != n_rows {
+            Err(Error::LengthMismatch { expected: n_rows, found: b.len() })?;
+        }
+        if r.diag().iter().any(|&r_ii| r_ii == 0.0) {
+            Err(Error::Singular)?;
+        }
+
+        // NOTE: This unwrap is safe, because Q is always allocated contiguously.
+        let (q_slice, q_layout) = utils::as_slice_with_layout(q).unwrap();
+        let leading_dim = match q_layout {
+            ColumnMajor => n_rows,
+            RowMajor => n_cols,
+        };
+        // b is copied, because it might not be contiguous.
+        let b: Vec<f64> = b.iter().cloned().collect();
+        let mut x = Array1::<f64>::zeros(n_cols);
+        // NOTE: This unwrap is safe, because R is always allocated contiguously.
+        let (r_slice, r_layout) = utils::as_slice_with_layout(r).unwrap();
+        // NOTE: This unwrap is safe, because x is a freshly allocated, contiguous array.
+        let x_slice = x.as_slice_mut().unwrap();
+
+        // Compute x = Qᵀb, and then solve R · x = Qᵀb for x in place.
+        unsafe {
+            blas::dgemv(
+                q_layout,
+                blas::Transpose::Ordinary,
+                n_rows as i32,
+                n_cols as i32,
+                1.0,
+                q_slice,
+                leading_dim as i32,
+                &b,
+                1,
+                0.0,
+                x_slice,
+                1,
+            );
+            blas::dtrsv(
+                r_layout,
+                blas::Part::Upper,
+                blas::Transpose::None,
+                blas::Diagonal::Generic,
+                n_cols as i32,
+                r_slice,
+                n_cols as i32,
+                x_slice,
+                1,
+            );
+        }
+
+        Ok(x)
+    }
+
+    /// Solves the linear least squares problems `min ‖AX - B‖_F` for all columns of `b` at once,
+    /// for a factorization `A = QR`.
+    ///
+    /// The right hand sides `QᵀB` are computed by a matrix product (blas `dgemm`), and the system
+    /// `RX = QᵀB` is then solved by back substitution (blas `dtrsm`). The solution is returned in
+    /// the memory layout of Q. Returns an error if the number of rows of `b` differs from that of
+    /// Q, or if R is singular. See [`solve`] for a single right hand side.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{arr2, Axis};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0], [1.0, 1.0], [1.0, 2.0]]);
+    /// let b = arr2(&[[1.0, 0.0], [2.0, 1.0], [4.0, 2.0]]);
+    /// let mut mgs = Modified::from_matrix(&a)?;
+    /// mgs.compute(&a)?;
+    ///
+    /// let x = mgs.solve_many(&b)?;
+    /// assert!(x.all_close(&arr2(&[[5.0 / 6.0, 0.0], [1.5, 1.0]]), 1e-14));
+    /// assert!(x.column(0).all_close(&mgs.solve(&b.column(0))?, 1e-14));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`solve`]: trait.GramSchmidt.html#method.solve
+    fn solve_many<S>(&self, b: &ArrayBase<S, Ix2>) -> Result<Array2<f64>>
+        where S: Data<Elem = f64>,
+              Self: GramSchmidt<Scalar = f64>,
+    {
+        use blas::Layout::*;
+
+        let q = self.q();
+        let r = self.r();
+        let (n_rows, n_cols) = q.dim();
+        let n_rhs = b.cols();
+        if b.rows() != n_rows {
+            Err(Error::ShapeMismatch { expected: (n_rows, n_rhs), found: b.dim() })?;
+        }
+        if r.diag().iter().any(|&r_ii| r_ii == 0.0) {
+            Err(Error::Singular)?;
+        }
+
+        // NOTE: This unwrap is safe, because Q is always allocated contiguously.
+        let (q_slice, layout) = utils::as_slice_with_layout(q).unwrap();
+        let (r_slice, uplo, trans) = match utils::as_triangular_operand(r, layout, blas::Transpose::None) {
+            Some(operand) => operand,
+            None => Err(Error::NonContiguous)?,
+        };
+        let column_major = layout == ColumnMajor;
+        let leading_dims = |rows: usize, cols: usize| match layout {
+            ColumnMajor => rows as i32,
+            RowMajor => cols as i32,
+        };
+
+        // b is copied into the layout of Q, because it might not be contiguous.
+        let mut b_copy = Array2::<f64>::zeros((n_rows, n_rhs).set_f(column_major));
+        b_copy.assign(b);
+        let mut x = Array2::<f64>::zeros((n_cols, n_rhs).set_f(column_major));
+        // NOTE: These unwraps are safe, because both arrays are freshly allocated and contiguous.
+        let b_slice = b_copy.as_slice_memory_order().unwrap();
+        let x_slice = x.as_slice_memory_order_mut().unwrap();
+
+        // Compute X = QᵀB, and then solve R · X = QᵀB for X in place.
+        unsafe {
+            blas::dgemm(
+                layout,
+                blas::Transpose::Ordinary,
+                blas::Transpose::None,
+                n_cols as i32,
+                n_rhs as i32,
+                n_rows as i32,
+                1.0,
+                q_slice,
+                leading_dims(n_rows, n_cols),
+                b_slice,
+                leading_dims(n_rows, n_rhs),
+                0.0,
+                x_slice,
+                leading_dims(n_cols, n_rhs),
+            );
+            blas::dtrsm(
+                layout,
+                blas::Side::Left,
+                uplo,
+                trans,
+                blas::Diagonal::Generic,
+                n_cols as i32,
+                n_rhs as i32,
+                1.0,
+                r_slice,
+                n_cols as i32,
+                x_slice,
+                leading_dims(n_cols, n_rhs),
+            );
+        }
+
+        Ok(x)
+    }
+
+    /// Writes the matrices Q and R to `writer` in the [Matrix Market] `array` format, one after
+    /// the other. See the [`matrix_market`] module for details of the format.
+    ///
+    /// The factorization can be restored with [`read_matrix_market`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Classical};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[2.0, 0.5], [0.0, 0.3]]);
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    /// cgs.compute(&a)?;
+    ///
+    /// let mut buffer = Vec::new();
+    /// cgs.write_matrix_market(&mut buffer)?;
+    /// let restored = Classical::read_matrix_market(&buffer[..])?;
+    /// assert_eq!(cgs.q(), restored.q());
+    /// assert_eq!(cgs.r(), restored.r());
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [Matrix Market]: https://math.nist.gov/MatrixMarket/formats.html
+    /// [`matrix_market`]: matrix_market/index.html
+    /// [`read_matrix_market`]: trait.GramSchmidt.html#method.read_matrix_market
+    fn write_matrix_market<W>(&self, mut writer: W) -> io::Result<()>
+        where W: io::Write,
+              Self: GramSchmidt<Scalar = f64>,
+    {
+        matrix_market::write_matrix(&mut writer, self.q())?;
+        matrix_market::write_matrix(&mut writer, self.r())
+    }
+
+    /// Reads the matrices Q and R written by [`write_matrix_market`] from `reader` and
+    /// constructs the factorization via [`from_parts`].
+    ///
+    /// Since the Matrix Market format stores matrices column by column, the restored
+    /// factorization is configured for column major (Fortran layout) matrices.
+    ///
+    /// [`write_matrix_market`]: trait.GramSchmidt.html#method.write_matrix_market
+    /// [`from_parts`]: trait.GramSchmidt.html#tymethod.from_parts
+    fn read_matrix_market<R>(mut reader: R) -> Result<Self>
+        where R: io::BufRead,
+              Self: GramSchmidt<Scalar = f64>,
+    {
+        let q = matrix_market::read_matrix(&mut reader)?;
+        let r = matrix_market::read_matrix(&mut reader)?;
+        Self::from_parts(q, r)
+    }
+
+    // Blanket impls
+    /// One-off version of [`compute`]. Takes the matrix `a` to be factorized, allocates a type
+    /// implementing the `GramSchmidt` trait, computes the QR decomposition, and returns clones of
+    /// the Q and R matrices.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#method.compute
+    #[allow(clippy::type_complexity)]
+    fn compute_once<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<Self::Scalar>, Array2<Self::Scalar>)>
+        where S: Data<Elem = Self::Scalar>,
+    {
+        let mut gram_schmidt = Self::from_matrix(a)?;
+        gram_schmidt.compute(a)?;
+        Ok((gram_schmidt.q().clone(), gram_schmidt.r().clone()))
+    }
+
+    /// Like [`compute_once`], but returns Q together with the [`norms`], i.e. the diagonal of R,
+    /// for users who don't need the projection coefficients.
+    ///
+    /// [`compute_once`]: trait.GramSchmidt.html#method.compute_once
+    /// [`norms`]: trait.GramSchmidt.html#method.norms
+    #[allow(clippy::type_complexity)]
+    fn compute_qn<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<Self::Scalar>, Array1<Self::Scalar>)>
+        where S: Data<Elem = Self::Scalar>,
+    {
+        let mut gram_schmidt = Self::from_matrix(a)?;
+        gram_schmidt.compute(a)?;
+        let norms = gram_schmidt.norms();
+        Ok((gram_schmidt.q().clone(), norms))
+    }
+
+    /// Computes the QR decompositions of a stack of `k` matrices of shape `m×n`, given as a
+    /// `k×m×n` array, returning the stacked `k×m×n` Qs and `k×n×n` Rs.
+    ///
+    /// The matrices are factorized one after another by a single procedure, which is allocated
+    /// for a row major layout once. Each matrix is copied into a buffer first, so that the stack
+    /// may be stored in any layout. With the `rayon` feature, [`par_compute_batch`] factorizes
+    /// the matrices in parallel instead.
+    ///
+    /// Returns the first error encountered, e.g. [`Error::RankDeficient`] for a matrix with
+    /// linearly dependent columns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Modified};
+    /// use ndarray::{Array3, Axis};
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let stack = Array3::from_shape_fn((3, 4, 2), |(k, i, j)| (k + 1) as f64 / (i + 2 * j + 1) as f64);
+    /// let (qs, rs) = Modified::compute_batch(&stack)?;
+    /// for k in 0..3 {
+    ///     let a = qs.index_axis(Axis(0), k).dot(&rs.index_axis(Axis(0), k));
+    ///     assert!(a.all_close(&stack.index_axis(Axis(0), k), 1e-14));
+    /// }
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`par_compute_batch`]: trait.ParallelGramSchmidt.html#method.par_compute_batch
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    #[allow(clippy::type_complexity)]
+    fn compute_batch<S>(stack: &ArrayBase<S, Ix3>) -> Result<(Array3<Self::Scalar>, Array3<Self::Scalar>)>
+        where S: Data<Elem = Self::Scalar>,
+    {
+        let (k, n_rows, n_cols) = stack.dim();
+        let mut gram_schmidt = Self::from_shape((n_rows, n_cols))?;
+        let mut buffer = Array2::zeros((n_rows, n_cols));
+        let mut qs = Array3::zeros((k, n_rows, n_cols));
+        let mut rs = Array3::zeros((k, n_cols, n_cols));
+        for (i, a) in stack.axis_iter(Axis(0)).enumerate() {
+            buffer.assign(&a);
+            gram_schmidt.compute(&buffer)?;
+            qs.index_axis_mut(Axis(0), i).assign(gram_schmidt.q());
+            rs.index_axis_mut(Axis(0), i).assign(gram_schmidt.r());
+        }
+        Ok((qs, rs))
+    }
+
+    /// Reserves the memory for a QR decomposition of `a` and copies `a` into a [`PreparedInput`]
+    /// with exactly the layout this procedure expects.
+    ///
+    /// Unlike [`from_matrix`], this also accepts non-contiguous arrays like strided views. The
+    /// shape and layout are fixed up front, so that the prepared input can then be factorized
+    /// (repeatedly) with [`compute_prepared`], which only fails on the contents of the matrix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{GramSchmidt, Reorthogonalized};
+    /// use ndarray::{Array2, s};
+    /// use ndarray_rand::RandomExt;
+    /// use rand::distributions::Normal;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let matrix = Array2::random((10,20), Normal::new(0.0, 1.0));
+    /// // Every second column, which is not contiguous.
+    /// let view = matrix.slice(s![.., ..;2]);
+    ///
+    /// let (mut cgs2, input) = Reorthogonalized::prepared(&view)?;
+    /// cgs2.compute_prepared(&input)?;
+    /// assert!(view.all_close(&cgs2.q().dot(cgs2.r()), 1e-12));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`PreparedInput`]: struct.PreparedInput.html
+    /// [`from_matrix`]: trait.GramSchmidt.html#method.from_matrix
+    /// [`compute_prepared`]: trait.GramSchmidt.html#method.compute_prepared
+    fn prepared<S>(a: &ArrayBase<S, Ix2>) -> Result<(Self, PreparedInput<Self::Scalar>)>
+        where S: Data<Elem = Self::Scalar>
+    {
+        if utils::is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        let input = PreparedInput::new(a);
+        let gram_schmidt = Self::from_matrix(input.as_array())?;
+        Ok((gram_schmidt, input))
+    }
+
+    /// Computes the QR decomposition of an input prepared for this procedure by [`prepared`].
+    ///
+    /// Returns the same errors as [`compute`] which depend on the contents of the matrix, e.g.
+    /// [`Error::RankDeficient`], [`Error::NonFinite`] or [`Error::NotOrthonormal`] for pinned
+    /// columns. If `input` was prepared for a factorization of a different shape,
+    /// [`Error::ShapeMismatch`] is returned.
+    ///
+    /// [`prepared`]: trait.GramSchmidt.html#method.prepared
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    /// [`Error::ShapeMismatch`]: enum.Error.html#variant.ShapeMismatch
+    fn compute_prepared(&mut self, input: &PreparedInput<Self::Scalar>) -> Result<()> {
+        self.compute(input.as_array())
+    }
+
+    /// Uses a matrix to reserve memory for a QR decomposition via a classical Gram Schmidt.
+    ///
+    /// The resulting object can be used to orthogonalize matrices of the same dimensions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ndarray::Array;
+    /// use gramschmidt::{
+    ///     Classical,
+    ///     GramSchmidt,
+    ///     Result,
+    /// };
+    ///
+    /// # fn main() -> Result<()> {
+    ///
+    /// let a = Array::<f64, _>::zeros((10, 10));
+    /// let mut cgs = Classical::from_matrix(&a)?;
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn from_matrix<S>(a: &ArrayBase<S, Ix2>) -> Result<Self>
+        where S: Data<Elem = Self::Scalar>
+    {
+        use blas::Layout::*;
+        if utils::is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        let dim = a.dim();
+        let shape = match utils::get_layout(a) {
+            Some(ColumnMajor) => dim.f(),
+            Some(RowMajor) => dim.into_shape(),
+            None => Err(Error::NonContiguous)?,
+        };
+
+        Self::from_shape(shape)
+    }
+
+}
+
+/// Performs a trivial blas computation and checks its result, returning `false` if the call
+/// panicked or produced a wrong result.
+///
+/// This is intended as a sanity check at application startup, so that a misconfigured blas
+/// backend (e.g. one using 64 bit integers where 32 bit ones are expected) can be reported with a
+/// clear message instead of producing garbage deep inside a factorization. Note that missing blas
+/// symbols are usually detected by the linker already, and that crashes inside the blas library
+/// (like a segmentation fault) can not be caught by this check. With the `pure-rust` feature, no
+/// blas library is used and the check always succeeds.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// assert!(gramschmidt::blas_available());
+/// ```
+pub fn blas_available() -> bool {
+    let x = [3.0f64, 4.0];
+    panic::catch_unwind(|| unsafe { blas::dnrm2(2, &x[..], 1) })
+        .map(|norm| (norm - 5.0).abs() < 1e-12)
+        .unwrap_or(false)
+}
+
+/// Convenience function that calculates a [Classical Gram Schmidt] QR factorization, returning
+/// it as a [`Qr`]. The tuple `(Q,R)` is obtained with [`Qr::into_tuple`].
+///
+/// If you want to repeatedly calculate QR factorizations, then prefer constructing a [`Classical`]
+/// struct and calling its [`GramSchmidt::compute`] method implemented through the [`GramSchmidt`] trait.
+///
+/// Like all convenience functions, this accepts views without copying them, e.g. a block of
+/// columns of a larger column major matrix. Views which are not contiguous are rejected with
+/// [`Error::NonContiguous`]; they can be copied with [`GramSchmidt::prepared`].
+///
+/// [Classical Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process
+/// [`Qr`]: struct.Qr.html
+/// [`Qr::into_tuple`]: struct.Qr.html#method.into_tuple
+/// [`Error::NonContiguous`]: enum.Error.html#variant.NonContiguous
+/// [`GramSchmidt::prepared`]: trait.GramSchmidt.html#method.prepared
+/// [`Classical`]: Classical
+/// [GramSchmidt]: GramSchmidt
+/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+pub fn cgs_qr<S>(a: &ArrayBase<S, Ix2>) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let (q, r) = Classical::compute_once(a)?;
+    Ok(Qr::from_factors(q, r, None))
+}
+
+/// Convenience function that calculates a Reorthogonalized Gram Schmmidt QR factorization (see
+/// [Giraud et al.] for details), returning it as a [`Qr`].
+///
+/// If you want to repeatedly calculate QR factorizations, then prefer constructing a
+/// [`Reorthogonalized`] struct and calling its [`GramSchmidt::compute`] method implemented through
+/// the [`GramSchmidt`] trait.
+///
+/// [Giraud et al.]: https://doi.org/10.1007/s00211-005-0615-4
+/// [`Qr`]: struct.Qr.html
+/// [`Reorthogonalized`]: Reorthogonalized
+/// [`GramSchmidt`]: GramSchmidt
+/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+pub fn cgs2_qr<S>(a: &ArrayBase<S, Ix2>) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let (q, r) = Reorthogonalized::compute_once(a)?;
+    Ok(Qr::from_factors(q, r, None))
+}
+
+/// Convenience function that calculates a [Modified Gram Schmidt] QR factorization, returning
+/// it as a [`Qr`].
+///
+/// If you want to repeatedly calculate QR factorizations, then prefer constructing a
+/// [`Modified`] struct and calling its [`GramSchmidt::compute`] method implemented through
+/// the [`GramSchmidt`] trait.
+///
+/// [Modified Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process#Numerical_stabilty
+/// [`Qr`]: struct.Qr.html
+/// [`Modified`]: Modified
+/// [`GramSchmidt`]: GramSchmidt
+/// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+pub fn mgs_qr<S>(a: &ArrayBase<S, Ix2>) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let (q, r) = Modified::compute_once(a)?;
+    Ok(Qr::from_factors(q, r, None))
+}
+
+/// Convenience function that calculates a [Classical Gram Schmidt] QR factorization, returning a
+/// tuple `(Q,R)`. Superseded by [`cgs_qr`], which returns a [`Qr`].
+///
+/// [Classical Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process
+/// [`cgs_qr`]: fn.cgs_qr.html
+/// [`Qr`]: struct.Qr.html
+#[deprecated(since = "0.6.0", note = "use `cgs_qr`, which returns a `Qr`")]
+pub fn cgs<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
+    where S: Data<Elem=f64>
+{
+    cgs_qr(a).map(Qr::into_tuple)
+}
+
+/// Convenience function that calculates a Reorthogonalized Gram Schmmidt QR factorization,
+/// returning a tuple `(Q,R)`. Superseded by [`cgs2_qr`], which returns a [`Qr`].
+///
+/// [`cgs2_qr`]: fn.cgs2_qr.html
+/// [`Qr`]: struct.Qr.html
+#[deprecated(since = "0.6.0", note = "use `cgs2_qr`, which returns a `Qr`")]
+pub fn cgs2<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
+    where S: Data<Elem=f64>
+{
+    cgs2_qr(a).map(Qr::into_tuple)
+}
+
+/// Convenience function that calculates a [Modified Gram Schmidt] QR factorization, returning a
+/// tuple `(Q,R)`. Superseded by [`mgs_qr`], which returns a [`Qr`].
+///
+/// [Modified Gram Schmidt]: https://en.wikipedia.org/wiki/Gram-Schmidt_process#Numerical_stabilty
+/// [`mgs_qr`]: fn.mgs_qr.html
+/// [`Qr`]: struct.Qr.html
+#[deprecated(since = "0.6.0", note = "use `mgs_qr`, which returns a `Qr`")]
+pub fn mgs<S>(a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
+    where S: Data<Elem=f64>
+{
+    mgs_qr(a).map(Qr::into_tuple)
+}
+
+/// Like [`cgs_qr`], but configures the procedure with `options` before the factorization.
+///
+/// Returns [`Error::TooManyPinned`] if more columns are pinned than `a` has.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{cgs_with_options, ProcedureOptions};
+/// use ndarray::arr2;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// // The first column is already normalized and is kept as is.
+/// let a = arr2(&[[0.6, 1.0],
+///                [0.8, 1.0]]);
+/// let options = ProcedureOptions { pinned: 1, ..ProcedureOptions::default() };
+/// let qr = cgs_with_options(&a, &options)?;
+/// assert_eq!(qr.q().column(0), a.column(0));
+/// assert!(a.all_close(&qr.reconstruct(), 1e-15));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`cgs_qr`]: fn.cgs_qr.html
+/// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+pub fn cgs_with_options<S>(a: &ArrayBase<S, Ix2>, options: &ProcedureOptions) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let mut cgs = Classical::from_matrix(a)?;
+    cgs.set_pinned(options.pinned)?;
+    if let Some(tolerance) = options.tolerance {
+        cgs.set_tolerance(tolerance);
+    }
+    cgs.compute(a)?;
+    Ok(Qr::from_factors(cgs.q().clone(), cgs.r().clone(), Some(cgs.tolerance())))
+}
+
+/// Like [`cgs2_qr`], but configures the procedure with `options` before the factorization.
+///
+/// Returns [`Error::TooManyPinned`] if more columns are pinned than `a` has.
+///
+/// [`cgs2_qr`]: fn.cgs2_qr.html
+/// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+pub fn cgs2_with_options<S>(a: &ArrayBase<S, Ix2>, options: &ProcedureOptions) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let mut cgs2 = Reorthogonalized::from_matrix(a)?;
+    cgs2.set_pinned(options.pinned)?;
+    if let Some(tolerance) = options.tolerance {
+        cgs2.set_tolerance(tolerance);
+    }
+    cgs2.set_reorthogonalization_tolerance(options.reorthogonalization_tolerance);
+    cgs2.compute(a)?;
+    Ok(Qr::from_factors(cgs2.q().clone(), cgs2.r().clone(), Some(cgs2.tolerance())))
+}
+
+/// Like [`mgs_qr`], but configures the procedure with `options` before the factorization.
+///
+/// Returns [`Error::TooManyPinned`] if more columns are pinned than `a` has.
+///
+/// [`mgs_qr`]: fn.mgs_qr.html
+/// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+pub fn mgs_with_options<S>(a: &ArrayBase<S, Ix2>, options: &ProcedureOptions) -> Result<Qr>
+    where S: Data<Elem=f64>
+{
+    let mut mgs = Modified::from_matrix(a)?;
+    mgs.set_pinned(options.pinned)?;
+    if let Some(tolerance) = options.tolerance {
+        mgs.set_tolerance(tolerance);
+    }
+    mgs.compute(a)?;
+    Ok(Qr::from_factors(mgs.q().clone(), mgs.r().clone(), Some(mgs.tolerance())))
+}
+
+/// Restores the orthonormality of the columns of `q` in place by `passes` sweeps of the
+/// classical Gram Schmidt procedure, e.g. for a basis which has drifted from orthonormality over
+/// many iterations of an algorithm updating it.
+///
+/// Every sweep orthonormalizes the columns of `q` against each other, discarding R, which is
+/// close to the identity for a nearly orthonormal `q`. This is the repeated classical Gram Schmidt
+/// procedure shown in the `lauchli` example: starting from a basis whose loss of orthogonality
+/// is well below one, a single sweep restores it to machine precision, while a strongly drifted
+/// basis may need a second one. Each sweep takes about `2mn²` operations for an `m×n` matrix.
+///
+/// Returns an error if `q` is wider than tall, or if its columns are linearly dependent.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::reorthonormalize;
+/// use ndarray::{arr2, Array2};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let mut q = arr2(&[[1.0, 1e-6],
+///                    [0.0, 1.0],
+///                    [0.0, 0.0]]);
+/// reorthonormalize(&mut q, 1)?;
+/// assert!(q.t().dot(&q).all_close(&Array2::eye(2), 1e-15));
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn reorthonormalize(q: &mut Array2<f64>, passes: usize) -> Result<()> {
+    if passes == 0 {
+        return Ok(());
+    }
+
+    let mut cgs = Classical::from_matrix(q)?;
+    for _ in 0..passes {
+        cgs.compute(q)?;
+        q.assign(cgs.q());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn reorthonormalize_restores_orthogonality() {
+        let mut q = cgs2_qr(&arr2(
+            &[[2.0, 0.5, 0.0, 1.0],
+              [0.0, 0.3, 0.0, 0.1],
+              [0.0, 1.0, 0.7, 0.0],
+              [1.0, 0.0, 0.2, 3.0],
+              [0.5, 0.5, 0.5, 0.5]]
+        )).unwrap().into_tuple().0;
+        let orthonormal = q.clone();
+        q.mapv_inplace(|q_ij| q_ij * (1.0 + 1e-6 * q_ij.sin()));
+        q[(2, 1)] += 1e-7;
+        let mut f_q = Array2::zeros(q.dim().f());
+        f_q.assign(&q);
+
+        for q in &mut [q, f_q] {
+            assert!(utils::orthogonality_loss(q) > 1e-7);
+            reorthonormalize(q, 1).unwrap();
+            assert!(utils::orthogonality_loss(q) < 1e-12);
+            assert!(q.all_close(&orthonormal, 1e-5));
+
+            let reorthonormalized = q.clone();
+            reorthonormalize(q, 0).unwrap();
+            assert_eq!(q, &reorthonormalized);
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_functions_return_tuples() {
+        let a = arr2(
+            &[[2.0, 0.5, 0.0],
+              [0.0, 0.3, 0.0],
+              [0.0, 1.0, 0.7],
+              [1.0, 0.0, 3.0]]
+        );
+        assert_eq!(cgs(&a).unwrap(), cgs_qr(&a).unwrap().into_tuple());
+        assert_eq!(cgs2(&a).unwrap(), cgs2_qr(&a).unwrap().into_tuple());
+        assert_eq!(mgs(&a).unwrap(), mgs_qr(&a).unwrap().into_tuple());
+    }
+
+    type Convenience = fn(&ArrayView2<'_, f64>) -> Result<Qr>;
+
+    fn convenience_functions() -> Vec<(&'static str, Convenience)> {
+        vec![
+            ("cgs_qr", |a| cgs_qr(a)),
+            ("cgs2_qr", |a| cgs2_qr(a)),
+            ("mgs_qr", |a| mgs_qr(a)),
+            ("cgs_with_options", |a| cgs_with_options(a, &ProcedureOptions::default())),
+            ("cgs2_with_options", |a| cgs2_with_options(a, &ProcedureOptions::default())),
+            ("mgs_with_options", |a| mgs_with_options(a, &ProcedureOptions::default())),
+        ]
+    }
+
+    #[test]
+    fn contiguous_views_are_factorized() {
+        let a = matrix(8, 6);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        // Blocks of columns of a column major and of rows of a row major matrix are contiguous.
+        let views = [f_a.slice(s![.., 0..4]), f_a.slice(s![.., 2..5]), a.slice(s![0..6, ..]), a.slice(s![1..7, ..])];
+        for (name, function) in convenience_functions() {
+            for view in views.iter() {
+                let qr = function(view).unwrap_or_else(|err| panic!("{} failed: {}", name, err));
+                let expected = function(&view.to_owned().view()).unwrap();
+                assert_eq!(qr.q(), expected.q(), "{}", name);
+                assert_eq!(qr.r(), expected.r(), "{}", name);
+                assert_eq!(qr.rank(), view.cols(), "{}", name);
+                assert!(view.all_close(&qr.reconstruct(), 1e-14), "{}", name);
+            }
+        }
+    }
+
+    /// A square matrix which is upper triangular up to the entry (2, 1).
+    fn small() -> Array2<f64> {
+        arr2(
+            &[[2.0, 0.5, 0.0, 0.0],
+              [0.0, 0.3, 0.0, 0.0],
+              [0.0, 1.0, 0.7, 0.0],
+              [0.0, 0.0, 0.0, 3.0]]
+        )
+    }
+
+    /// Returns `a` and a copy of it in column major layout.
+    fn with_f_order(a: Array2<f64>) -> [Array2<f64>; 2] {
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        [a, f_a]
+    }
+
+    /// Returns the classical procedure after factorizing `a`. The features tested with it are
+    /// shared by all procedures, either as default methods or through common helpers.
+    fn factorized<S>(a: &ArrayBase<S, Ix2>) -> Classical<f64>
+        where S: Data<Elem = f64>
+    {
+        let mut cgs = Classical::from_matrix(a).unwrap();
+        cgs.compute(a).unwrap();
+        cgs
+    }
+
+    #[test]
+    fn orthogonality_error_matches_frobenius_norm() {
+        for a in &with_f_order(matrix(8, 6)) {
+            let cgs = factorized(a);
+            let gram = cgs.q().t().dot(cgs.q());
+            let mut squared_error = 0.0;
+            for ((i, j), g_ij) in gram.indexed_iter() {
+                let deviation = if i == j { 1.0 - g_ij } else { -g_ij };
+                squared_error += deviation * deviation;
+            }
+
+            let error = cgs.orthogonality_error();
+            assert!((error - squared_error.sqrt()).abs() < 1e-15);
+            assert!(error < 1e-12);
+        }
+    }
+
+    #[test]
+    fn orthogonality_error_of_f32_and_complex_factorizations() {
+        use num_complex::{Complex32, Complex64};
+
+        let mut cgs: Classical<f32> = factorized_by(&small().mapv(|x| x as f32));
+        assert!(cgs.orthogonality_error() < 1e-5);
+        // Stretching a column only changes its diagonal entry of the Gram matrix, to 1.5² - 1.
+        cgs.q_mut().column_mut(1).mapv_inplace(|q_i| 1.5 * q_i);
+        assert!((cgs.orthogonality_error() - 1.25).abs() < 1e-5);
+
+        let mut cgs: Classical<Complex64> = factorized_by(&complexify(&small(), |x| x));
+        assert!(cgs.orthogonality_error() < 1e-12);
+        // A phase keeps the columns orthonormal with respect to the conjugate transpose.
+        cgs.q_mut().column_mut(0).mapv_inplace(|q_i| Complex64::i() * q_i);
+        assert!(cgs.orthogonality_error() < 1e-12);
+        cgs.q_mut().column_mut(0).mapv_inplace(|q_i| 2.0 * q_i);
+        assert!((cgs.orthogonality_error() - 3.0).abs() < 1e-12);
+
+        let mgs: Modified<Complex32> = factorized_by(&complexify(&small(), |x| x as f32));
+        assert!(mgs.orthogonality_error() < 1e-5);
+    }
+
+    #[test]
+    fn reconstruction_error() {
+        let cgs = factorized(&small());
+        assert!(cgs.reconstruction_error(&small()).unwrap() < 1e-12);
+
+        match cgs.reconstruction_error(&matrix(8, 6)) {
+            Err(Error::ShapeMismatch { expected: (4, 4), found: (8, 6) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+
+        let mut r = cgs.r().clone();
+        r[(0, 1)] += 0.5;
+        let corrupted = Classical::from_parts(cgs.q().clone(), r).unwrap();
+        let error = corrupted.reconstruction_error(&small()).unwrap();
+        // Only the column 1 of QR changes, by 0.5·q_0 with ‖q_0‖ = 1.
+        assert!((error - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reconstruct_returns_original() {
+        for a in &with_f_order(small()) {
+            let reconstructed = factorized(a).reconstruct();
+            assert!(a.all_close(&reconstructed, 1e-12));
+            assert_eq!(reconstructed.is_standard_layout(), a.is_standard_layout());
+        }
+    }
+
+    #[test]
+    fn matches_only_original() {
+        let a = matrix(8, 6);
+        let cgs = factorized(&a);
+        assert!(cgs.matches(&a, 1e-10));
+
+        let mut modified = a.clone();
+        modified[(2, 3)] += 1e-3;
+        assert!(!cgs.matches(&modified, 1e-10));
+        assert!(!cgs.matches(&small(), 1e-10));
+    }
+
+    #[test]
+    fn q_rinv_transpose() {
+        for a in &with_f_order(matrix(8, 6)) {
+            for &transpose_r in &[false, true] {
+                let mut cgs = Classical::from_shape_with_r_transposed(a.dim().set_f(!a.is_standard_layout()), transpose_r).unwrap();
+                cgs.compute(a).unwrap();
+                let x = cgs.q_rinv_transpose().unwrap();
+                assert!(cgs.q().all_close(&x.dot(&cgs.r().t()), 1e-12));
+            }
+        }
+
+        match Classical::from_matrix(&matrix(8, 6)).unwrap().q_rinv_transpose() {
+            Err(Error::Singular) => {},
+            other => panic!("expected a singular R, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn matrix_market_round_trip() {
+        let cgs = factorized(&matrix(8, 6));
+        let mut buffer = Vec::new();
+        cgs.write_matrix_market(&mut buffer).unwrap();
+        let restored = Classical::read_matrix_market(&buffer[..]).unwrap();
+        assert_eq!(cgs.q(), restored.q());
+        assert_eq!(cgs.r(), restored.r());
+    }
+
+    #[test]
+    fn batch_matches_individual_factorizations() {
+        let mut stack = Array3::zeros((4, 4, 4));
+        let mut f_stack = Array3::zeros((4, 4, 4).f());
+        for (k, mut a) in stack.axis_iter_mut(Axis(0)).enumerate() {
+            a.assign(&(small() * (k + 1) as f64));
+            a[(3, 0)] = k as f64;
+        }
+        f_stack.assign(&stack);
+
+        for s in [&stack, &f_stack].iter() {
+            let (qs, rs) = Classical::compute_batch(*s).unwrap();
+            assert_eq!(qs.dim(), (4, 4, 4));
+            for (k, a) in s.axis_iter(Axis(0)).enumerate() {
+                let (q, r) = Classical::compute_once(&a.to_owned()).unwrap();
+                assert_eq!(qs.index_axis(Axis(0), k), q);
+                assert_eq!(rs.index_axis(Axis(0), k), r);
+            }
+        }
+    }
+
+    #[test]
+    fn compute_qn_returns_diagonal_of_r() {
+        for a in &with_f_order(small()) {
+            let (q, norms) = Classical::compute_qn(a).unwrap();
+            let (q_once, r_once) = Classical::compute_once(a).unwrap();
+            assert_eq!(q, q_once);
+            assert_eq!(norms, r_once.diag());
+        }
+    }
+
+    #[test]
+    fn packed_r_unpacks_to_dense_r() {
+        for a in &with_f_order(small()) {
+            let cgs = factorized(a);
+            let packed = cgs.r_packed();
+            assert_eq!(packed.len(), 10);
+
+            let mut unpacked = Array2::zeros((4, 4));
+            for j in 0..4 {
+                for i in 0..=j {
+                    unpacked[(i, j)] = packed[i + j * (j + 1) / 2];
+                }
+            }
+            assert_eq!(&unpacked, cgs.r());
+        }
+    }
+
+    #[test]
+    fn basis_vectors_are_columns_of_q() {
+        for a in &with_f_order(matrix(8, 6)) {
+            let cgs = factorized(a);
+            assert_eq!(cgs.basis_vectors().count(), cgs.q().cols());
+            for (j, q_j) in cgs.basis_vectors().enumerate() {
+                assert_eq!(q_j, cgs.q().column(j));
+            }
+        }
+    }
+
+    #[test]
+    fn projections_and_norms_assemble_r() {
+        for a in &with_f_order(matrix(8, 6)) {
+            let cgs = factorized(a);
+            let projections = cgs.projections();
+            let norms = cgs.norms();
+            assert!(norms.iter().all(|&norm| norm > 0.0));
+            assert!(projections.diag().iter().all(|&x| x == 0.0));
+            let mut assembled = projections;
+            assembled.diag_mut().assign(&norms);
+            assert_eq!(&assembled, cgs.r());
+
+            // The projections are the inner products of the basis with the columns of A.
+            let q = cgs.q();
+            for j in 0..a.cols() {
+                for i in 0..j {
+                    assert!((cgs.projections()[(i, j)] - q.column(i).dot(&a.column(j))).abs() < 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn max_offdiagonal_ratio() {
+        let cgs = factorized(&matrix(8, 6));
+        let r = cgs.r();
+        let mut expected: f64 = 0.0;
+        for j in 0..r.cols() {
+            for i in 0..j {
+                expected = expected.max(r[(i, j)].abs() / r[(j, j)].abs());
+            }
+        }
+        assert!(expected > 0.0);
+        assert_eq!(expected, cgs.max_offdiagonal_ratio());
+
+        assert_eq!(factorized(&Array2::eye(4)).max_offdiagonal_ratio(), 0.0);
+    }
+
+    #[test]
+    fn estimated_rank_by_gap_finds_gap() {
+        let diagonal = arr1(&[5.0, 4.0, 1e-9, 1e-10]);
+        let diagonal = Array2::from_shape_fn((4, 4), |(i, j)| if i == j { diagonal[i] } else { 0.0 });
+        for a in &with_f_order(diagonal) {
+            assert_eq!(factorized(a).estimated_rank_by_gap(), 2);
+        }
+        assert_eq!(factorized(&Array2::eye(4)).estimated_rank_by_gap(), 4);
+    }
+
+    #[test]
+    fn normal_equations_inverse_inverts_gram_matrix() {
+        for a in &with_f_order(matrix(8, 6)) {
+            for &transpose_r in &[false, true] {
+                let mut cgs = Classical::from_shape_with_r_transposed(a.dim().set_f(!a.is_standard_layout()), transpose_r).unwrap();
+                cgs.compute(a).unwrap();
+                let inverse = cgs.normal_equations_inverse().unwrap();
+                let gram = a.t().dot(a);
+                assert!(inverse.dot(&gram).all_close(&Array2::eye(a.cols()), 1e-9));
+                assert!(inverse.all_close(&inverse.t(), 1e-9));
+            }
+        }
+
+        match Classical::from_matrix(&small()).unwrap().normal_equations_inverse() {
+            Err(Error::Singular) => {},
+            other => panic!("expected a singular R, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn project_basis_vectors() {
+        for a in &with_f_order(small()) {
+            let cgs = factorized(a);
+            for q_j in cgs.basis_vectors() {
+                assert!(cgs.project(&q_j).unwrap().all_close(&q_j, 1e-14));
+                assert!(cgs.project_orthogonal(&q_j).unwrap().iter().all(|x| x.abs() < 1e-14));
+            }
+            match cgs.project(&arr1(&[1.0, 2.0])) {
+                Err(Error::LengthMismatch { expected: 4, found: 2 }) => {},
+                other => panic!("expected a length mismatch, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn angle_to_span_of_columns_and_complement() {
+        use std::f64::consts::FRAC_PI_2;
+
+        // The first two columns of the identity span a plane.
+        let cgs = factorized(&Array2::eye(4).slice(s![.., ..2]).to_owned());
+
+        let inside = arr1(&[1.0, -2.0, 0.0, 0.0]);
+        assert!(cgs.angle_to_span(&inside).unwrap().abs() < 1e-15);
+        assert!(cgs.project_orthogonal(&inside).unwrap().iter().all(|x| x.abs() < 1e-15));
+
+        let orthogonal = arr1(&[0.0, 0.0, 3.0, 4.0]);
+        assert!((cgs.angle_to_span(&orthogonal).unwrap() - FRAC_PI_2).abs() < 1e-15);
+        assert_eq!(cgs.project_orthogonal(&orthogonal).unwrap(), orthogonal);
+
+        // sin θ = 1e-10 is resolved accurately.
+        let almost_inside = arr1(&[1.0, 0.0, 1e-10, 0.0]);
+        let angle = cgs.angle_to_span(&almost_inside).unwrap();
+        assert!((angle - 1e-10).abs() < 1e-20);
+
+        match cgs.angle_to_span(&arr1(&[1.0, 2.0])) {
+            Err(Error::LengthMismatch { expected: 4, found: 2 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn det_of_small() {
+        // The matrix is triangular up to the entry (2, 1), which does not enter the determinant.
+        let det = 2.0 * 0.3 * 0.7 * 3.0;
+        for a in &with_f_order(small()) {
+            let cgs = factorized(a);
+            assert!((cgs.det().unwrap() - det).abs() < 1e-14);
+            assert!((cgs.ln_abs_det() - f64::ln(det)).abs() < 1e-14);
+        }
+
+        // Swapping two columns flips the sign.
+        let swapped = small().select(Axis(1), &[1, 0, 2, 3]);
+        assert!((factorized(&swapped).det().unwrap() + det).abs() < 1e-14);
+
+        // A tall matrix has no determinant, but the volume spanned by its columns.
+        let cgs = factorized(&matrix(8, 6));
+        match cgs.det() {
+            Err(Error::NotSquare) => {},
+            other => panic!("expected a non-square matrix, got {:?}", other),
+        }
+        assert!(cgs.ln_abs_det().is_finite());
+    }
+
+    #[test]
+    fn solve_least_squares() {
+        // The normal equations are [[2, 1], [1, 2]]·x = [1, 1], solved by x = [1/3, 1/3].
+        let b = arr1(&[1.0, 1.0, 0.0]);
+        let expected = arr1(&[1.0 / 3.0, 1.0 / 3.0]);
+
+        for a in &with_f_order(arr2(&[[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]])) {
+            let cgs = factorized(a);
+            assert!(cgs.solve(&b).unwrap().all_close(&expected, 1e-14));
+            match cgs.solve(&arr1(&[1.0, 2.0])) {
+                Err(Error::LengthMismatch { expected: 3, found: 2 }) => {},
+                other => panic!("expected a length mismatch, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn solve_many_minimizes_residual() {
+        let b = Array2::from_shape_fn((8, 3), |(i, j)| ((i * 3 + j) as f64).sin());
+        for a in &with_f_order(matrix(8, 6)) {
+            let cgs = factorized(a);
+            let x = cgs.solve_many(&b).unwrap();
+            assert_eq!(x.is_standard_layout(), a.is_standard_layout());
+            // The residual of the least squares solution is orthogonal to the columns of A.
+            let residual = a.dot(&x) - &b;
+            assert!(a.t().dot(&residual).all_close(&Array2::zeros((6, 3)), 1e-10));
+            for (j, x_column) in x.gencolumns().into_iter().enumerate() {
+                assert!(x_column.all_close(&cgs.solve(&b.column(j)).unwrap(), 1e-12));
+            }
+
+            match cgs.solve_many(&Array2::zeros((4, 3))) {
+                Err(Error::ShapeMismatch { expected: (8, 3), found: (4, 3) }) => {},
+                other => panic!("expected a shape mismatch, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn non_contiguous_views_are_rejected() {
+        let a = matrix(8, 6);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        let views = [a.slice(s![.., 0..4]), f_a.slice(s![0..6, ..]), a.slice(s![.., ..;2])];
+        for (name, function) in convenience_functions() {
+            for view in views.iter() {
+                match function(view) {
+                    Err(Error::NonContiguous) => {},
+                    other => panic!("{}: expected a non-contiguous input error, got {:?}", name, other),
+                }
+            }
+        }
+    }
+
+    /// A dense square matrix with entries spanning several orders of magnitude.
+    fn large() -> Array2<f64> {
+        arr2(
+            &[[-4.079764601288893, 4.831491499921403, -2.9560001027996132, -0.02239325297550033, -0.2672544204261703, -0.07718850306444144],
+              [1.2917480323712418, 0.030479388871438983, 0.604549448561548, 0.013409783846041783, 0.037439247530467186, 0.03153579130305008],
+              [-47.584641085515464, 5.501371846864031, 41.39822251681311, -33.69079455346558, 43.13388644338738, 68.7695035292409],
+              [2.5268795799504997, 25.418530275775225, 33.473125141381374, 77.3391516894698, -44.091836957161426, 45.10932299622911],
+              [-20.383209804181938, -19.163209972229616, 0.09795435026201423, -53.296988576627484, -88.482334971421, 16.757575995918756],
+              [62.270964677492124, -75.82678462673792, -0.6889077708993588, 2.2569901796884064, 9.21906803233946, 44.891962279862234]]
+        )
+    }
+
+    /// Returns the procedure `G` after factorizing `a`.
+    fn factorized_by<G, S>(a: &ArrayBase<S, Ix2>) -> G
+        where G: GramSchmidt,
+              S: Data<Elem = G::Scalar>,
+    {
+        let mut method = G::from_matrix(a).unwrap();
+        method.compute(a).unwrap();
+        method
+    }
+
+    /// Checks that `QᴴQ = I` and `A = QR` hold for the complex matrix `a`, and that the
+    /// diagonal of R is real and positive.
+    fn check_complex<G>(a: &Array2<G::Scalar>, tol: <G::Scalar as GramSchmidtScalar>::Real)
+        where G: GramSchmidt,
+    {
+        let method: G = factorized_by(a);
+
+        let q = method.q();
+        let qh_q = q.mapv(|z| z.conj()).t().dot(q);
+        for ((i, j), &z) in qh_q.indexed_iter() {
+            let expected = if i == j { G::Scalar::one() } else { G::Scalar::zero() };
+            assert!((z - expected).modulus() < tol);
+        }
+
+        let reconstructed = q.dot(method.r());
+        assert!(a.iter().zip(&reconstructed).all(|(&x, &y)| (x - y).modulus() < tol));
+        assert!(method.r().diag().iter().all(|&r_ii| r_ii == G::Scalar::from_real(r_ii.modulus())));
+        assert!(method.r().diag().iter().all(|&r_ii| !r_ii.is_zero()));
+    }
+
+    fn complexify<T>(a: &Array2<f64>, cast: fn(f64) -> T) -> Array2<num_complex::Complex<T>>
+        where T: Copy
+    {
+        let mut z = a.mapv(|x| num_complex::Complex::new(cast(x), cast(x)));
+        for ((i, j), z_ij) in z.indexed_iter_mut() {
+            z_ij.im = cast(a[(j, i)]);
+        }
+        z
+    }
+
+    #[test]
+    fn complex_orthonormal() {
+        use num_complex::{Complex32, Complex64};
+
+        for a in with_f_order(small()).iter().chain(&with_f_order(large())) {
+            let z = complexify(a, |x| x);
+            check_complex::<Classical<Complex64>>(&z, 1e-11);
+            check_complex::<Modified<Complex64>>(&z, 1e-12);
+            check_complex::<Reorthogonalized<Complex64>>(&z, 1e-12);
+            check_complex::<BlockClassical<Complex64>>(&z, 1e-11);
+            check_complex::<Givens<Complex64>>(&z, 1e-12);
+        }
+        let z = complexify(&small(), |x| x as f32);
+        check_complex::<Classical<Complex32>>(&z, 1e-5);
+        check_complex::<Modified<Complex32>>(&z, 1e-5);
+        check_complex::<Reorthogonalized<Complex32>>(&z, 1e-5);
+        check_complex::<BlockClassical<Complex32>>(&z, 1e-5);
+        check_complex::<Givens<Complex32>>(&z, 1e-5);
+    }
+
+    #[test]
+    fn update_column_matches_recompute() {
+        let mut a = large().slice(s![.., ..5]).to_owned();
+        let mut cgs = factorized(&a);
+
+        let new_column = arr1(&[1.0, -2.0, 0.5, 3.0, 0.0, 1.5]);
+        cgs.update_column(2, &new_column).unwrap();
+        a.column_mut(2).assign(&new_column);
+        let recomputed = factorized(&a);
+        assert!(cgs.q().all_close(recomputed.q(), 1e-12));
+        assert!(cgs.r().all_close(recomputed.r(), 1e-12));
+
+        // Replacing a pinned column copies the pinned columns again.
+        a.column_mut(0).assign(&arr1(&[0.0, 0.0, 0.0, 0.0, 0.0, 1.0]));
+        cgs.set_pinned(1).unwrap();
+        cgs.compute(&a).unwrap();
+        let unit = arr1(&[0.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+        cgs.update_column(0, &unit).unwrap();
+        assert_eq!(cgs.q().column(0), unit);
+        a.column_mut(0).assign(&unit);
+        assert!(a.all_close(&cgs.q().dot(cgs.r()), 1e-12));
+
+        match cgs.update_column(1, &arr1(&[1.0, 2.0])) {
+            Err(Error::LengthMismatch { expected: 6, found: 2 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+        match cgs.update_column(5, &unit) {
+            Err(Error::ColumnOutOfBounds { index: 5, cols: 5 }) => {},
+            other => panic!("expected a column out of bounds, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn compute_into_matches_compute() {
+        for a in &with_f_order(large()) {
+            let mut cgs = Classical::from_matrix(a).unwrap();
+            let mut q = cgs.q().clone();
+            let mut r = cgs.r().clone();
+            cgs.compute_into(a, &mut q, &mut r).unwrap();
+            cgs.compute(a).unwrap();
+            assert_eq!(&q, cgs.q());
+            assert_eq!(&r, cgs.r());
+
+            let mut wrong_shape = Array2::zeros((6, 5));
+            match cgs.compute_into(a, &mut wrong_shape, &mut r) {
+                Err(Error::ShapeMismatch { expected: (6, 6), found: (6, 5) }) => {},
+                other => panic!("expected a shape mismatch, got {:?}", other),
+            }
+            let mut wrong_layout = Array2::zeros(q.dim().set_f(q.is_standard_layout()));
+            match cgs.compute_into(a, &mut wrong_layout, &mut r) {
+                Err(Error::IncompatibleLayouts) => {},
+                other => panic!("expected incompatible layouts, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn f_order_budgeted_compute_with_generous_deadline_is_done() {
+        let a = &with_f_order(large())[1];
+        let deadline = Instant::now() + std::time::Duration::from_secs(3600);
+        let mut cgs = Classical::from_matrix(a).unwrap();
+        let status = cgs.compute_budgeted(a, deadline).unwrap();
+        assert_eq!(status, ComputeStatus::Done);
+        assert!(a.all_close(&cgs.q().dot(cgs.r()), 1e-12));
+    }
+
+    #[test]
+    fn column_callback_sees_diagonal_of_r() {
+        for a in &with_f_order(small()) {
+            let mut cgs = Classical::from_matrix(a).unwrap();
+            let mut norms = Vec::new();
+            cgs.compute_with_callback(a, |j, norm| norms.push((j, norm))).unwrap();
+
+            let reference = factorized(a);
+            let expected: Vec<_> = reference.r().diag().iter().cloned().enumerate().collect();
+            assert_eq!(norms, expected);
+            assert_eq!(cgs.q(), reference.q());
+        }
+    }
+
+    #[test]
+    fn truncated_compute_stops_at_rank() {
+        // The last three columns are combinations of the first three.
+        let combinations = arr2(
+            &[[1.0, 0.0, 0.0, 1.0, 2.0, 0.0],
+              [0.0, 1.0, 0.0, 1.0, -1.0, 3.0],
+              [0.0, 0.0, 1.0, 0.0, 1.0, -2.0]]
+        );
+        let c = large().slice(s![.., ..3]).dot(&combinations);
+
+        for a in &with_f_order(c) {
+            let mut cgs = Classical::from_matrix(a).unwrap();
+            assert_eq!(cgs.compute_truncated(a, 6, 1e-10).unwrap(), 3);
+            assert!(cgs.q().slice(s![.., 3..]).iter().all(|&q_ij| q_ij == 0.0));
+            assert!(cgs.r().slice(s![.., 3..]).iter().all(|&r_ij| r_ij == 0.0));
+            let leading = cgs.q().slice(s![.., ..3]).dot(&cgs.r().slice(s![..3, ..3]));
+            assert!(leading.all_close(&a.slice(s![.., ..3]), 1e-12));
+
+            assert_eq!(cgs.compute_truncated(a, 2, 1e-10).unwrap(), 2);
+            assert!(cgs.q().slice(s![.., 2..]).iter().all(|&q_ij| q_ij == 0.0));
+        }
+    }
+
+    #[test]
+    fn prepared_non_contiguous_input() {
+        let large = large();
+        let mut reversed = large.view();
+        reversed.invert_axis(Axis(1));
+        assert!(reversed.as_slice_memory_order().is_none());
+        let (mut cgs, input) = Classical::prepared(&reversed).unwrap();
+        assert_eq!(&reversed, input.as_array());
+        cgs.compute_prepared(&input).unwrap();
+        assert!(reversed.all_close(&cgs.q().dot(cgs.r()), 1e-12));
+    }
+
+    #[test]
+    fn prepared_rank_deficient_input_is_an_error() {
+        let mut a = large();
+        let first = a.column(0).to_owned();
+        a.column_mut(2).assign(&first);
+        let (mut cgs, input) = Classical::prepared(&a.view()).unwrap();
+        cgs.set_tolerance(1e-10);
+        match cgs.compute_prepared(&input) {
+            Err(Error::RankDeficient { column: 2 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn f_order_prepared_input_keeps_layout() {
+        let a = &with_f_order(large())[1];
+        let (mut cgs, input) = Classical::prepared(a).unwrap();
+        assert!(!input.as_array().is_standard_layout());
+        cgs.compute_prepared(&input).unwrap();
+        assert!(a.all_close(&cgs.q().dot(cgs.r()), 1e-12));
+    }
+
+    #[test]
+    fn r_lapack_args_follow_r_layout() {
+        use crate::Layout::*;
+
+        let cgs = Classical::<f64>::from_shape_with_r_transposed((6, 6), false).unwrap();
+        assert_eq!(cgs.r_lapack_args(), (RowMajor, b'U', 6));
+        let cgs = Classical::<f64>::from_shape_with_r_transposed((6, 6), true).unwrap();
+        assert_eq!(cgs.r_lapack_args(), (ColumnMajor, b'U', 6));
+        let cgs = Classical::<f64>::from_shape_with_r_transposed((6, 6).f(), false).unwrap();
+        assert_eq!(cgs.r_lapack_args(), (ColumnMajor, b'U', 6));
+        let cgs = Classical::<f64>::from_shape_with_r_transposed((6, 6).f(), true).unwrap();
+        assert_eq!(cgs.r_lapack_args(), (RowMajor, b'U', 6));
+    }
 }
@@ -0,0 +1,207 @@
+//! Reading and writing of dense matrices in the [Matrix Market] exchange format.
+//!
+//! Matrices are written in the `array` format, which lists all entries in column major order.
+//! When reading, both the `array` and the `coordinate` format are understood, but only for `real`
+//! and `general` matrices. Several matrices can be written to and read from the same stream one
+//! after another, each starting with its own header.
+//!
+//! [Matrix Market]: https://math.nist.gov/MatrixMarket/formats.html
+
+use ndarray::{
+    Data,
+    ShapeBuilder,
+};
+use ndarray::prelude::*;
+use std::io::{
+    self,
+    BufRead,
+    Write,
+};
+
+const HEADER_PREFIX: &str = "%%MatrixMarket";
+
+/// Writes the matrix `a` to `writer` in the Matrix Market `array` format.
+///
+/// The values are written in scientific notation with the shortest representation that reads
+/// back to the exact same `f64`.
+///
+/// # Example
+///
+/// ```
+/// use gramschmidt::matrix_market;
+/// use ndarray::arr2;
+///
+/// let a = arr2(&[[1.0, 2.0], [3.0, 4.0]]);
+/// let mut buffer = Vec::new();
+/// matrix_market::write_matrix(&mut buffer, &a).unwrap();
+///
+/// let b = matrix_market::read_matrix(&buffer[..]).unwrap();
+/// assert_eq!(a, b);
+/// ```
+pub fn write_matrix<W, S>(mut writer: W, a: &ArrayBase<S, Ix2>) -> io::Result<()>
+    where W: Write,
+          S: Data<Elem = f64>,
+{
+    let (n_rows, n_cols) = a.dim();
+    writeln!(writer, "{} matrix array real general", HEADER_PREFIX)?;
+    writeln!(writer, "{} {}", n_rows, n_cols)?;
+    for column in a.gencolumns() {
+        for x in column {
+            writeln!(writer, "{:e}", x)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a single matrix in the Matrix Market `array` or `coordinate` format from `reader`.
+///
+/// The returned matrix is stored in column major (Fortran) layout. Reading stops after the
+/// matrix, so that several matrices can be read from the same stream by passing the same
+/// buffered reader repeatedly.
+pub fn read_matrix<R>(mut reader: R) -> io::Result<Array2<f64>>
+    where R: BufRead,
+{
+    let mut lines = Lines { reader: &mut reader, line: String::new() };
+
+    let header = lines.next_line()?
+        .ok_or_else(|| invalid_data("missing Matrix Market header"))?
+        .to_lowercase();
+    let fields: Vec<&str> = header.split_whitespace().collect();
+    let is_array = match fields.as_slice() {
+        [prefix, "matrix", format, "real", "general"] if prefix.eq_ignore_ascii_case(HEADER_PREFIX) => {
+            match *format {
+                "array" => true,
+                "coordinate" => false,
+                _ => Err(invalid_data("unknown Matrix Market format"))?,
+            }
+        },
+        _ => Err(invalid_data("unsupported Matrix Market header, expected a real general matrix"))?,
+    };
+
+    // Skip comments and empty lines until the size line is found.
+    let size = loop {
+        match lines.next_line()? {
+            Some(line) if line.starts_with('%') || line.trim().is_empty() => continue,
+            Some(line) => break parse_numbers::<usize>(&line)?,
+            None => Err(invalid_data("missing Matrix Market size line"))?,
+        }
+    };
+
+    if is_array {
+        let (n_rows, n_cols) = match size.as_slice() {
+            [n_rows, n_cols] => (*n_rows, *n_cols),
+            _ => Err(invalid_data("expected the number of rows and columns"))?,
+        };
+        let mut values = Vec::with_capacity(n_rows * n_cols);
+        while values.len() < n_rows * n_cols {
+            let line = lines.next_line()?
+                .ok_or_else(|| invalid_data("unexpected end of matrix entries"))?;
+            values.extend(parse_numbers::<f64>(&line)?);
+        }
+        Array2::from_shape_vec((n_rows, n_cols).f(), values)
+            .map_err(|_| invalid_data("number of entries does not match the matrix shape"))
+    } else {
+        let (n_rows, n_cols, n_entries) = match size.as_slice() {
+            [n_rows, n_cols, n_entries] => (*n_rows, *n_cols, *n_entries),
+            _ => Err(invalid_data("expected the number of rows, columns, and entries"))?,
+        };
+        let mut a = Array2::zeros((n_rows, n_cols).f());
+        for _ in 0..n_entries {
+            let line = lines.next_line()?
+                .ok_or_else(|| invalid_data("unexpected end of matrix entries"))?;
+            let mut fields = line.split_whitespace();
+            let i = parse_index(fields.next(), n_rows)?;
+            let j = parse_index(fields.next(), n_cols)?;
+            let x = fields.next()
+                .ok_or_else(|| invalid_data("missing matrix entry"))?
+                .parse::<f64>()
+                .map_err(|_| invalid_data("malformed matrix entry"))?;
+            a[(i, j)] = x;
+        }
+        Ok(a)
+    }
+}
+
+/// Reads lines one by one from a buffered reader, reusing the allocated line.
+struct Lines<'a, R> {
+    reader: &'a mut R,
+    line: String,
+}
+
+impl<'a, R: BufRead> Lines<'a, R> {
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(self.line.trim_end().to_owned()))
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_numbers<T: std::str::FromStr>(line: &str) -> io::Result<Vec<T>> {
+    line.split_whitespace()
+        .map(|field| field.parse::<T>().map_err(|_| invalid_data("malformed number")))
+        .collect()
+}
+
+/// Parses a 1-based Matrix Market index into a 0-based one, checking it against `bound`.
+fn parse_index(field: Option<&str>, bound: usize) -> io::Result<usize> {
+    let index = field
+        .ok_or_else(|| invalid_data("missing matrix index"))?
+        .parse::<usize>()
+        .map_err(|_| invalid_data("malformed matrix index"))?;
+    if index == 0 || index > bound {
+        Err(invalid_data("matrix index out of bounds"))?;
+    }
+    Ok(index - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::prelude::*;
+    use super::*;
+
+    #[test]
+    fn array_round_trip_preserves_values() {
+        let a = arr2(&[[1.0, -2.5e-300, 3.0],
+                       [std::f64::consts::PI, 0.0, 1e300]]);
+        let mut buffer = Vec::new();
+        write_matrix(&mut buffer, &a).unwrap();
+        write_matrix(&mut buffer, &a.t()).unwrap();
+
+        let mut reader = &buffer[..];
+        assert_eq!(a, read_matrix(&mut reader).unwrap());
+        assert_eq!(a.t(), read_matrix(&mut reader).unwrap());
+    }
+
+    #[test]
+    fn reads_coordinate_format() {
+        let input = "%%MatrixMarket matrix coordinate real general\n\
+                     % a comment\n\
+                     3 2 3\n\
+                     1 1 1.5\n\
+                     3 2 -2.0\n\
+                     2 1 4e1\n";
+        let a = read_matrix(input.as_bytes()).unwrap();
+        assert_eq!(a, arr2(&[[1.5, 0.0], [40.0, 0.0], [0.0, -2.0]]));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let inputs = [
+            "",
+            "%%MatrixMarket matrix array complex general\n1 1\n1.0 0.0\n",
+            "%%MatrixMarket matrix array real general\n2 2\n1.0\n2.0\n3.0\n",
+            "%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1.0\n",
+        ];
+        for input in inputs.iter() {
+            let err = read_matrix(input.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
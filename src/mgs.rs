@@ -1,4 +1,3 @@
-use cblas;
 use ndarray::{
     Data,
     Dim,
@@ -6,13 +5,43 @@ use ndarray::{
     ShapeBuilder,
 };
 use ndarray::prelude::*;
-use std::slice;
+use std::fmt;
+use std::time::Instant;
 
 use crate::{
+    ColumnProvenance,
+    ComputeStatus,
     Error,
     GramSchmidt,
+    GramSchmidtScalar,
     Result,
-    utils::get_layout,
+    StabilityClass,
+    blas,
+    givens,
+    utils::{
+        check_finite,
+        check_pinned,
+        check_rank,
+        check_shape,
+        clear_columns_from,
+        clear_sign_flips,
+        deadline_passed,
+        default_tolerance,
+        display_factorization,
+        force_positive_diagonal,
+        get_layout,
+        is_broadcast,
+        last_flops,
+        normalization,
+        observe_columns,
+        orthonormal_shortcut,
+        parts_layouts,
+        pin_columns,
+        provenance,
+        rescale_columns,
+        reset_factors,
+        transpose_layout,
+    },
 };
 
 /// A modified Gram Schmidt factorization, which has a better numerical stability compared to
@@ -23,14 +52,33 @@ use crate::{
 /// [Wikipedia entry]: https://en.wikipedia.org/wiki/Gram-Schmidt_process#Numerical_stabilty
 /// [`GramSchmidt` trait]: GramSchmidt
 #[derive(Clone, Debug)]
-pub struct Modified {
-    q: Array2<f64>,
-    r: Array2<f64>,
-    memory_layout: cblas::Layout,
+#[cfg_attr(feature = "serde", derive(serde_crate::Serialize, serde_crate::Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    crate = "serde_crate",
+    try_from = "crate::serialization::ModifiedState<A>",
+    bound(serialize = "A: serde_crate::Serialize", deserialize = "A: GramSchmidtScalar + serde_crate::Deserialize<'de>"),
+))]
+pub struct Modified<A = f64> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::CblasLayout"))]
+    memory_layout: blas::Layout,
+    pinned: usize,
+    sign_flipped: Vec<bool>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    computed: bool,
+    tolerance: Option<f64>,
+    orthonormal_threshold: f64,
+    skip_finite_check: bool,
 }
 
-impl GramSchmidt for Modified {
-    fn from_shape<T>(shape: T) -> Result<Self>
+impl<A: GramSchmidtScalar> GramSchmidt for Modified<A> {
+    type Scalar = A;
+
+    const STABILITY: StabilityClass = StabilityClass::Medium;
+    const COST_FACTOR: f64 = 1.0;
+
+    fn from_shape_with_r_transposed<T>(shape: T, transpose_r: bool) -> Result<Self>
         where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
     {
         // Unfortunately we cannot check the shape itself to see if it's
@@ -42,80 +90,451 @@ impl GramSchmidt for Modified {
             Some(layout) => layout,
             None => Err(Error::NonContiguous)?,
         };
+        check_shape(q.dim())?;
 
         let (_, n_cols) = q.dim();
 
+        let r_layout = if transpose_r {
+            transpose_layout(memory_layout)
+        } else {
+            memory_layout
+        };
         let r = Array2::zeros(
-            (n_cols, n_cols).set_f(memory_layout == cblas::Layout::ColumnMajor)
+            (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor)
         );
 
         Ok(Self {
             q,
             r,
             memory_layout,
+            pinned: 0,
+            sign_flipped: vec![false; n_cols],
+            computed: false,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
         })
     }
 
+    fn from_parts(q: Array2<A>, r: Array2<A>) -> Result<Self> {
+        let (memory_layout, _) = parts_layouts(&q, &r)?;
+        let sign_flipped = vec![false; q.cols()];
+        Ok(Self {
+            q,
+            r,
+            memory_layout,
+            pinned: 0,
+            sign_flipped,
+            computed: false,
+            tolerance: None,
+            orthonormal_threshold: 0.0,
+            skip_finite_check: false,
+        })
+    }
+
+    fn set_tolerance(&mut self, tol: f64) {
+        self.tolerance = Some(tol);
+    }
+
+    fn tolerance(&self) -> f64 {
+        self.tolerance.unwrap_or_else(|| default_tolerance::<A>(self.q.rows(), self.q.cols()))
+    }
+
     fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
-        where S: Data<Elem = f64>,
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, 0, None, |_, _| true).map(|_| ())
+    }
+
+    fn continue_from<S>(&mut self, a: &ArrayBase<S, Ix2>, next_col: usize, deadline: Instant) -> Result<ComputeStatus>
+        where S: Data<Elem = A>
     {
-        let (n_rows, n_cols) = a.dim();
+        self.compute_from(a, next_col, Some(deadline), |_, _| true)
+    }
+
+    fn compute_from_column<S>(&mut self, a: &ArrayBase<S, Ix2>, start: usize) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        self.compute_from(a, start, None, |_, _| true).map(|_| ())
+    }
+
+    fn compute_observed<S, F>(&mut self, a: &ArrayBase<S, Ix2>, observe: F) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              F: FnMut(usize, A::Real) -> bool
+    {
+        self.compute_from(a, 0, None, observe)
+    }
+
+    fn q(&self) -> &Array2<A> {
+        &self.q
+    }
+
+    fn r(&self) -> &Array2<A> {
+        &self.r
+    }
+
+    fn q_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.q.view_mut()
+    }
+
+    fn r_mut(&mut self) -> ArrayViewMut2<'_, A> {
+        self.r.view_mut()
+    }
+
+    fn provenance(&self) -> Vec<ColumnProvenance> {
+        provenance(self.pinned, &self.sign_flipped, |_| false)
+    }
+
+    fn last_flops(&self) -> u64 {
+        last_flops(self.computed, self.q.rows(), &self.provenance(), Self::COST_FACTOR)
+    }
+
+    fn rescale_columns<S>(&mut self, weights: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        rescale_columns(&mut self.q, &mut self.r, weights, &mut self.sign_flipped)
+    }
+
+    fn force_positive_diagonal(&mut self) {
+        force_positive_diagonal(&mut self.q, &mut self.r, &mut self.sign_flipped);
+    }
+
+    fn low_rank_update<S, T>(&mut self, u: &ArrayBase<S, Ix2>, v: &ArrayBase<T, Ix2>) -> Result<()>
+        where S: Data<Elem = A>,
+              T: Data<Elem = A>
+    {
+        givens::low_rank_update(&mut self.q, &mut self.r, u, v)
+    }
+
+    fn remove_column(&mut self, index: usize) -> Result<()> {
+        givens::remove_column(&mut self.q, &mut self.r, index)?;
+        self.sign_flipped.remove(index);
+        if index < self.pinned {
+            self.pinned -= 1;
+        }
+        Ok(())
+    }
+
+    fn clear_columns_from(&mut self, start: usize) {
+        clear_columns_from(&mut self.q, &mut self.r, start);
+        clear_sign_flips(&mut self.sign_flipped, start);
+    }
+}
+
+impl<A: GramSchmidtScalar> Modified<A> {
+    /// Sets the number of leading columns which are pinned.
+    ///
+    /// The columns `0..pinned` of the matrices passed to `compute` are then taken to be an
+    /// already orthonormal set: they are copied into Q unchanged, and only the remaining
+    /// columns are orthonormalized against them and each other. The corresponding block of R is
+    /// the identity. Computing returns [`Error::NotOrthonormal`] if the pinned columns deviate
+    /// from orthonormality by more than `1e-10`.
+    ///
+    /// Returns [`Error::TooManyPinned`] if `pinned` exceeds the number of columns, leaving the
+    /// number of pinned columns unchanged.
+    ///
+    /// [`Error::NotOrthonormal`]: enum.Error.html#variant.NotOrthonormal
+    /// [`Error::TooManyPinned`]: enum.Error.html#variant.TooManyPinned
+    pub fn set_pinned(&mut self, pinned: usize) -> Result<()> {
+        check_pinned(self.q.cols(), pinned)?;
+        self.pinned = pinned;
+        Ok(())
+    }
+
+    /// Returns the number of leading columns which are pinned. See [`set_pinned`] for details.
+    ///
+    /// [`set_pinned`]: #method.set_pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned
+    }
+
+    /// Sets the threshold below which the input is considered to be orthonormal already, so
+    /// that the projections are skipped.
+    ///
+    /// If the loss of orthogonality `‖I - AᵀA‖_F` of the matrix A passed to [`compute`] is
+    /// below `threshold`, Q is set to the normalized columns of A and R to the diagonal matrix of
+    /// their norms. This is much cheaper than a full factorization, e.g. when restoring the
+    /// orthonormality of a slowly drifting basis, but the resulting Q is only orthonormal up to
+    /// about `threshold`. Otherwise, the factorization proceeds as usual. The shortcut is never
+    /// taken when columns are pinned, or when a computation is resumed with `continue_from`. The
+    /// default, `0.0`, disables it.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    pub fn set_orthonormal_threshold(&mut self, threshold: f64) {
+        self.orthonormal_threshold = threshold;
+    }
+
+    /// Returns the threshold below which the input is considered to be orthonormal already. See
+    /// [`set_orthonormal_threshold`] for details.
+    ///
+    /// [`set_orthonormal_threshold`]: #method.set_orthonormal_threshold
+    pub fn orthonormal_threshold(&self) -> f64 {
+        self.orthonormal_threshold
+    }
+
+    /// Sets whether the check of the input for entries which are not finite is skipped.
+    ///
+    /// By default, [`compute`] scans the input once before factorizing it, and returns
+    /// [`Error::NonFinite`] for the first NaN or infinite entry, which would otherwise silently
+    /// propagate into Q and R. Skipping the scan saves a pass over the input in hot loops whose
+    /// inputs are known to be finite. Resuming a computation with `continue_from` never scans.
+    ///
+    /// [`compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    pub fn set_skip_finite_check(&mut self, skip: bool) {
+        self.skip_finite_check = skip;
+    }
+
+    /// Returns whether the check of the input for entries which are not finite is skipped. See
+    /// [`set_skip_finite_check`] for details.
+    ///
+    /// [`set_skip_finite_check`]: #method.set_skip_finite_check
+    pub fn skips_finite_check(&self) -> bool {
+        self.skip_finite_check
+    }
+
+    /// Restores which columns had their signs flipped, e.g. after deserializing.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_sign_flipped(&mut self, sign_flipped: Vec<bool>) {
+        self.sign_flipped = sign_flipped;
+    }
+
+    /// Resizes the factorization for matrices of a new `shape` like [`GramSchmidt::from_shape`],
+    /// but reuses the memory of Q and R where possible, e.g. to factorize a stream of matrices of
+    /// varying sizes without allocating for every one of them.
+    ///
+    /// The memory layout is derived from `shape` anew, and R keeps being stored in the same or the
+    /// opposite layout. All settings are kept, except that the number of pinned columns is reduced
+    /// to the new number of columns if necessary. Returns [`Error::Underdetermined`] for a wide
+    /// shape and [`Error::InvalidShape`] for one without rows or columns, leaving the
+    /// factorization unchanged.
+    ///
+    /// [`GramSchmidt::from_shape`]: trait.GramSchmidt.html#method.from_shape
+    /// [`Error::Underdetermined`]: enum.Error.html#variant.Underdetermined
+    /// [`Error::InvalidShape`]: enum.Error.html#variant.InvalidShape
+    pub fn reset_shape<T>(&mut self, shape: T) -> Result<()>
+        where T: ShapeBuilder<Dim = Dim<[Ix; 2]>>,
+    {
+        // R is not accessed through blas, so that only its current layout is kept.
+        let transpose_r = get_layout(&self.r) != Some(self.memory_layout);
+        let (memory_layout, _) = reset_factors(&mut self.q, &mut self.r, shape, transpose_r)?;
+        self.memory_layout = memory_layout;
+        self.sign_flipped.clear();
+        self.sign_flipped.resize(self.q.cols(), false);
+        self.computed = false;
+        self.pinned = self.pinned.min(self.q.cols());
+        Ok(())
+    }
+
+    /// Orthonormalizes the columns `start..` of `a`, assuming that the columns `0..start` have
+    /// already been processed. Returns early if the optional `deadline` has passed, after at least
+    /// one column has been processed, or once `observe` returns `false` for a processed column.
+    fn compute_from<S, O>(
+        &mut self,
+        a: &ArrayBase<S, Ix2>,
+        start: usize,
+        deadline: Option<Instant>,
+        mut observe: O,
+    ) -> Result<ComputeStatus>
+        where S: Data<Elem = A>,
+              O: FnMut(usize, A::Real) -> bool,
+    {
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+        if is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+
+        let (_, n_cols) = a.dim();
+
+        if start == 0 && !self.skip_finite_check {
+            check_finite(a)?;
+        }
+
+        // The signs of the columns computed anew are determined by the procedure again.
+        clear_sign_flips(&mut self.sign_flipped, if start < self.pinned { 0 } else { start });
+        self.computed = true;
+
+        if start == 0 && self.pinned == 0
+            && orthonormal_shortcut(&mut self.q, &mut self.r, a, self.orthonormal_threshold)
+        {
+            let status = observe_columns(&self.r, 0..self.q.cols(), &mut observe);
+            return Ok(status.unwrap_or(ComputeStatus::Done));
+        }
+
+        // Pinned columns are not orthonormalized, but only copied.
+        let start = if start < self.pinned {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+            if let Some(status) = observe_columns(&self.r, 0..self.pinned, &mut observe) {
+                return Ok(status);
+            }
+            self.pinned
+        } else {
+            start
+        };
 
         // Orthonormalize the current row with respect to all already orthonormalized rows.
         //
         // Another strategy would have been to normalize the current row, and then remove it
         // from all not-yet-orthonormalized rows. However, benchmarking reveals that the first
         // strategy is about 10% faster.
-        for i in 0..n_cols {
+        for i in start..n_cols {
+            if i > start && deadline_passed(deadline) {
+                return Ok(ComputeStatus::InProgress { next_col: i });
+            }
+
             {
                 let (q_done, mut q_todo) = self.q.view_mut().split_at(Axis(1), i);
                 let mut q_todo_column = q_todo.column_mut(0);
                 q_todo_column.assign(&a.column(i));
 
                 for (j, q_done_column) in q_done.gencolumns().into_iter().enumerate() {
-                    let projection_factor = q_done_column.dot(&q_todo_column);
+                    let projection_factor = A::dotc(q_done_column, q_todo_column.view());
                     self.r[(j, i)] = projection_factor;
                     q_todo_column.scaled_add(-projection_factor, &q_done_column);
                 }
             }
 
-            let norm = {
-                let len = self.q.len();
-                let q_ptr = self.q.as_mut_ptr();
-                unsafe {
-                    let (q_column, q_inc) = match self.memory_layout {
-                        cblas::Layout::RowMajor => {
-                            let offset = i as isize;
-                            let q_column = slice::from_raw_parts_mut(q_ptr.offset(offset), len - i);
-                            (q_column, n_cols as i32)
-                        },
-
-                        cblas::Layout::ColumnMajor => {
-                            let offset = n_rows * i;
-                            let q_column = slice::from_raw_parts_mut(q_ptr.offset(offset as isize), len - offset);
-                            (q_column, 1)
-                        },
-                    };
-                    cblas::dnrm2(n_rows as i32, q_column, q_inc)
-                }
-            };
+            let norm = normalization(&self.q.column(i));
 
-            self.r[(i,i)] = norm;
+            check_rank(i, norm, self.tolerance(), || normalization(&a.column(i)))?;
+            self.r[(i,i)] = A::from_real(norm);
             let mut q_column = self.q.column_mut(i);
-            q_column /= norm;
+            q_column /= A::from_real(norm);
+
+            if let Some(status) = observe_columns(&self.r, i..i + 1, &mut observe) {
+                return Ok(status);
+            }
         }
 
-        Ok(())
+        Ok(ComputeStatus::Done)
     }
+}
 
-    fn q(&self) -> &Array2<f64> {
-        &self.q
+/// Prints the dimensions and the layout of the factorization followed by Q and R. The precision
+/// of the entries can be set with the formatter, e.g. `{:.3}`.
+impl<A: GramSchmidtScalar + fmt::Display> fmt::Display for Modified<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_factorization(f, "Modified", self.memory_layout, &self.q, &self.r)
     }
+}
 
-    fn r(&self) -> &Array2<f64> {
-        &self.r
+#[cfg(feature = "rayon")]
+impl<A: GramSchmidtScalar> crate::ParallelGramSchmidt for Modified<A> {
+    fn par_compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = A>
+    {
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+        if is_broadcast(a) {
+            Err(Error::BroadcastInput)?;
+        }
+        if !self.skip_finite_check {
+            check_finite(a)?;
+        }
+        clear_sign_flips(&mut self.sign_flipped, 0);
+        self.computed = true;
+        if self.pinned > 0 {
+            pin_columns(&mut self.q, &mut self.r, a, self.pinned)?;
+        }
+
+        let tolerance = self.tolerance();
+        crate::parallel::compute_columns_modified(&mut self.q, &mut self.r, a, self.pinned, tolerance)
     }
 }
 
 #[cfg(test)]
-generate_tests!(Modified, 1e-13);
+generate_tests!(
+    Modified,
+    1e-13,
+    precisions: [double: f64 => 1e-13, single: f32 => 1e-4]
+);
+
+#[cfg(test)]
+mod stability_tests {
+    extern crate openblas_src;
+
+    use crate::utils::{
+        check_conditioned_matrices,
+        check_hilbert_orthogonality,
+        check_identity_plus_noise_orthogonality,
+    };
+    use super::*;
+
+    // Modified Gram Schmidt loses orthogonality linearly with the condition number.
+    #[test]
+    fn hilbert_orthogonality() {
+        check_hilbert_orthogonality::<Modified<f64>>(&[(6, 1e-9), (8, 1e-5), (10, 1e-2)]);
+    }
+
+    #[test]
+    fn identity_plus_noise_orthogonality() {
+        check_identity_plus_noise_orthogonality::<Modified<f64>, _>(|kappa| 10.0 * f64::EPSILON * kappa);
+    }
+
+    #[test]
+    fn random_conditioned_matrices() {
+        check_conditioned_matrices::<Modified<f64>, _>(1e-13, |kappa| 10.0 * f64::EPSILON * kappa);
+    }
+}
+
+#[cfg(test)]
+mod zero_column_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn zero_column_is_rank_deficient() {
+        for &column in &[0, 2] {
+            // A Vandermonde matrix, which has full rank apart from the zero column.
+            let mut a = Array2::from_shape_fn((5, 4), |(i, j)| ((i + 1) as f64).powi(j as i32));
+            a.column_mut(column).fill(0.0);
+            let mut f_a = Array2::zeros((5, 4).f());
+            f_a.assign(&a);
+            for a in &[a, f_a] {
+                let mut mgs = Modified::from_matrix(a).unwrap();
+                match mgs.compute(a) {
+                    Err(crate::Error::RankDeficient { column: found }) => assert_eq!(found, column),
+                    other => panic!("expected a rank deficient column {}, got {:?}", column, other),
+                }
+                // The vanishing norm is rejected before normalizing by it.
+                assert!(mgs.q().iter().all(|q_ij| !q_ij.is_nan()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn display_shows_dimensions_layout_and_factors() {
+        let mut a = Array2::zeros((3, 2).f());
+        a.assign(&arr2(&[[3.0, 1.0],
+                         [4.0, 2.0],
+                         [0.0, 5.0]]));
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+        assert!(mgs.to_string().starts_with("Modified factorization of a 3×2 matrix in column major layout"));
+        assert!(mgs.to_string().contains("  5.000000  2.200000\n"));
+    }
+}
+
+#[cfg(test)]
+mod orthonormal_shortcut_tests {
+    extern crate openblas_src;
+
+    use super::*;
+
+    #[test]
+    fn orthonormal_shortcut_is_opt_in() {
+        assert_eq!(Modified::<f64>::from_shape((8, 6)).unwrap().orthonormal_threshold(), 0.0);
+        crate::utils::check_orthonormal_shortcut(Modified::set_orthonormal_threshold);
+    }
+}
@@ -0,0 +1,416 @@
+//! Parallel variants of the blas backed Gram Schmidt procedures, enabled by the `rayon` feature.
+
+use ndarray::{
+    Data,
+    s,
+};
+use ndarray::prelude::*;
+use rayon::prelude::*;
+
+use crate::{
+    GramSchmidt,
+    GramSchmidtScalar,
+    Result,
+    blas,
+    utils::{
+        check_rank,
+        normalization,
+    },
+};
+
+/// The smallest number of rows or columns of Q handed to a single task, below which the overhead
+/// of scheduling outweighs the gain of parallelizing.
+const MIN_BLOCK: usize = 64;
+
+/// The Gram Schmidt procedures whose orthogonalization can be parallelized with [rayon].
+///
+/// For the classical procedures, the projection coefficients `Qᴴ·a` of every column are computed
+/// in blocks of columns of Q, and the update `a - Q·(Qᴴ·a)` in blocks of rows of Q, each block
+/// with its own blas `?gemv` call on the global rayon thread pool. The modified procedure instead
+/// removes the projection onto every newly normalized column from all later columns at once, each
+/// of which is updated independently. The result agrees with the serial procedure up to rounding
+/// errors. Since blas may already be multithreaded itself, it is usually best to
+/// restrict it to a single thread, e.g. by setting `OPENBLAS_NUM_THREADS=1` for OpenBLAS.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{GramSchmidt, ParallelGramSchmidt, Reorthogonalized};
+/// use ndarray::Array2;
+/// use ndarray_rand::RandomExt;
+/// use rand::distributions::Normal;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = Array2::random((300, 200), Normal::new(0.0, 1.0));
+/// let mut cgs2 = Reorthogonalized::from_matrix(&a)?;
+/// cgs2.par_compute(&a)?;
+/// assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-12));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [rayon]: https://docs.rs/rayon
+pub trait ParallelGramSchmidt: GramSchmidt {
+    /// Computes the QR factorization of the matrix `a` like [`GramSchmidt::compute`], but
+    /// parallelizes the orthogonalization of every column.
+    ///
+    /// Unlike the serial procedures, the layout of `a` may differ from the configured one. Like
+    /// them, it returns [`Error::NonFinite`] for the first entry of `a` which is NaN or infinite,
+    /// unless the procedure skips this check.
+    ///
+    /// [`GramSchmidt::compute`]: trait.GramSchmidt.html#tymethod.compute
+    /// [`Error::NonFinite`]: enum.Error.html#variant.NonFinite
+    fn par_compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = Self::Scalar>;
+
+    /// Computes the QR decompositions of a stack of matrices like
+    /// [`GramSchmidt::compute_batch`], but factorizes the matrices in parallel, each one serially
+    /// by a procedure cloned for every rayon task.
+    ///
+    /// [`GramSchmidt::compute_batch`]: trait.GramSchmidt.html#method.compute_batch
+    #[allow(clippy::type_complexity)]
+    fn par_compute_batch<S>(stack: &ArrayBase<S, Ix3>) -> Result<(Array3<Self::Scalar>, Array3<Self::Scalar>)>
+        where S: Data<Elem = Self::Scalar>,
+              Self: Clone + Send + Sync,
+    {
+        let (k, n_rows, n_cols) = stack.dim();
+        let prototype = Self::from_shape((n_rows, n_cols))?;
+        let stack = stack.view();
+        let factors = (0..k).into_par_iter()
+            .map_init(
+                || (prototype.clone(), Array2::zeros((n_rows, n_cols))),
+                |(gram_schmidt, buffer), i| {
+                    buffer.assign(&stack.index_axis(Axis(0), i));
+                    gram_schmidt.compute(&*buffer)?;
+                    Ok((gram_schmidt.q().clone(), gram_schmidt.r().clone()))
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut qs = Array3::zeros((k, n_rows, n_cols));
+        let mut rs = Array3::zeros((k, n_cols, n_cols));
+        for (i, (q, r)) in factors.iter().enumerate() {
+            qs.index_axis_mut(Axis(0), i).assign(q);
+            rs.index_axis_mut(Axis(0), i).assign(r);
+        }
+        Ok((qs, rs))
+    }
+}
+
+/// Returns the number of rows or columns per block when splitting `len` of them among the threads,
+/// so that there is at most one block per thread.
+fn block_size(len: usize) -> usize {
+    (len / rayon::current_num_threads() + 1).max(MIN_BLOCK)
+}
+
+/// Computes `coefficients = Q[:, ..i]ᴴ·x` in parallel blocks of columns of Q, where `q` is the
+/// slice underlying the `n_rows×n_cols` matrix Q in `layout`.
+fn project<A: GramSchmidtScalar>(
+    q: &[A],
+    layout: blas::Layout,
+    (n_rows, n_cols): (usize, usize),
+    x: &[A],
+    coefficients: &mut [A],
+) {
+    use blas::Layout::*;
+
+    let block = block_size(coefficients.len());
+    coefficients.par_chunks_mut(block).enumerate().for_each(|(b, chunk)| {
+        let (offset, leading_dim) = match layout {
+            ColumnMajor => (b * block * n_rows, n_rows),
+            RowMajor => (b * block, n_cols),
+        };
+        unsafe {
+            A::gemv(
+                layout,
+                A::ADJOINT,
+                n_rows as i32,
+                chunk.len() as i32,
+                A::one(),
+                &q[offset..],
+                leading_dim as i32,
+                x,
+                1,
+                A::zero(),
+                chunk,
+                1,
+            );
+        }
+    });
+}
+
+/// Computes `x ← x - Q[:, ..i]·coefficients` in parallel blocks of rows of Q, with `i` the length
+/// of `coefficients`. See [`project`] for the other arguments.
+fn subtract<A: GramSchmidtScalar>(
+    q: &[A],
+    layout: blas::Layout,
+    (n_rows, n_cols): (usize, usize),
+    coefficients: &[A],
+    x: &mut [A],
+) {
+    use blas::Layout::*;
+
+    let block = block_size(x.len());
+    x.par_chunks_mut(block).enumerate().for_each(|(b, chunk)| {
+        let (offset, leading_dim) = match layout {
+            ColumnMajor => (b * block, n_rows),
+            RowMajor => (b * block * n_cols, n_cols),
+        };
+        unsafe {
+            A::gemv(
+                layout,
+                blas::Transpose::None,
+                chunk.len() as i32,
+                coefficients.len() as i32,
+                -A::one(),
+                &q[offset..],
+                leading_dim as i32,
+                coefficients,
+                1,
+                A::one(),
+                chunk,
+                1,
+            );
+        }
+    });
+}
+
+/// Orthonormalizes the columns `start..` of `a` into `q` and `r` by classical Gram Schmidt,
+/// parallelizing every pass with [`project`] and [`subtract`].
+///
+/// After the first pass, `reorthogonalize` is called with the index of the column and its norms
+/// before and after the pass, and decides whether a second pass is performed.
+pub(crate) fn compute_columns<A, S, F>(
+    q: &mut Array2<A>,
+    r: &mut Array2<A>,
+    a: &ArrayBase<S, Ix2>,
+    start: usize,
+    tolerance: f64,
+    mut reorthogonalize: F,
+) -> Result<()>
+    where A: GramSchmidtScalar,
+          S: Data<Elem = A>,
+          F: FnMut(usize, A::Real, A::Real) -> bool,
+{
+    let dim = q.dim();
+    let (n_rows, n_cols) = dim;
+    // NOTE: This unwrap is safe, because Q is always allocated contiguously.
+    let layout = crate::utils::get_layout(q).unwrap();
+
+    let mut x = vec![A::zero(); n_rows];
+    let mut coefficients = vec![A::zero(); n_cols];
+    let mut correction = vec![A::zero(); n_cols];
+
+    for i in start..n_cols {
+        for (x_k, &a_k) in x.iter_mut().zip(a.column(i)) {
+            *x_k = a_k;
+        }
+
+        if i > 0 {
+            // NOTE: This unwrap is safe, because Q is always allocated contiguously.
+            let q_slice = q.as_slice_memory_order().unwrap();
+            let norm_before = unsafe { A::nrm2(n_rows as i32, &x, 1) };
+            project(q_slice, layout, dim, &x, &mut coefficients[..i]);
+            subtract(q_slice, layout, dim, &coefficients[..i], &mut x);
+
+            let norm_after = unsafe { A::nrm2(n_rows as i32, &x, 1) };
+            if reorthogonalize(i, norm_before, norm_after) {
+                project(q_slice, layout, dim, &x, &mut correction[..i]);
+                subtract(q_slice, layout, dim, &correction[..i], &mut x);
+                for (c, &d) in coefficients[..i].iter_mut().zip(&correction[..i]) {
+                    *c += d;
+                }
+            }
+        }
+
+        let norm = unsafe { A::nrm2(n_rows as i32, &x, 1) };
+        check_rank(i, norm, tolerance, || normalization(&a.column(i)))?;
+
+        let norm = A::from_real(norm);
+        for (j, &c) in coefficients[..i].iter().enumerate() {
+            r[(j, i)] = c;
+        }
+        r[(i, i)] = norm;
+        for (q_k, &x_k) in q.column_mut(i).iter_mut().zip(&x) {
+            *q_k = x_k / norm;
+        }
+    }
+
+    Ok(())
+}
+
+/// Orthonormalizes the columns `pinned..` of `a` into `q` and `r` by modified Gram Schmidt,
+/// assuming that the columns `0..pinned` of `q` and `r` are already set.
+///
+/// As soon as a column is normalized, its projection is removed from all later columns in
+/// parallel, which performs the same operations as the serial procedure in a different order.
+pub(crate) fn compute_columns_modified<A, S>(
+    q: &mut Array2<A>,
+    r: &mut Array2<A>,
+    a: &ArrayBase<S, Ix2>,
+    pinned: usize,
+    tolerance: f64,
+) -> Result<()>
+    where A: GramSchmidtScalar,
+          S: Data<Elem = A>,
+{
+    let n_cols = q.cols();
+    q.slice_mut(s![.., pinned..]).assign(&a.slice(s![.., pinned..]));
+
+    for j in 0..n_cols {
+        if j >= pinned {
+            let norm = normalization(&q.column(j));
+            check_rank(j, norm, tolerance, || normalization(&a.column(j)))?;
+            r[(j, j)] = A::from_real(norm);
+            let mut q_column = q.column_mut(j);
+            q_column /= A::from_real(norm);
+        }
+
+        let first = (j + 1).max(pinned);
+        let coefficients: Vec<A> = {
+            let (done, mut todo) = q.view_mut().split_at(Axis(1), first);
+            let q_column = done.column(j);
+            let columns: Vec<_> = todo.gencolumns_mut().into_iter().collect();
+            columns.into_par_iter()
+                .with_min_len(block_size(n_cols - first))
+                .map(|mut column| {
+                    let projection_factor = A::dotc(q_column, column.view());
+                    column.scaled_add(-projection_factor, &q_column);
+                    projection_factor
+                })
+                .collect()
+        };
+        for (i, c) in (first..n_cols).zip(coefficients) {
+            r[(j, i)] = c;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::ShapeBuilder;
+    use crate::{
+        Classical,
+        Modified,
+        Reorthogonalized,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    fn check_matches_serial<G>(a: &Array2<f64>, tolerance: f64)
+        where G: ParallelGramSchmidt<Scalar = f64> + Send
+    {
+        let mut serial = G::from_matrix(a).unwrap();
+        serial.compute(a).unwrap();
+        // Several threads split the 300×200 matrix into blocks, irrespective of the machine.
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let mut parallel = G::from_matrix(a).unwrap();
+        pool.install(|| parallel.par_compute(a)).unwrap();
+
+        assert!(parallel.q().all_close(serial.q(), tolerance));
+        assert!(parallel.r().all_close(serial.r(), tolerance));
+        assert_eq!(parallel.provenance(), serial.provenance());
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let a = matrix(300, 200);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            check_matches_serial::<Classical>(a, 1e-10);
+            check_matches_serial::<Modified>(a, 1e-10);
+            check_matches_serial::<Reorthogonalized>(a, 1e-10);
+        }
+    }
+
+    #[test]
+    fn settings_are_respected() {
+        let mut a = matrix(300, 200);
+        a.column_mut(0).fill(0.0);
+        a[(0, 0)] = 1.0;
+        let dependent = &a.column(1) + &a.column(2);
+        a.column_mut(5).assign(&dependent);
+
+        let mut cgs2 = Reorthogonalized::from_matrix(&a).unwrap();
+        cgs2.set_pinned(1).unwrap();
+        cgs2.set_reorthogonalization_tolerance(0.5);
+        cgs2.set_tolerance(1e-10);
+        match cgs2.par_compute(&a) {
+            Err(crate::Error::RankDeficient { column: 5 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+        assert_eq!(cgs2.q().column(0), a.column(0));
+        assert!(cgs2.provenance()[0].pinned);
+    }
+
+    #[test]
+    fn parallel_batch_matches_serial() {
+        let small = arr2(
+            &[[2.0, 0.5, 0.0, 0.0],
+              [0.0, 0.3, 0.0, 0.0],
+              [0.0, 1.0, 0.7, 0.0],
+              [0.0, 0.0, 0.0, 3.0]]
+        );
+        let mut stack = Array3::zeros((4, 4, 4).f());
+        for (k, mut a) in stack.axis_iter_mut(Axis(0)).enumerate() {
+            a.assign(&(&small * (k + 1) as f64));
+            a[(3, 0)] = k as f64;
+        }
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let (qs, rs) = pool.install(|| Reorthogonalized::par_compute_batch(&stack)).unwrap();
+        let (serial_qs, serial_rs) = Reorthogonalized::compute_batch(&stack).unwrap();
+        assert_eq!(qs, serial_qs);
+        assert_eq!(rs, serial_rs);
+
+        stack.index_axis_mut(Axis(0), 2).column_mut(1).fill(0.0);
+        match Reorthogonalized::par_compute_batch(&stack) {
+            Err(crate::Error::RankDeficient { column: 1 }) => {},
+            other => panic!("expected a rank deficiency, got {:?}", other),
+        }
+    }
+
+    fn check_non_finite_rejected<G>(set_skip_finite_check: fn(&mut G, bool))
+        where G: ParallelGramSchmidt<Scalar = f64>
+    {
+        let mut a = matrix(300, 200);
+        a[(120, 70)] = f64::NAN;
+        a[(5, 150)] = f64::INFINITY;
+        let mut method = G::from_matrix(&a).unwrap();
+        match method.par_compute(&a) {
+            Err(crate::Error::NonFinite { row: 120, col: 70 }) => {},
+            other => panic!("expected a non finite entry, got {:?}", other),
+        }
+
+        set_skip_finite_check(&mut method, true);
+        if let Err(crate::Error::NonFinite { .. }) = method.par_compute(&a) {
+            panic!("the check was not skipped");
+        }
+    }
+
+    #[test]
+    fn non_finite_input_is_rejected() {
+        check_non_finite_rejected(Classical::set_skip_finite_check);
+        check_non_finite_rejected(Modified::set_skip_finite_check);
+        check_non_finite_rejected(Reorthogonalized::set_skip_finite_check);
+    }
+
+    #[test]
+    fn mismatched_shape_is_rejected() {
+        let mut cgs = Classical::from_shape((4, 3)).unwrap();
+        match cgs.par_compute(&matrix(300, 200)) {
+            Err(crate::Error::ShapeMismatch { expected: (4, 3), found: (300, 200) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+    }
+}
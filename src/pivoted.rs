@@ -0,0 +1,404 @@
+use ndarray::{
+    Data,
+    ShapeBuilder,
+    s,
+};
+use ndarray::prelude::*;
+use num_traits::{
+    Float,
+    One,
+    Zero,
+};
+use std::time::Instant;
+
+use crate::{
+    Error,
+    GramSchmidt,
+    GramSchmidtScalar,
+    Result,
+    blas,
+    scalar::private::Sealed,
+    utils::{
+        get_layout,
+        normalization,
+        permutation_sign,
+    },
+};
+
+/// A QR decomposition with column pivoting, `A·P = Q·R`, performed by any of the Gram Schmidt
+/// procedures.
+///
+/// At every step, the remaining column with the largest norm after removing its projections onto
+/// the already orthonormalized columns is chosen as the next pivot. This makes the decomposition
+/// rank revealing: the absolute values of the diagonal of R decrease, and for a (numerically)
+/// rank deficient matrix of rank `k` the trailing entries `r_jj`, `j ≥ k`, are (nearly) zero.
+/// The remaining norms are updated incrementally after every step instead of being recomputed,
+/// following LAPACK's `dgeqp3`.
+///
+/// The permutation P is given by [`permutation`]: the `j`-th column of `A·P` is the column
+/// `permutation()[j]` of A. Pinned columns of the wrapped procedure are not supported.
+///
+/// Past the numerical rank, the wrapped procedure orthonormalizes columns which consist of
+/// rounding errors. The [`Classical`] procedure loses orthogonality doing so, which makes all but
+/// the first of the trailing entries of R unreliable; prefer [`Modified`] or [`Reorthogonalized`]
+/// for rank deficient matrices.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{ColumnPivoted, Reorthogonalized};
+/// use ndarray::{arr2, Axis};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = arr2(&[[1.0, 0.0, 3.0],
+///                [0.0, 2.0, 4.0],
+///                [1.0, 0.0, 0.0]]);
+/// let mut pivoted = ColumnPivoted::<Reorthogonalized>::from_matrix(&a)?;
+/// pivoted.compute(&a)?;
+///
+/// // The column with the largest norm comes first.
+/// assert_eq!(pivoted.permutation()[0], 2);
+/// let permuted = a.select(Axis(1), pivoted.permutation());
+/// assert!(permuted.all_close(&pivoted.q().dot(pivoted.r()), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`permutation`]: #method.permutation
+/// [`Classical`]: struct.Classical.html
+/// [`Modified`]: struct.Modified.html
+/// [`Reorthogonalized`]: struct.Reorthogonalized.html
+#[derive(Clone, Debug)]
+pub struct ColumnPivoted<G: GramSchmidt> {
+    inner: G,
+    permuted: Array2<G::Scalar>,
+    permutation: Vec<usize>,
+}
+
+impl<G: GramSchmidt> ColumnPivoted<G> {
+    /// Wraps the procedure `inner`, which determines the shape and layout of the matrices to be
+    /// factorized.
+    ///
+    /// The [`tolerance`] of `inner` is set to zero, so that the trailing columns of a rank
+    /// deficient matrix are orthonormalized at the level of rounding errors, revealing its rank
+    /// in the diagonal of R instead of failing.
+    ///
+    /// [`tolerance`]: trait.GramSchmidt.html#tymethod.set_tolerance
+    pub fn new(mut inner: G) -> Self {
+        inner.set_tolerance(0.0);
+        let dim = inner.q().dim();
+        let column_major = get_layout(inner.q()) == Some(blas::Layout::ColumnMajor);
+        Self {
+            inner,
+            permuted: Array2::zeros(dim.set_f(column_major)),
+            permutation: (0..dim.1).collect(),
+        }
+    }
+
+    /// Reserves the memory for a pivoted QR decomposition of matrices with the shape and layout
+    /// of `a`. See [`GramSchmidt::from_matrix`].
+    ///
+    /// [`GramSchmidt::from_matrix`]: trait.GramSchmidt.html#method.from_matrix
+    pub fn from_matrix<S>(a: &ArrayBase<S, Ix2>) -> Result<Self>
+        where S: Data<Elem = G::Scalar>
+    {
+        G::from_matrix(a).map(Self::new)
+    }
+
+    /// Computes the pivoted QR decomposition `A·P = Q·R` of the matrix `a`.
+    ///
+    /// Returns an error if the shape of `a` differs from the configured one, or if the wrapped
+    /// procedure fails, e.g. because the remaining norm of a pivot vanishes exactly.
+    pub fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = G::Scalar>
+    {
+        if a.dim() != self.permuted.dim() {
+            Err(Error::ShapeMismatch { expected: self.permuted.dim(), found: a.dim() })?;
+        }
+
+        let (n_rows, n_cols) = a.dim();
+        self.permuted.assign(a);
+        self.permutation = (0..n_cols).collect();
+
+        let mut norms: Vec<_> = self.permuted.gencolumns().into_iter().map(|column| normalization(&column)).collect();
+        // The norms at the last recomputation, which detect cancellation in the updates.
+        let mut reference_norms = norms.clone();
+        let tolerance = Real::<G>::epsilon().sqrt();
+
+        for k in 0..n_cols {
+            let pivot = (k..n_cols)
+                .fold(k, |pivot, j| if norms[j] > norms[pivot] { j } else { pivot });
+            if pivot != k {
+                for i in 0..n_rows {
+                    self.permuted.swap((i, k), (i, pivot));
+                }
+                norms.swap(k, pivot);
+                reference_norms.swap(k, pivot);
+                self.permutation.swap(k, pivot);
+            }
+
+            // A deadline in the past makes the procedure orthonormalize exactly the column k.
+            self.inner.continue_from(&self.permuted, k, Instant::now())?;
+
+            let q = self.inner.q();
+            let q_k = q.column(k);
+            for j in (k + 1)..n_cols {
+                if norms[j].is_zero() {
+                    continue;
+                }
+                let projection = G::Scalar::dotc(q_k, self.permuted.column(j)).modulus();
+                let ratio = projection / norms[j];
+                let remaining = (Real::<G>::one() - ratio * ratio).max(Real::<G>::zero());
+                let relative = norms[j] / reference_norms[j];
+                if remaining * relative * relative <= tolerance {
+                    // The update suffers from cancellation, so the norm is recomputed.
+                    let q_done = q.slice(s![.., ..=k]);
+                    let a_j = self.permuted.column(j);
+                    let residual = &a_j - &q_done.dot(&G::Scalar::adjoint_dot(q_done, a_j));
+                    norms[j] = normalization(&residual);
+                    reference_norms[j] = norms[j];
+                } else {
+                    norms[j] *= remaining.sqrt();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the permutation P, such that the `j`-th column of `A·P` is the column
+    /// `permutation()[j]` of A.
+    pub fn permutation(&self) -> &[usize] {
+        &self.permutation
+    }
+
+    /// Applies the permutation P to the columns of `m`, i.e. returns `M·P`, whose `j`-th column is
+    /// the column `permutation()[j]` of `m`. This reorders data associated with the columns of A
+    /// consistently with Q and R. The result has the memory layout of `m`.
+    ///
+    /// Returns [`Error::LengthMismatch`] if `m` does not have as many columns as A.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate openblas_src;
+    ///
+    /// use gramschmidt::{ColumnPivoted, Modified};
+    /// use ndarray::arr2;
+    ///
+    /// # fn main() -> gramschmidt::Result<()> {
+    ///
+    /// let a = arr2(&[[1.0, 0.0, 3.0],
+    ///                [0.0, 2.0, 4.0]]);
+    /// let mut pivoted = ColumnPivoted::<Modified>::from_matrix(&a.t())?;
+    /// pivoted.compute(&a.t())?;
+    ///
+    /// // One row of labels per column of the factorized matrix.
+    /// let labels = arr2(&[[10, 20]]);
+    /// let permuted = pivoted.permute_columns(&labels)?;
+    /// assert_eq!(permuted, arr2(&[[20, 10]]));
+    /// assert_eq!(pivoted.unpermute_columns(&permuted)?, labels);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Error::LengthMismatch`]: enum.Error.html#variant.LengthMismatch
+    pub fn permute_columns<S, A>(&self, m: &ArrayBase<S, Ix2>) -> Result<Array2<A>>
+        where S: Data<Elem = A>,
+              A: Clone + Zero,
+    {
+        let mut permuted = self.permuted_buffer(m)?;
+        for (j, &column) in self.permutation.iter().enumerate() {
+            permuted.column_mut(j).assign(&m.column(column));
+        }
+        Ok(permuted)
+    }
+
+    /// Applies the inverse permutation Pᵀ to the columns of `m`, i.e. returns `M·Pᵀ`. This undoes
+    /// [`permute_columns`], and has the same requirements.
+    ///
+    /// [`permute_columns`]: #method.permute_columns
+    pub fn unpermute_columns<S, A>(&self, m: &ArrayBase<S, Ix2>) -> Result<Array2<A>>
+        where S: Data<Elem = A>,
+              A: Clone + Zero,
+    {
+        let mut unpermuted = self.permuted_buffer(m)?;
+        for (j, &column) in self.permutation.iter().enumerate() {
+            unpermuted.column_mut(column).assign(&m.column(j));
+        }
+        Ok(unpermuted)
+    }
+
+    /// Allocates a matrix of the shape and layout of `m` after checking that its columns can be
+    /// permuted.
+    fn permuted_buffer<S, A>(&self, m: &ArrayBase<S, Ix2>) -> Result<Array2<A>>
+        where S: Data<Elem = A>,
+              A: Clone + Zero,
+    {
+        if m.cols() != self.permutation.len() {
+            Err(Error::LengthMismatch { expected: self.permutation.len(), found: m.cols() })?;
+        }
+        let column_major = get_layout(m) == Some(blas::Layout::ColumnMajor);
+        Ok(Array2::zeros(m.dim().set_f(column_major)))
+    }
+
+    /// Return a reference to the matrix q.
+    pub fn q(&self) -> &Array2<G::Scalar> {
+        self.inner.q()
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<G::Scalar> {
+        self.inner.r()
+    }
+
+    /// Returns the factorized matrix `A = Q·R·Pᵀ` up to rounding errors, i.e. the product of Q
+    /// and R with the inverse permutation applied to its columns. See
+    /// [`GramSchmidt::reconstruct`].
+    ///
+    /// [`GramSchmidt::reconstruct`]: trait.GramSchmidt.html#method.reconstruct
+    pub fn reconstruct(&self) -> Array2<G::Scalar> {
+        // NOTE: This unwrap is safe, because R has a column for every entry of the permutation.
+        self.unpermute_columns(&self.inner.reconstruct()).unwrap()
+    }
+
+    /// Returns the logarithm of the absolute value of the determinant of the factorized matrix.
+    /// See [`GramSchmidt::ln_abs_det`].
+    ///
+    /// [`GramSchmidt::ln_abs_det`]: trait.GramSchmidt.html#method.ln_abs_det
+    pub fn ln_abs_det(&self) -> f64 {
+        self.inner.ln_abs_det()
+    }
+
+    /// Return a reference to the wrapped procedure.
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+}
+
+impl<G: GramSchmidt<Scalar = f64>> ColumnPivoted<G> {
+    /// Returns the determinant of the factorized square matrix A, whose sign accounts for the
+    /// parity of the permutation, `det(A) = det(P)·det(Q)·det(R)`. Returns
+    /// [`Error::NotSquare`] if A is not square. See [`GramSchmidt::det`].
+    ///
+    /// [`Error::NotSquare`]: enum.Error.html#variant.NotSquare
+    /// [`GramSchmidt::det`]: trait.GramSchmidt.html#method.det
+    pub fn det(&self) -> Result<f64> {
+        Ok(permutation_sign(&self.permutation) * self.inner.det()?)
+    }
+}
+
+/// The real type underlying the scalars of the procedure `G`.
+type Real<G> = <<G as GramSchmidt>::Scalar as GramSchmidtScalar>::Real;
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+    use crate::{
+        Classical,
+        Modified,
+        Reorthogonalized,
+    };
+    use super::*;
+
+    /// A matrix of rank 3, whose columns 3 and 4 are linear combinations of the first ones.
+    fn rank_deficient() -> Array2<f64> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut a = Array2::random_using((8, 5), Normal::new(0.0, 1.0), &mut rng);
+        let column = &a.column(1) * 2.0;
+        a.column_mut(3).assign(&column);
+        let column = &a.column(0) - &a.column(2);
+        a.column_mut(4).assign(&column);
+        a
+    }
+
+    /// Checks that the decomposition of `a` reveals its rank of 3, and that the `negligible`
+    /// entries of the diagonal of R following the rank are at the level of rounding errors.
+    fn check_rank_revealing<G: GramSchmidt<Scalar = f64>>(a: &Array2<f64>, negligible: usize) {
+        let mut pivoted = ColumnPivoted::<G>::from_matrix(a).unwrap();
+        pivoted.compute(a).unwrap();
+
+        let mut permutation = pivoted.permutation().to_vec();
+        let permuted = a.select(Axis(1), &permutation);
+        assert!(permuted.all_close(&pivoted.q().dot(pivoted.r()), 1e-12));
+        assert!(a.all_close(&pivoted.reconstruct(), 1e-12));
+        permutation.sort();
+        assert_eq!(permutation, vec![0, 1, 2, 3, 4]);
+
+        let diagonal = pivoted.r().diag().mapv(f64::abs);
+        // The order of the trailing entries, which are at the level of rounding errors, is arbitrary.
+        assert!(diagonal.slice(s![..4]).windows(2).into_iter().all(|pair| pair[0] >= pair[1]));
+        assert!(diagonal[2] > 1e-2 * diagonal[0]);
+        assert!(diagonal.slice(s![3..3 + negligible]).iter().all(|&r_jj| r_jj < 1e-12 * diagonal[0]));
+    }
+
+    #[test]
+    fn rank_deficiency_is_revealed() {
+        let a = rank_deficient();
+        // Having normalized a column at the level of rounding errors, the classical procedure
+        // loses orthogonality, so that only the first negligible entry is reliable.
+        check_rank_revealing::<Classical>(&a, 1);
+        check_rank_revealing::<Modified>(&a, 2);
+        check_rank_revealing::<Reorthogonalized>(&a, 2);
+
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        check_rank_revealing::<Reorthogonalized>(&f_a, 2);
+    }
+
+    #[test]
+    fn det_accounts_for_permutation() {
+        let a = arr2(&[[1.0, 0.0, 3.0],
+                       [0.0, 2.0, 4.0],
+                       [1.0, 0.0, 0.0]]);
+        let mut pivoted = ColumnPivoted::<Modified>::from_matrix(&a).unwrap();
+        pivoted.compute(&a).unwrap();
+        assert_ne!(pivoted.permutation(), &[0, 1, 2]);
+        assert!((pivoted.det().unwrap() + 6.0).abs() < 1e-13);
+        assert!((pivoted.ln_abs_det() - f64::ln(6.0)).abs() < 1e-14);
+    }
+
+    #[test]
+    fn permuting_columns_round_trips() {
+        let a = rank_deficient();
+        let mut pivoted = ColumnPivoted::<Modified>::from_matrix(&a).unwrap();
+        pivoted.compute(&a).unwrap();
+        assert_ne!(pivoted.permutation(), &[0, 1, 2, 3, 4]);
+
+        let permuted = pivoted.permute_columns(&a).unwrap();
+        assert_eq!(permuted, a.select(Axis(1), pivoted.permutation()));
+        assert_eq!(pivoted.unpermute_columns(&permuted).unwrap(), a);
+
+        let metadata = Array2::from_shape_fn((2, 5).f(), |(i, j)| 10 * i + j);
+        let permuted = pivoted.permute_columns(&metadata).unwrap();
+        assert!(permuted.t().is_standard_layout());
+        assert_eq!(pivoted.unpermute_columns(&permuted).unwrap(), metadata);
+
+        match pivoted.permute_columns(&a.slice(s![.., ..4])) {
+            Err(Error::LengthMismatch { expected: 5, found: 4 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mismatched_shape_is_rejected() {
+        let mut pivoted = ColumnPivoted::<Modified>::from_matrix(&Array2::<f64>::zeros((3, 2))).unwrap();
+        match pivoted.compute(&rank_deficient()) {
+            Err(Error::ShapeMismatch { expected: (3, 2), found: (8, 5) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+    }
+}
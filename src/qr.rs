@@ -0,0 +1,700 @@
+use ndarray::{
+    Data,
+    ShapeBuilder,
+    s,
+};
+use ndarray::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{
+    Classical,
+    Error,
+    GramSchmidt,
+    Layout,
+    Modified,
+    PreparedInput,
+    Reorthogonalized,
+    Result,
+    blas,
+    cgs_with_options,
+    cgs2_with_options,
+    mgs_with_options,
+    utils,
+};
+
+/// The Gram Schmidt procedure used by [`qr`] and [`factorize`].
+///
+/// A procedure can be parsed from its name, e.g. one read from a configuration file. Both the
+/// names of the procedures, as printed by `Display`, and the names of the convenience functions
+/// are accepted, ignoring case:
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{qr, Options, Procedure};
+/// use ndarray::arr2;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let procedure: Procedure = "mgs".parse()?;
+/// assert_eq!(procedure, Procedure::Modified);
+/// assert_eq!(procedure.to_string().parse::<Procedure>()?, procedure);
+///
+/// let a = arr2(&[[1.0, 0.0],
+///                [1.0, 1.0],
+///                [1.0, 2.0]]);
+/// let qr = qr(a.clone(), Options { procedure, ..Options::default() })?;
+/// assert!(a.all_close(&qr.reconstruct(), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`qr`]: fn.qr.html
+/// [`factorize`]: fn.factorize.html
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Procedure {
+    /// The classical Gram Schmidt procedure, see [`Classical`](struct.Classical.html).
+    Classical,
+
+    /// The modified Gram Schmidt procedure, see [`Modified`](struct.Modified.html).
+    Modified,
+
+    /// The classical Gram Schmidt procedure with reorthogonalization, see
+    /// [`Reorthogonalized`](struct.Reorthogonalized.html).
+    Reorthogonalized,
+}
+
+impl fmt::Display for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Procedure::Classical => "classical",
+            Procedure::Modified => "modified",
+            Procedure::Reorthogonalized => "reorthogonalized",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Procedure {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "classical" | "cgs" => Ok(Procedure::Classical),
+            "modified" | "mgs" => Ok(Procedure::Modified),
+            "reorthogonalized" | "cgs2" => Ok(Procedure::Reorthogonalized),
+            _ => Err(Error::UnknownProcedure { name: name.to_owned() }),
+        }
+    }
+}
+
+/// The shape of the factors returned by [`qr`] for an `m×n` matrix with `m > n`.
+///
+/// [`qr`]: fn.qr.html
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QMode {
+    /// The thin factorization, with an `m×n` matrix Q and an `n×n` matrix R.
+    Thin,
+
+    /// The full factorization, with a square `m×m` orthogonal matrix Q and an `m×n` matrix R.
+    /// The trailing `m - n` columns of Q are an orthonormal basis of the orthogonal complement of
+    /// the column space, e.g. of the null space of `Aᵀ`, and the trailing `m - n` rows of R are
+    /// zero.
+    Full,
+}
+
+/// Procedure specific options, used by [`qr`] and the convenience functions like
+/// [`cgs_with_options`].
+///
+/// [`qr`]: fn.qr.html
+/// [`cgs_with_options`]: fn.cgs_with_options.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProcedureOptions {
+    /// The number of leading columns which are already orthonormal and only copied into Q. See
+    /// [`Classical::set_pinned`](struct.Classical.html#method.set_pinned). Defaults to `0`.
+    pub pinned: usize,
+
+    /// The tolerance deciding whether a column is orthogonalized a second time, only used by the
+    /// reorthogonalized procedure. See
+    /// [`Reorthogonalized::set_reorthogonalization_tolerance`](struct.Reorthogonalized.html#method.set_reorthogonalization_tolerance).
+    /// Defaults to infinity, i.e. always reorthogonalizing.
+    pub reorthogonalization_tolerance: f64,
+
+    /// The tolerance below which a column is considered to be linearly dependent on the previous
+    /// ones, failing the factorization with [`Error::RankDeficient`], which also determines the
+    /// [`Qr::rank`] of the result. See
+    /// [`GramSchmidt::set_tolerance`](trait.GramSchmidt.html#tymethod.set_tolerance). Defaults
+    /// to `None`, i.e. `max(m, n)·ε` for an `m×n` matrix.
+    ///
+    /// [`Error::RankDeficient`]: enum.Error.html#variant.RankDeficient
+    /// [`Qr::rank`]: struct.Qr.html#method.rank
+    pub tolerance: Option<f64>,
+}
+
+impl Default for ProcedureOptions {
+    fn default() -> Self {
+        Self {
+            pinned: 0,
+            reorthogonalization_tolerance: f64::INFINITY,
+            tolerance: None,
+        }
+    }
+}
+
+/// Options for the factorization performed by [`qr`].
+///
+/// [`qr`]: fn.qr.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    /// The procedure used for the factorization. Defaults to the numerically most stable
+    /// [`Procedure::Reorthogonalized`](enum.Procedure.html#variant.Reorthogonalized).
+    pub procedure: Procedure,
+
+    /// Diagonal entries of R with `|r_ii| <= rank_tolerance · max_j |r_jj|` are considered zero
+    /// when determining the rank. Defaults to the tolerance of the procedure, see
+    /// [`ProcedureOptions::tolerance`](struct.ProcedureOptions.html#structfield.tolerance).
+    pub rank_tolerance: Option<f64>,
+
+    /// Options passed on to the procedure.
+    pub procedure_options: ProcedureOptions,
+
+    /// Whether the thin or the full factorization is returned. Defaults to
+    /// [`QMode::Thin`](enum.QMode.html#variant.Thin).
+    pub q_mode: QMode,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            procedure: Procedure::Reorthogonalized,
+            rank_tolerance: None,
+            procedure_options: ProcedureOptions::default(),
+            q_mode: QMode::Thin,
+        }
+    }
+}
+
+/// A self-contained QR decomposition `A = QR` of a matrix `A`, as returned by [`qr`] and the
+/// convenience functions like [`cgs_qr`].
+///
+/// Unlike the procedures implementing [`GramSchmidt`], which reuse their buffers for repeated
+/// factorizations of matrices with the same shape, this owns the result of a single
+/// factorization and provides the most common operations on it.
+///
+/// [`qr`]: fn.qr.html
+/// [`cgs_qr`]: fn.cgs_qr.html
+/// [`GramSchmidt`]: trait.GramSchmidt.html
+#[derive(Clone, Debug)]
+pub struct Qr {
+    q: Array2<f64>,
+    r: Array2<f64>,
+    rank: usize,
+    permutation: Vec<usize>,
+    layout: Layout,
+}
+
+/// Computes the QR decomposition of the matrix `a` with the procedure selected by `options`.
+///
+/// The input is consumed; if it is not stored contiguously, it is copied into a contiguous
+/// array first.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{qr, Options};
+/// use ndarray::{arr1, arr2};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = arr2(&[[1.0, 0.0],
+///                [1.0, 1.0],
+///                [1.0, 2.0]]);
+/// let qr = qr(a.clone(), Options::default())?;
+/// assert_eq!(qr.rank(), 2);
+/// assert!(a.all_close(&qr.reconstruct(), 1e-14));
+///
+/// // Fit a line through the points (0, 1), (1, 3), (2, 5).
+/// let x = qr.solve(&arr1(&[1.0, 3.0, 5.0]))?;
+/// assert!(x.all_close(&arr1(&[1.0, 2.0]), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn qr(a: Array2<f64>, options: Options) -> Result<Qr> {
+    if utils::is_broadcast(&a) {
+        Err(Error::BroadcastInput)?;
+    }
+    let a = if utils::get_layout(&a).is_some() {
+        a
+    } else {
+        PreparedInput::new(&a).into_inner()
+    };
+
+    let qr = match options.procedure {
+        Procedure::Classical => cgs_with_options(&a, &options.procedure_options)?,
+        Procedure::Modified => mgs_with_options(&a, &options.procedure_options)?,
+        Procedure::Reorthogonalized => cgs2_with_options(&a, &options.procedure_options)?,
+    };
+    // The rank is determined with the tolerance of the procedure unless configured separately.
+    let qr = match options.rank_tolerance {
+        Some(_) => Qr::from_factors(qr.q, qr.r, options.rank_tolerance),
+        None => qr,
+    };
+    match options.q_mode {
+        QMode::Thin => Ok(qr),
+        QMode::Full => Ok(qr.into_full()),
+    }
+}
+
+/// Computes the QR decomposition of the borrowed matrix `a` with the given `procedure`,
+/// returning the tuple `(Q,R)`.
+///
+/// Unlike [`qr`], this neither consumes nor copies the input, and is the runtime counterpart of
+/// calling [`GramSchmidt::compute_once`] on the selected procedure.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{factorize, Procedure};
+/// use ndarray::arr2;
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let procedure: Procedure = "cgs2".parse()?;
+/// let a = arr2(&[[1.0, 0.0],
+///                [1.0, 1.0],
+///                [1.0, 2.0]]);
+/// let (q, r) = factorize(procedure, &a)?;
+/// assert!(a.all_close(&q.dot(&r), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`qr`]: fn.qr.html
+/// [`GramSchmidt::compute_once`]: trait.GramSchmidt.html#method.compute_once
+pub fn factorize<S>(procedure: Procedure, a: &ArrayBase<S, Ix2>) -> Result<(Array2<f64>, Array2<f64>)>
+    where S: Data<Elem = f64>
+{
+    match procedure {
+        Procedure::Classical => Classical::compute_once(a),
+        Procedure::Modified => Modified::compute_once(a),
+        Procedure::Reorthogonalized => Reorthogonalized::compute_once(a),
+    }
+}
+
+impl Qr {
+    /// Wraps the factors of an unpivoted factorization, determining the rank with `rank_tolerance`
+    /// as described for [`Options::rank_tolerance`], or with the default tolerance of the
+    /// procedures if it is `None`.
+    ///
+    /// [`Options::rank_tolerance`]: struct.Options.html#structfield.rank_tolerance
+    pub(crate) fn from_factors(q: Array2<f64>, r: Array2<f64>, rank_tolerance: Option<f64>) -> Self {
+        let (n_rows, n_cols) = (q.rows(), r.cols());
+        let rank_tolerance = rank_tolerance
+            .unwrap_or_else(|| utils::default_tolerance::<f64>(n_rows, n_cols));
+        let max_diagonal = r.diag().fold(0.0, |acc: f64, r_ii| acc.max(r_ii.abs()));
+        let rank = r.diag().iter()
+            .filter(|r_ii| r_ii.abs() > rank_tolerance * max_diagonal)
+            .count();
+
+        // NOTE: This unwrap is safe, because Q is allocated contiguously by the procedures.
+        let layout = utils::get_layout(&q).unwrap().into();
+
+        Self {
+            q,
+            r,
+            rank,
+            permutation: (0..n_cols).collect(),
+            layout,
+        }
+    }
+
+    /// Return a reference to the matrix q.
+    pub fn q(&self) -> &Array2<f64> {
+        &self.q
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<f64> {
+        &self.r
+    }
+
+    /// Returns the numerical rank of the factorized matrix, i.e. the number of diagonal entries
+    /// of R that are not considered zero. See [`Options::rank_tolerance`].
+    ///
+    /// [`Options::rank_tolerance`]: struct.Options.html#structfield.rank_tolerance
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// Returns the column permutation of the factorization: the `j`-th column of Q·R is the
+    /// `permutation()[j]`-th column of the factorized matrix. Since none of the procedures pivots
+    /// at the moment, this is always the identity.
+    pub fn permutation(&self) -> &[usize] {
+        &self.permutation
+    }
+
+    /// Returns the memory layout of Q, which is the one of the factorized matrix.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Extends a thin factorization to the full one, see [`QMode::Full`].
+    ///
+    /// [`QMode::Full`]: enum.QMode.html#variant.Full
+    fn into_full(self) -> Self {
+        let (n_rows, n_cols) = (self.q.rows(), self.r.cols());
+        let column_major = self.layout == Layout::ColumnMajor;
+
+        let mut r = Array2::zeros((n_rows, n_cols).set_f(column_major));
+        r.slice_mut(s![..n_cols, ..]).assign(&self.r);
+
+        Self {
+            q: Reorthogonalized::complete_basis(&self.q),
+            r,
+            ..self
+        }
+    }
+
+    /// Consumes the factorization, returning Q and R.
+    pub fn into_tuple(self) -> (Array2<f64>, Array2<f64>) {
+        (self.q, self.r)
+    }
+
+    /// Returns the product Q·R with the inverse [`permutation`] applied to its columns, i.e. the
+    /// factorized matrix up to rounding errors.
+    ///
+    /// [`permutation`]: #method.permutation
+    pub fn reconstruct(&self) -> Array2<f64> {
+        let product = self.q.dot(&self.r);
+        let mut a = Array2::zeros(product.dim());
+        for (j, &column) in self.permutation.iter().enumerate() {
+            a.column_mut(column).assign(&product.column(j));
+        }
+        a
+    }
+
+    /// Returns the orthogonal projection `QQᵀb` of `b` onto the column space of the factorized
+    /// matrix.
+    pub fn project_onto<S>(&self, b: &ArrayBase<S, Ix1>) -> Result<Array1<f64>>
+        where S: Data<Elem = f64>
+    {
+        self.check_length(b)?;
+        let q = self.thin_q();
+        Ok(q.dot(&q.t().dot(b)))
+    }
+
+    /// Solves the linear least squares problem `min ‖Ax - b‖` by solving `Rx = Qᵀb` with a
+    /// triangular solve. Returns an error if R is singular.
+    pub fn solve<S>(&self, b: &ArrayBase<S, Ix1>) -> Result<Array1<f64>>
+        where S: Data<Elem = f64>
+    {
+        self.check_length(b)?;
+        if self.r.diag().iter().any(|&r_ii| r_ii == 0.0) {
+            Err(Error::Singular)?;
+        }
+
+        let mut x = self.thin_q().t().dot(b);
+        let n = self.r.cols();
+        let (r_slice, r_layout) = match utils::as_slice_with_layout(&self.r) {
+            Some(r) => r,
+            None => Err(Error::NonContiguous)?,
+        };
+        // The leading n×n block of the m×n R of a full factorization is read in place.
+        let leading_dim = match r_layout {
+            blas::Layout::RowMajor => n,
+            blas::Layout::ColumnMajor => self.r.rows(),
+        };
+        // NOTE: This unwrap is safe, because x is a freshly allocated, contiguous array.
+        let x_slice = x.as_slice_mut().unwrap();
+
+        unsafe {
+            crate::blas::dtrsv(
+                r_layout,
+                blas::Part::Upper,
+                blas::Transpose::None,
+                blas::Diagonal::Generic,
+                n as i32,
+                r_slice,
+                leading_dim as i32,
+                x_slice,
+                1,
+            );
+        }
+
+        Ok(x)
+    }
+
+    /// Returns an estimate of the 2-norm condition number of the factorized matrix, the ratio
+    /// `max_i |r_ii| / min_i |r_ii|` of the diagonal entries of R.
+    ///
+    /// This is a lower bound of the actual condition number, and is infinite for a singular R.
+    pub fn condition_number(&self) -> f64 {
+        let (min, max) = self.r.diag().fold((f64::INFINITY, 0.0), |(min, max): (f64, f64), r_ii| {
+            (min.min(r_ii.abs()), max.max(r_ii.abs()))
+        });
+        max / min
+    }
+
+    /// Returns the determinant of the factorized square matrix, or [`Error::NotSquare`] if it is
+    /// not square. See [`GramSchmidt::det`](trait.GramSchmidt.html#method.det).
+    ///
+    /// [`Error::NotSquare`]: enum.Error.html#variant.NotSquare
+    pub fn det(&self) -> Result<f64> {
+        if self.q.rows() != self.r.cols() {
+            Err(Error::NotSquare)?;
+        }
+        let product: f64 = self.r.diag().iter().product();
+        if product == 0.0 {
+            return Ok(0.0);
+        }
+        Ok(utils::permutation_sign(&self.permutation) * utils::orthogonal_det_sign(&self.q) * product)
+    }
+
+    /// Returns the logarithm of the absolute value of the determinant of the factorized matrix.
+    /// See [`GramSchmidt::ln_abs_det`](trait.GramSchmidt.html#method.ln_abs_det).
+    pub fn ln_abs_det(&self) -> f64 {
+        self.r.diag().iter().map(|r_ii| r_ii.abs().ln()).sum()
+    }
+
+    /// Returns the leading columns of Q spanning the column space of the factorized matrix, which
+    /// are all of them for a thin factorization.
+    fn thin_q(&self) -> ArrayView2<'_, f64> {
+        self.q.slice(s![.., ..self.r.cols()])
+    }
+
+    fn check_length<S>(&self, b: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        if b.len() != self.q.rows() {
+            Err(Error::LengthMismatch { expected: self.q.rows(), found: b.len() })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn all_procedures_reconstruct() {
+        for &procedure in [Procedure::Classical, Procedure::Modified, Procedure::Reorthogonalized].iter() {
+            for a in [matrix(4, 3), matrix(4, 3).reversed_axes().to_owned().reversed_axes()].iter() {
+                let options = Options { procedure, ..Options::default() };
+                let qr = qr(a.clone(), options).unwrap();
+                assert!(a.all_close(&qr.reconstruct(), 1e-14));
+                assert_eq!(qr.rank(), 3);
+                assert_eq!(qr.permutation(), &[0, 1, 2]);
+            }
+        }
+    }
+
+    #[test]
+    fn parsed_procedures_match_convenience_functions() {
+        let a = matrix(4, 3);
+        for name in ["Classical", "mgs", "cgs2"].iter() {
+            let procedure: Procedure = name.parse().unwrap();
+            let options = Options { procedure, ..Options::default() };
+            let dispatched = qr(a.clone(), options).unwrap();
+            let direct = match procedure {
+                Procedure::Classical => crate::cgs_qr(&a),
+                Procedure::Modified => crate::mgs_qr(&a),
+                Procedure::Reorthogonalized => crate::cgs2_qr(&a),
+            }.unwrap();
+            assert_eq!(dispatched.q(), direct.q());
+            assert_eq!(dispatched.r(), direct.r());
+        }
+
+        match "householder".parse::<Procedure>() {
+            Err(Error::UnknownProcedure { ref name }) if name == "householder" => {},
+            other => panic!("expected an unknown procedure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn factorize_classical_matches_cgs() {
+        let a = matrix(4, 3);
+        assert_eq!(factorize(Procedure::Classical, &a).unwrap(), crate::cgs(&a).unwrap());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn factorize_modified_matches_mgs() {
+        let a = matrix(4, 3);
+        assert_eq!(factorize(Procedure::Modified, &a).unwrap(), crate::mgs(&a).unwrap());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn factorize_reorthogonalized_matches_cgs2() {
+        let a = matrix(4, 3);
+        assert_eq!(factorize(Procedure::Reorthogonalized, &a).unwrap(), crate::cgs2(&a).unwrap());
+    }
+
+    #[test]
+    fn procedure_options_are_applied() {
+        let mut a = matrix(4, 3);
+        a.column_mut(0).assign(&arr1(&[0.0, 0.6, 0.0, 0.8]));
+        let procedure_options = ProcedureOptions {
+            pinned: 1,
+            reorthogonalization_tolerance: 0.5,
+            ..ProcedureOptions::default()
+        };
+        for &procedure in [Procedure::Classical, Procedure::Modified, Procedure::Reorthogonalized].iter() {
+            let options = Options { procedure, procedure_options, ..Options::default() };
+            let qr = qr(a.clone(), options).unwrap();
+            assert_eq!(qr.q().column(0), a.column(0));
+            assert!(a.all_close(&qr.reconstruct(), 1e-14));
+        }
+
+        let q = crate::cgs2_with_options(&a, &procedure_options).unwrap().into_tuple().0;
+        let q_always = crate::cgs2_qr(&a).unwrap().into_tuple().0;
+        assert!(q.all_close(&q_always, 1e-14));
+    }
+
+    #[test]
+    fn invalid_procedure_options_are_rejected() {
+        type WithOptions = fn(&Array2<f64>, &ProcedureOptions) -> Result<Qr>;
+        let functions: [WithOptions; 3] = [
+            crate::cgs_with_options,
+            crate::cgs2_with_options,
+            crate::mgs_with_options,
+        ];
+
+        // The third column deviates from twice the first one by 1e-10.
+        let mut a = matrix(4, 3);
+        let mut dependent = &a.column(0) * 2.0;
+        dependent[1] += 1e-10;
+        a.column_mut(2).assign(&dependent);
+        let too_many_pinned = ProcedureOptions { pinned: 4, ..ProcedureOptions::default() };
+        let tolerance = ProcedureOptions { tolerance: Some(1e-8), ..ProcedureOptions::default() };
+
+        for with_options in functions.iter() {
+            match with_options(&a, &too_many_pinned) {
+                Err(Error::TooManyPinned { pinned: 4, cols: 3 }) => {},
+                other => panic!("expected too many pinned columns, got {:?}", other),
+            }
+            match with_options(&a, &tolerance) {
+                Err(Error::RankDeficient { column: 2 }) => {},
+                other => panic!("expected a rank deficient matrix, got {:?}", other),
+            }
+            assert!(with_options(&a, &ProcedureOptions::default()).is_ok());
+        }
+    }
+
+    #[test]
+    fn layout_follows_input() {
+        let qr_c = qr(matrix(4, 3), Options::default()).unwrap();
+        assert_eq!(qr_c.layout(), Layout::RowMajor);
+        let f_matrix = Array2::from_shape_vec((4, 3).f(), matrix(4, 3).t().iter().cloned().collect()).unwrap();
+        let qr_f = qr(f_matrix, Options::default()).unwrap();
+        assert_eq!(qr_f.layout(), Layout::ColumnMajor);
+        assert!(qr_c.q().all_close(qr_f.q(), 1e-14));
+    }
+
+    #[test]
+    fn solve_and_project_agree() {
+        let a = matrix(4, 3);
+        let qr = qr(a.clone(), Options::default()).unwrap();
+        let b = arr1(&[1.0, -2.0, 0.5, 4.0]);
+        let x = qr.solve(&b).unwrap();
+        let projection = qr.project_onto(&b).unwrap();
+        assert!(a.dot(&x).all_close(&projection, 1e-13));
+
+        // The residual is orthogonal to the column space.
+        let residual = &b - &projection;
+        assert!(a.t().dot(&residual).iter().all(|x| x.abs() < 1e-13));
+
+        match qr.solve(&arr1(&[1.0, 2.0])) {
+            Err(Error::LengthMismatch { expected: 4, found: 2 }) => {},
+            _ => panic!("expected a length mismatch"),
+        }
+    }
+
+    #[test]
+    fn det_of_square_matrix() {
+        let qr = qr(arr2(&[[0.0, 2.0], [3.0, 1.0]]), Options::default()).unwrap();
+        assert!((qr.det().unwrap() + 6.0).abs() < 1e-14);
+        assert!((qr.ln_abs_det() - f64::ln(6.0)).abs() < 1e-14);
+
+        let tall = super::qr(arr2(&[[0.0, 2.0], [3.0, 1.0], [1.0, 1.0]]), Options::default()).unwrap();
+        match tall.det() {
+            Err(Error::NotSquare) => {},
+            other => panic!("expected a non-square matrix, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rank_and_condition_of_dependent_columns() {
+        let mut a = matrix(4, 3);
+        let dependent = &a.column(0) * 2.0 + &a.column(1) * 1e-15;
+        a.column_mut(2).assign(&dependent);
+        // The procedure accepts the dependent column, whose rank is decided separately.
+        let options = Options {
+            procedure: Procedure::Modified,
+            rank_tolerance: Some(1e-10),
+            procedure_options: ProcedureOptions { tolerance: Some(0.0), ..ProcedureOptions::default() },
+            ..Options::default()
+        };
+        let qr = qr(a, options).unwrap();
+        assert_eq!(qr.rank(), 2);
+        assert!(qr.condition_number() > 1e10);
+    }
+
+    #[test]
+    fn full_q_is_square_and_orthogonal() {
+        let a = arr2(
+            &[[2.0, 0.5, 0.0],
+              [0.0, 0.3, 0.0],
+              [0.0, 1.0, 0.7],
+              [1.0, 0.0, 3.0],
+              [0.5, 2.0, 1.0]]
+        );
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        for a in [a, f_a].iter() {
+            let thin = qr(a.clone(), Options::default()).unwrap();
+            let options = Options { q_mode: QMode::Full, ..Options::default() };
+            let full = qr(a.clone(), options).unwrap();
+
+            assert_eq!(full.q().dim(), (5, 5));
+            assert_eq!(full.r().dim(), (5, 3));
+            assert_eq!(full.layout(), thin.layout());
+            assert!(full.q().t().dot(full.q()).all_close(&Array2::eye(5), 1e-14));
+            assert!(full.q().slice(s![.., ..3]).all_close(thin.q(), 1e-15));
+            assert!(full.r().slice(s![3.., ..]).iter().all(|&r_ij| r_ij == 0.0));
+            assert!(a.all_close(&full.reconstruct(), 1e-14));
+            assert_eq!(full.rank(), 3);
+
+            // The trailing columns span the null space of Aᵀ.
+            assert!(a.t().dot(&full.q().slice(s![.., 3..])).iter().all(|x| x.abs() < 1e-14));
+
+            let b = arr1(&[1.0, -2.0, 0.5, 4.0, 0.0]);
+            assert!(full.solve(&b).unwrap().all_close(&thin.solve(&b).unwrap(), 1e-14));
+            assert!(full.project_onto(&b).unwrap().all_close(&thin.project_onto(&b).unwrap(), 1e-14));
+        }
+    }
+
+    #[test]
+    fn full_q_skips_unit_vectors_in_the_column_space() {
+        // The leading unit vectors lie in the column space and cannot extend the basis.
+        let a = Array2::from_shape_fn((5, 3), |(i, j)| if i == j { 2.0 } else { 0.0 });
+        let options = Options { q_mode: QMode::Full, ..Options::default() };
+        let full = qr(a.clone(), options).unwrap();
+        assert!(full.q().t().dot(full.q()).all_close(&Array2::eye(5), 1e-15));
+        assert!(a.all_close(&full.reconstruct(), 1e-15));
+    }
+}
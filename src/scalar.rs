@@ -0,0 +1,282 @@
+use ndarray::{
+    LinalgScalar,
+    ScalarOperand,
+};
+use num_complex::Complex;
+use num_traits::{
+    Float,
+    NumAssign,
+    ToPrimitive,
+};
+use std::fmt;
+use std::ops::Neg;
+
+use crate::blas;
+
+pub(crate) mod private {
+    use ndarray::prelude::*;
+
+    use crate::blas;
+
+    /// Operations which differ between real and complex scalars. Being private, this trait also
+    /// seals [`GramSchmidtScalar`](super::GramSchmidtScalar).
+    pub trait Sealed: Sized {
+        /// The blas transposition which yields the adjoint: the transpose for real matrices, and
+        /// the conjugate transpose for complex ones.
+        const ADJOINT: blas::Transpose;
+
+        /// Returns the inner product `xᴴ·y`, conjugating the entries of `x`.
+        fn dotc(x: ArrayView1<Self>, y: ArrayView1<Self>) -> Self;
+
+        /// Returns the product `Aᴴ·v`.
+        fn adjoint_dot(a: ArrayView2<Self>, v: ArrayView1<Self>) -> Array1<Self>;
+
+        /// Returns the Gram matrix `AᴴA`.
+        fn gram(a: ArrayView2<Self>) -> Array2<Self>;
+    }
+
+    macro_rules! impl_real {
+        ($scalar:ty) => {
+            impl Sealed for $scalar {
+                const ADJOINT: blas::Transpose = blas::Transpose::Ordinary;
+
+                fn dotc(x: ArrayView1<Self>, y: ArrayView1<Self>) -> Self {
+                    x.dot(&y)
+                }
+
+                fn adjoint_dot(a: ArrayView2<Self>, v: ArrayView1<Self>) -> Array1<Self> {
+                    a.t().dot(&v)
+                }
+
+                fn gram(a: ArrayView2<Self>) -> Array2<Self> {
+                    a.t().dot(&a)
+                }
+            }
+        };
+    }
+
+    macro_rules! impl_complex {
+        ($scalar:ty) => {
+            impl Sealed for $scalar {
+                const ADJOINT: blas::Transpose = blas::Transpose::Conjugate;
+
+                fn dotc(x: ArrayView1<Self>, y: ArrayView1<Self>) -> Self {
+                    x.iter().zip(&y).fold(Self::new(0.0, 0.0), |acc, (x_i, &y_i)| acc + x_i.conj() * y_i)
+                }
+
+                fn adjoint_dot(a: ArrayView2<Self>, v: ArrayView1<Self>) -> Array1<Self> {
+                    // Aᴴv is the conjugate of Aᵀv̄.
+                    a.t().dot(&v.mapv(|v_i| v_i.conj())).mapv(|x_i| x_i.conj())
+                }
+
+                fn gram(a: ArrayView2<Self>) -> Array2<Self> {
+                    a.t().mapv(|a_ij| a_ij.conj()).dot(&a)
+                }
+            }
+        };
+    }
+
+    impl_real!(f32);
+    impl_real!(f64);
+    impl_complex!(num_complex::Complex<f32>);
+    impl_complex!(num_complex::Complex<f64>);
+}
+
+/// The element types the Gram Schmidt procedures can operate on: the real `f32` and `f64`, and
+/// the complex `Complex<f32>` and `Complex<f64>` of the [num-complex crate].
+///
+/// For complex matrices, all inner products conjugate their first argument, so that Q has
+/// orthonormal columns with respect to the conjugate transpose, `QᴴQ = I`. The diagonal of R
+/// remains real and positive.
+///
+/// The blas functions dispatch to the routines of the respective type, e.g. [`gemv`] calls
+/// `sgemv` for `f32`, `dgemv` for `f64`, `cgemv` for `Complex<f32>`, and `zgemv` for
+/// `Complex<f64>`. They take the same arguments as their counterparts in the [cblas crate] and
+/// are just as unsafe.
+///
+/// # Safety
+///
+/// The slices passed to the blas functions have to hold all elements addressed by the
+/// dimensions, increments and leading dimensions, as blas does not check any bounds.
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+///
+/// [`gemv`]: #tymethod.gemv
+/// [num-complex crate]: https://docs.rs/num-complex
+/// [cblas crate]: https://docs.rs/cblas
+pub trait GramSchmidtScalar:
+    LinalgScalar + ScalarOperand + NumAssign + Neg<Output = Self> + fmt::Debug + Send + Sync + private::Sealed
+{
+    /// The real type underlying the scalar, i.e. `f64` for both `f64` and `Complex<f64>`.
+    type Real: GramSchmidtScalar<Real = Self::Real> + Float;
+
+    /// Returns the complex conjugate, which is the identity for real scalars.
+    fn conj(self) -> Self;
+
+    /// Converts a real number to the scalar type.
+    fn from_real(re: Self::Real) -> Self;
+
+    /// Returns the absolute value (the modulus for complex scalars).
+    fn modulus(self) -> Self::Real;
+
+    /// Converts a real number to `f64`, the precision of the tolerances and diagnostics.
+    fn to_f64(x: Self::Real) -> f64 {
+        // NOTE: This unwrap is safe, because both f32 and f64 can be converted to f64.
+        ToPrimitive::to_f64(&x).unwrap()
+    }
+
+    /// Computes `y ← α·op(A)·x + β·y` (blas `?gemv`).
+    ///
+    /// # Safety
+    ///
+    /// See the [trait level documentation](trait.GramSchmidtScalar.html#safety).
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn gemv(
+        layout: blas::Layout,
+        transa: blas::Transpose,
+        m: i32,
+        n: i32,
+        alpha: Self,
+        a: &[Self],
+        lda: i32,
+        x: &[Self],
+        incx: i32,
+        beta: Self,
+        y: &mut [Self],
+        incy: i32,
+    );
+
+    /// Computes `C ← α·op(A)·op(B) + β·C` (blas `?gemm`).
+    ///
+    /// # Safety
+    ///
+    /// See the [trait level documentation](trait.GramSchmidtScalar.html#safety).
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn gemm(
+        layout: blas::Layout,
+        transa: blas::Transpose,
+        transb: blas::Transpose,
+        m: i32,
+        n: i32,
+        k: i32,
+        alpha: Self,
+        a: &[Self],
+        lda: i32,
+        b: &[Self],
+        ldb: i32,
+        beta: Self,
+        c: &mut [Self],
+        ldc: i32,
+    );
+
+    /// Returns the euclidean norm of the vector `x` (blas `?nrm2`).
+    ///
+    /// # Safety
+    ///
+    /// See the [trait level documentation](trait.GramSchmidtScalar.html#safety).
+    unsafe fn nrm2(n: i32, x: &[Self], incx: i32) -> Self::Real;
+
+    /// Computes `y ← α·x + y` (blas `?axpy`).
+    ///
+    /// # Safety
+    ///
+    /// See the [trait level documentation](trait.GramSchmidtScalar.html#safety).
+    unsafe fn axpy(n: i32, alpha: Self, x: &[Self], incx: i32, y: &mut [Self], incy: i32);
+}
+
+macro_rules! impl_blas {
+    ($gemv:ident, $gemm:ident, $nrm2:ident, $axpy:ident) => {
+        unsafe fn gemv(
+            layout: blas::Layout,
+            transa: blas::Transpose,
+            m: i32,
+            n: i32,
+            alpha: Self,
+            a: &[Self],
+            lda: i32,
+            x: &[Self],
+            incx: i32,
+            beta: Self,
+            y: &mut [Self],
+            incy: i32,
+        ) {
+            crate::blas::$gemv(layout, transa, m, n, alpha, a, lda, x, incx, beta, y, incy)
+        }
+
+        unsafe fn gemm(
+            layout: blas::Layout,
+            transa: blas::Transpose,
+            transb: blas::Transpose,
+            m: i32,
+            n: i32,
+            k: i32,
+            alpha: Self,
+            a: &[Self],
+            lda: i32,
+            b: &[Self],
+            ldb: i32,
+            beta: Self,
+            c: &mut [Self],
+            ldc: i32,
+        ) {
+            crate::blas::$gemm(layout, transa, transb, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc)
+        }
+
+        unsafe fn nrm2(n: i32, x: &[Self], incx: i32) -> Self::Real {
+            crate::blas::$nrm2(n, x, incx)
+        }
+
+        unsafe fn axpy(n: i32, alpha: Self, x: &[Self], incx: i32, y: &mut [Self], incy: i32) {
+            crate::blas::$axpy(n, alpha, x, incx, y, incy)
+        }
+    };
+}
+
+macro_rules! impl_real_scalar {
+    ($scalar:ty, $gemv:ident, $gemm:ident, $nrm2:ident, $axpy:ident) => {
+        impl GramSchmidtScalar for $scalar {
+            type Real = $scalar;
+
+            fn conj(self) -> Self {
+                self
+            }
+
+            fn from_real(re: Self::Real) -> Self {
+                re
+            }
+
+            fn modulus(self) -> Self::Real {
+                self.abs()
+            }
+
+            impl_blas!($gemv, $gemm, $nrm2, $axpy);
+        }
+    };
+}
+
+macro_rules! impl_complex_scalar {
+    ($real:ty, $gemv:ident, $gemm:ident, $nrm2:ident, $axpy:ident) => {
+        impl GramSchmidtScalar for Complex<$real> {
+            type Real = $real;
+
+            fn conj(self) -> Self {
+                Complex::conj(&self)
+            }
+
+            fn from_real(re: Self::Real) -> Self {
+                Complex::new(re, 0.0)
+            }
+
+            fn modulus(self) -> Self::Real {
+                self.norm()
+            }
+
+            impl_blas!($gemv, $gemm, $nrm2, $axpy);
+        }
+    };
+}
+
+impl_real_scalar!(f32, sgemv, sgemm, snrm2, saxpy);
+impl_real_scalar!(f64, dgemv, dgemm, dnrm2, daxpy);
+impl_complex_scalar!(f32, cgemv, cgemm, scnrm2, caxpy);
+impl_complex_scalar!(f64, zgemv, zgemm, dznrm2, zaxpy);
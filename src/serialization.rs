@@ -0,0 +1,273 @@
+//! Serialization of the Gram Schmidt procedures with serde, enabled by the `serde` feature.
+//!
+//! The procedures serialize their matrices Q and R, their memory layouts, and their settings.
+//! Deserialization goes through the states defined here: the matrices are restored in their
+//! memory layouts and checked like the arguments of
+//! [`GramSchmidt::from_parts`](crate::GramSchmidt::from_parts) before a procedure is formed from
+//! them, because the blas calls rely on the shapes and layouts being consistent.
+
+use ndarray::ShapeBuilder;
+use ndarray::prelude::*;
+use serde_crate::{
+    Deserialize,
+    Serialize,
+};
+use std::convert::TryFrom;
+
+use crate::{
+    Classical,
+    Error,
+    GramSchmidt,
+    GramSchmidtScalar,
+    Modified,
+    Reorthogonalized,
+    Result,
+    blas,
+    utils::get_layout,
+};
+
+/// A mirror of `blas::Layout`, which does not implement the serde traits itself.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", remote = "blas::Layout")]
+pub(crate) enum CblasLayout {
+    RowMajor,
+    ColumnMajor,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(crate) struct ClassicalState<A> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[serde(with = "CblasLayout")]
+    memory_layout: blas::Layout,
+    #[serde(with = "CblasLayout")]
+    r_layout: blas::Layout,
+    pinned: usize,
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    orthonormal_threshold: f64,
+    #[serde(default)]
+    skip_finite_check: bool,
+    #[serde(default)]
+    accept_any_layout: bool,
+    #[serde(default)]
+    sign_flipped: Vec<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(crate) struct ModifiedState<A> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[serde(with = "CblasLayout")]
+    memory_layout: blas::Layout,
+    pinned: usize,
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    orthonormal_threshold: f64,
+    #[serde(default)]
+    skip_finite_check: bool,
+    #[serde(default)]
+    sign_flipped: Vec<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate")]
+pub(crate) struct ReorthogonalizedState<A> {
+    q: Array2<A>,
+    r: Array2<A>,
+    #[serde(with = "CblasLayout")]
+    memory_layout: blas::Layout,
+    #[serde(with = "CblasLayout")]
+    r_layout: blas::Layout,
+    reorthogonalization_tolerance: f64,
+    pinned: usize,
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    orthonormal_threshold: f64,
+    #[serde(default)]
+    skip_finite_check: bool,
+    reorthogonalized: Vec<bool>,
+    #[serde(default)]
+    accept_any_layout: bool,
+    #[serde(default)]
+    sign_flipped: Vec<bool>,
+}
+
+/// Returns the recorded sign flips of `n_cols` columns. States serialized before the sign flips
+/// were recorded have none, in which case no column was flipped.
+fn sign_flips(sign_flipped: Vec<bool>, n_cols: usize) -> Result<Vec<bool>> {
+    match sign_flipped.len() {
+        0 => Ok(vec![false; n_cols]),
+        len if len == n_cols => Ok(sign_flipped),
+        len => Err(Error::LengthMismatch { expected: n_cols, found: len }),
+    }
+}
+
+/// Returns `a` stored in `layout`, copying it if necessary. ndarray always deserializes arrays
+/// in row major layout.
+fn with_layout<A: GramSchmidtScalar>(a: Array2<A>, layout: blas::Layout) -> Array2<A> {
+    if get_layout(&a) == Some(layout) {
+        a
+    } else {
+        let mut copy = Array2::zeros(a.dim().set_f(layout == blas::Layout::ColumnMajor));
+        copy.assign(&a);
+        copy
+    }
+}
+
+impl<A: GramSchmidtScalar> TryFrom<ClassicalState<A>> for Classical<A> {
+    type Error = Error;
+
+    fn try_from(state: ClassicalState<A>) -> Result<Self> {
+        let sign_flipped = sign_flips(state.sign_flipped, state.q.cols())?;
+        let q = with_layout(state.q, state.memory_layout);
+        let r = with_layout(state.r, state.r_layout);
+        let mut cgs = Self::from_parts(q, r)?;
+        cgs.set_pinned(state.pinned)?;
+        if let Some(tolerance) = state.tolerance {
+            cgs.set_tolerance(tolerance);
+        }
+        cgs.set_orthonormal_threshold(state.orthonormal_threshold);
+        cgs.set_skip_finite_check(state.skip_finite_check);
+        cgs.set_accept_any_layout(state.accept_any_layout);
+        cgs.set_sign_flipped(sign_flipped);
+        Ok(cgs)
+    }
+}
+
+impl<A: GramSchmidtScalar> TryFrom<ModifiedState<A>> for Modified<A> {
+    type Error = Error;
+
+    fn try_from(state: ModifiedState<A>) -> Result<Self> {
+        let sign_flipped = sign_flips(state.sign_flipped, state.q.cols())?;
+        let q = with_layout(state.q, state.memory_layout);
+        let mut mgs = Self::from_parts(q, state.r)?;
+        mgs.set_pinned(state.pinned)?;
+        if let Some(tolerance) = state.tolerance {
+            mgs.set_tolerance(tolerance);
+        }
+        mgs.set_orthonormal_threshold(state.orthonormal_threshold);
+        mgs.set_skip_finite_check(state.skip_finite_check);
+        mgs.set_sign_flipped(sign_flipped);
+        Ok(mgs)
+    }
+}
+
+impl<A: GramSchmidtScalar> TryFrom<ReorthogonalizedState<A>> for Reorthogonalized<A> {
+    type Error = Error;
+
+    fn try_from(state: ReorthogonalizedState<A>) -> Result<Self> {
+        if state.reorthogonalized.len() != state.q.cols() {
+            Err(Error::LengthMismatch { expected: state.q.cols(), found: state.reorthogonalized.len() })?;
+        }
+        let sign_flipped = sign_flips(state.sign_flipped, state.q.cols())?;
+        let q = with_layout(state.q, state.memory_layout);
+        let r = with_layout(state.r, state.r_layout);
+        let mut cgs2 = Self::from_parts(q, r)?;
+        cgs2.set_reorthogonalization_tolerance(state.reorthogonalization_tolerance);
+        cgs2.set_pinned(state.pinned)?;
+        if let Some(tolerance) = state.tolerance {
+            cgs2.set_tolerance(tolerance);
+        }
+        cgs2.set_orthonormal_threshold(state.orthonormal_threshold);
+        cgs2.set_skip_finite_check(state.skip_finite_check);
+        cgs2.set_reorthogonalized(state.reorthogonalized);
+        cgs2.set_accept_any_layout(state.accept_any_layout);
+        cgs2.set_sign_flipped(sign_flipped);
+        Ok(cgs2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use crate::utils::matrix;
+    use super::*;
+
+    fn check_round_trip<G>(mut method: G, a: &Array2<f64>) -> G
+        where G: GramSchmidt<Scalar = f64> + Serialize + for<'de> Deserialize<'de>
+    {
+        method.compute(a).unwrap();
+        let json = serde_json::to_string(&method).unwrap();
+        let restored: G = serde_json::from_str(&json).unwrap();
+        assert_eq!(method.q(), restored.q());
+        assert_eq!(method.r(), restored.r());
+        assert_eq!(get_layout(method.q()), get_layout(restored.q()));
+        restored
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let a = matrix(4, 3);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+
+        for a in &[&a, &f_a] {
+            let mut cgs = Classical::from_matrix(*a).unwrap();
+            cgs.set_tolerance(1e-10);
+            cgs.set_orthonormal_threshold(1e-8);
+            cgs.set_skip_finite_check(true);
+            let cgs = check_round_trip(cgs, a);
+            assert_eq!(cgs.tolerance(), 1e-10);
+            assert_eq!(cgs.orthonormal_threshold(), 1e-8);
+            assert!(cgs.skips_finite_check());
+            assert!(!cgs.accepts_any_layout());
+
+            let cgs = Classical::from_shape_accepting_any_layout(a.dim()).unwrap();
+            assert!(check_round_trip(cgs, a).accepts_any_layout());
+
+            let mut mgs = Modified::from_matrix(*a).unwrap();
+            mgs.set_pinned(0).unwrap();
+            check_round_trip(mgs, a);
+
+            let mut cgs2 = Reorthogonalized::from_matrix(*a).unwrap();
+            cgs2.set_reorthogonalization_tolerance(0.5);
+            let provenance = {
+                let mut cgs2 = cgs2.clone();
+                cgs2.compute(*a).unwrap();
+                cgs2.provenance()
+            };
+            let cgs2 = check_round_trip(cgs2, a);
+            assert_eq!(cgs2.reorthogonalization_tolerance(), 0.5);
+            assert_eq!(cgs2.provenance(), provenance);
+
+            // The restored procedure can be reused.
+            let mut cgs2 = cgs2;
+            cgs2.compute(*a).unwrap();
+            assert!(a.all_close(&cgs2.q().dot(cgs2.r()), 1e-14));
+        }
+    }
+
+    #[test]
+    fn sign_flips_survive_round_trip() {
+        let a = matrix(4, 3);
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+        mgs.rescale_columns(&arr1(&[1.0, -1.0, 1.0])).unwrap();
+        let json = serde_json::to_string(&mgs).unwrap();
+        let restored: Modified = serde_json::from_str(&json).unwrap();
+        assert!(restored.provenance()[1].sign_flipped);
+        assert_eq!(restored.provenance(), mgs.provenance());
+    }
+
+    #[test]
+    fn inconsistent_state_is_rejected() {
+        let a = matrix(4, 3);
+        let mut cgs = Classical::from_matrix(&a).unwrap();
+        cgs.compute(&a).unwrap();
+        let mut value = serde_json::to_value(&cgs).unwrap();
+
+        let mut tampered = value.clone();
+        tampered["r"] = serde_json::to_value(Array2::<f64>::eye(2)).unwrap();
+        assert!(serde_json::from_value::<Classical>(tampered).is_err());
+
+        value["pinned"] = serde_json::to_value(4).unwrap();
+        assert!(serde_json::from_value::<Classical>(value).is_err());
+    }
+}
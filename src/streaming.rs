@@ -0,0 +1,161 @@
+use ndarray::Data;
+use ndarray::prelude::*;
+
+use crate::{
+    Error,
+    Result,
+    blas,
+    cholesky::cholesky_upper,
+};
+
+/// Maintains the R factor of a QR decomposition `A = QR` of a matrix A whose rows arrive one by
+/// one, without ever storing A or Q.
+///
+/// Every pushed row `x` updates the Gram matrix `AᵀA ← AᵀA + xxᵀ` with a symmetric rank one
+/// update (blas `dsyr`), after which R is rederived by a Cholesky decomposition `AᵀA = RᵀR`. The
+/// memory used is thus independent of the number of rows processed, which suits monitoring the
+/// rank and conditioning of an unbounded stream of observations.
+///
+/// While A is rank deficient (e.g. before as many independent rows as columns have been pushed),
+/// the rows of R belonging to columns that depend linearly on the previous ones are zero, and
+/// [`rank`] reports the number of independent columns. Note that working with `AᵀA` squares the
+/// condition number of A, so that R is less accurate than the one of an actual QR decomposition.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::StreamingR;
+/// use ndarray::arr1;
+///
+/// let mut streaming = StreamingR::new(2);
+/// streaming.push(&arr1(&[1.0, 2.0])).unwrap();
+/// assert_eq!(streaming.rank(), 1);
+///
+/// streaming.push(&arr1(&[0.0, 1.0])).unwrap();
+/// assert_eq!(streaming.rank(), 2);
+/// assert_eq!(streaming.rows_pushed(), 2);
+/// ```
+///
+/// [`rank`]: #method.rank
+#[derive(Clone, Debug)]
+pub struct StreamingR {
+    gram: Array2<f64>,
+    r: Array2<f64>,
+    rank: usize,
+    rows_pushed: usize,
+}
+
+impl StreamingR {
+    /// Creates an empty stream for matrices with `n_cols` columns.
+    pub fn new(n_cols: usize) -> Self {
+        Self {
+            gram: Array2::zeros((n_cols, n_cols)),
+            r: Array2::zeros((n_cols, n_cols)),
+            rank: 0,
+            rows_pushed: 0,
+        }
+    }
+
+    /// Appends the row `row` to the matrix A, and updates R and the rank accordingly.
+    ///
+    /// Returns an error if the length of `row` differs from the number of columns.
+    pub fn push<S>(&mut self, row: &ArrayBase<S, Ix1>) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        let n_cols = self.gram.cols();
+        if row.len() != n_cols {
+            Err(Error::LengthMismatch { expected: n_cols, found: row.len() })?;
+        }
+
+        let row = row.to_owned();
+        // NOTE: These unwraps are safe, because both arrays are owned and in standard layout.
+        let row_slice = row.as_slice().unwrap();
+        let gram_slice = self.gram.as_slice_mut().unwrap();
+
+        // Only the upper triangle of the Gram matrix is updated and read.
+        unsafe {
+            crate::blas::dsyr(
+                blas::Layout::RowMajor,
+                blas::Part::Upper,
+                n_cols as i32,
+                1.0,
+                row_slice,
+                1,
+                gram_slice,
+                n_cols as i32,
+            );
+        }
+
+        let tolerance = n_cols.max(1) as f64 * f64::EPSILON;
+        self.rank = cholesky_upper(&self.gram, &mut self.r, Some(tolerance))?;
+        self.rows_pushed += 1;
+        Ok(())
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<f64> {
+        &self.r
+    }
+
+    /// Returns the numerical rank of the rows pushed so far, i.e. the number of nonzero rows of R.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// Returns the number of rows pushed so far.
+    pub fn rows_pushed(&self) -> usize {
+        self.rows_pushed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::{
+        GramSchmidt,
+        Modified,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    #[test]
+    fn streamed_r_agrees_with_gram_schmidt() {
+        // The rows of the identity keep the rank exact while it is still deficient.
+        let a = ndarray::stack(Axis(0), &[Array2::eye(4).view(), matrix(2, 4).view()]).unwrap();
+        let mut streaming = StreamingR::new(4);
+        for (i, row) in a.genrows().into_iter().enumerate() {
+            streaming.push(&row).unwrap();
+            assert_eq!(streaming.rank(), (i + 1).min(4));
+        }
+        assert_eq!(streaming.rows_pushed(), 6);
+
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+        assert!(streaming.r().all_close(mgs.r(), 1e-12));
+    }
+
+    #[test]
+    fn dependent_columns_reduce_rank() {
+        let mut streaming = StreamingR::new(3);
+        for &x in [1.0, 2.0, -0.5, 3.0].iter() {
+            // The third column is the sum of the first two.
+            streaming.push(&arr1(&[x, 1.0, x + 1.0])).unwrap();
+        }
+        assert_eq!(streaming.rank(), 2);
+        assert!(streaming.r().row(2).iter().all(|&r_2j| r_2j == 0.0));
+    }
+
+    #[test]
+    fn wrong_length_is_rejected() {
+        let mut streaming = StreamingR::new(3);
+        match streaming.push(&arr1(&[1.0, 2.0])) {
+            Err(Error::LengthMismatch { expected: 3, found: 2 }) => {},
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+        assert_eq!(streaming.rows_pushed(), 0);
+    }
+}
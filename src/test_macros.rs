@@ -1,11 +1,16 @@
 macro_rules! generate_tests {
-    ($method:ident, $tolerance:expr) => {
+    (
+        $method:ident,
+        $tolerance:expr,
+        precisions: [$($precision:ident: $scalar:ty => $precision_tolerance:expr),*]
+    ) => {
         #[cfg(test)]
         mod tests {
             extern crate openblas_src;
 
             use lazy_static::lazy_static;
             use ndarray::prelude::*;
+            use ndarray::s;
             use super::*;
 
             lazy_static!(
@@ -82,7 +87,7 @@ macro_rules! generate_tests {
             fn small_orthogonal() {
                 let mut method = $method::from_matrix(&*SMALL).unwrap();
                 assert!(method.compute(&*SMALL).is_ok());
-                assert!(crate::utils::orthogonal(method.q(),$tolerance));
+                assert!(crate::diagnostics::is_orthogonal(method.q(), $tolerance));
             }
 
             #[test]
@@ -96,7 +101,7 @@ macro_rules! generate_tests {
             fn large_orthogonal() {
                 let mut method = $method::from_matrix(&*LARGE).unwrap();
                 assert!(method.compute(&*LARGE).is_ok());
-                assert!(crate::utils::orthogonal(method.q(),$tolerance));
+                assert!(crate::diagnostics::is_orthogonal(method.q(), $tolerance));
             }
 
             #[test]
@@ -118,7 +123,7 @@ macro_rules! generate_tests {
             fn f_order_small_orthogonal() {
                 let mut method = $method::from_matrix(&*F_SMALL).unwrap();
                 assert!(method.compute(&*F_SMALL).is_ok());
-                assert!(crate::utils::orthogonal(method.q(),$tolerance));
+                assert!(crate::diagnostics::is_orthogonal(method.q(), $tolerance));
             }
 
             #[test]
@@ -132,7 +137,7 @@ macro_rules! generate_tests {
             fn f_order_large_orthogonal() {
                 let mut method = $method::from_matrix(&*F_LARGE).unwrap();
                 assert!(method.compute(&*F_LARGE).is_ok());
-                assert!(crate::utils::orthogonal(method.q(),$tolerance));
+                assert!(crate::diagnostics::is_orthogonal(method.q(), $tolerance));
             }
 
             #[test]
@@ -141,6 +146,493 @@ macro_rules! generate_tests {
                 assert!(method.compute(&*F_LARGE).is_ok());
                 assert!(F_LARGE.all_close(&method.q().dot(method.r()), $tolerance));
             }
+
+            #[test]
+            fn sign_flips_are_recorded() {
+                let a = arr2(&[[1.0, 0.0], [1.0, 1.0], [1.0, 2.0]]);
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.compute(&a).unwrap();
+                assert!(method.provenance().iter().all(|column| !column.sign_flipped));
+
+                method.rescale_columns(&arr1(&[-1.0, 1.0])).unwrap();
+                method.force_positive_diagonal();
+                let sign_flipped: Vec<_> = method.provenance().iter().map(|column| column.sign_flipped).collect();
+                assert_eq!(sign_flipped, vec![true, false]);
+                assert!(a.all_close(&method.q().dot(method.r()), $tolerance));
+
+                // Computing anew determines the signs again.
+                method.compute(&a).unwrap();
+                assert!(method.provenance().iter().all(|column| !column.sign_flipped));
+            }
+
+            #[test]
+            fn flops_are_counted_once_computed() {
+                let a = arr2(&[[1.0, 0.0], [1.0, 1.0], [1.0, 2.0]]);
+                let mut method = $method::from_matrix(&a).unwrap();
+                assert_eq!(method.last_flops(), 0);
+
+                method.compute(&a).unwrap();
+                assert!(method.last_flops() > 0);
+            }
+
+            #[test]
+            fn configured_tolerance_detects_nearly_dependent_column() {
+                // The third column deviates from the sum of the first two by 1e-8.
+                let a = arr2(&[[1.0, 0.0, 1.0],
+                               [1.0, 1.0, 2.0 + 1e-8],
+                               [1.0, 2.0, 3.0],
+                               [1.0, 3.0, 4.0]]);
+                let mut method = $method::from_matrix(&a).unwrap();
+                assert_eq!(method.tolerance(), 4.0 * f64::EPSILON);
+                assert!(method.compute(&a).is_ok());
+
+                let mut loose = $method::from_matrix(&a).unwrap().with_tolerance(1e-6);
+                assert_eq!(loose.tolerance(), 1e-6);
+                match loose.compute(&a) {
+                    Err(Error::RankDeficient { column: 2 }) => {},
+                    other => panic!("expected a rank deficient matrix, got {:?}", other),
+                }
+            }
+
+
+            #[test]
+            fn negative_columns_have_positive_diagonal() {
+                let mut method = $method::from_matrix(&arr2(&[[-2.0]])).unwrap();
+                method.compute(&arr2(&[[-2.0]])).unwrap();
+                assert_eq!(method.q(), &arr2(&[[-1.0]]));
+                assert_eq!(method.r(), &arr2(&[[2.0]]));
+
+                let a = arr2(&[[-3.0], [0.0], [-4.0]]);
+                let mut f_a = Array2::zeros((3, 1).f());
+                f_a.assign(&a);
+                for a in &[a, f_a] {
+                    for &transpose_r in [false, true].iter() {
+                        let mut method = $method::from_shape_with_r_transposed(
+                            a.dim().set_f(!a.is_standard_layout()),
+                            transpose_r,
+                        ).unwrap();
+                        method.compute(a).unwrap();
+                        assert!(method.r().all_close(&arr2(&[[5.0]]), 1e-15));
+                        assert!(method.q().all_close(&arr2(&[[-0.6], [0.0], [-0.8]]), 1e-15));
+                        assert!(a.all_close(&method.q().dot(method.r()), 1e-15));
+                    }
+                }
+
+                let mut a = LARGE.clone();
+                a.column_mut(0).mapv_inplace(|x| -x.abs() - 1.0);
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.compute(&a).unwrap();
+                assert!(method.r().diag().iter().all(|&r_ii| r_ii > 0.0));
+                assert!(method.q().column(0).iter().all(|&q_i| q_i < 0.0));
+                assert!(a.all_close(&method.q().dot(method.r()), 1e-12));
+            }
+
+            #[test]
+            fn transposed_r_matches_untransposed_r() {
+                for a in &[&*LARGE, &*F_LARGE] {
+                    let column_major = !a.is_standard_layout();
+                    let mut method = $method::from_shape_with_r_transposed(a.dim().set_f(column_major), true).unwrap();
+                    assert_eq!(method.r().is_standard_layout(), column_major);
+                    method.compute(*a).unwrap();
+                    assert!(a.all_close(&method.q().dot(method.r()), 1e-12));
+
+                    let mut reference = $method::from_matrix(*a).unwrap();
+                    reference.compute(*a).unwrap();
+                    assert!(method.r().all_close(reference.r(), 1e-12));
+                }
+            }
+
+            #[test]
+            fn rank_deficiency_is_reported() {
+                // The third column equals the first.
+                let a = arr2(
+                    &[[2.0, 0.5, 2.0],
+                      [0.0, 0.3, 0.0],
+                      [1.0, 1.0, 1.0],
+                      [0.5, 0.0, 0.5]]
+                );
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.set_tolerance(1e-10);
+                match method.compute(&a) {
+                    Err(Error::RankDeficient { column: 2 }) => {},
+                    other => panic!("expected a rank deficient column 2, got {:?}", other),
+                }
+
+                // An exactly vanishing column is rejected even without a tolerance.
+                let mut a = a;
+                a.column_mut(1).fill(0.0);
+                method.set_tolerance(0.0);
+                match method.compute(&a) {
+                    Err(Error::RankDeficient { column: 1 }) => {},
+                    other => panic!("expected a rank deficient column 1, got {:?}", other),
+                }
+            }
+
+            #[test]
+            fn rescale_columns_preserves_reconstruction() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                method.compute(&*LARGE).unwrap();
+                let weights = arr1(&[1.0, -2.0, 0.5, 3.0, -0.25, 10.0]);
+                assert!(method.rescale_columns(&weights).is_ok());
+                assert!(LARGE.all_close(&method.q().dot(method.r()), 1e-12));
+                let q_norms = method.q().gencolumns().into_iter().map(|c| c.dot(&c).sqrt()).collect::<Array1<_>>();
+                assert!(q_norms.all_close(&weights.mapv(f64::abs), 1e-12));
+            }
+
+            #[test]
+            fn rescale_columns_rejects_invalid_weights() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                method.compute(&*LARGE).unwrap();
+                let q = method.q().clone();
+                match method.rescale_columns(&arr1(&[1.0, 2.0])) {
+                    Err(Error::LengthMismatch { expected: 6, found: 2 }) => {},
+                    other => panic!("expected a length mismatch, got {:?}", other),
+                }
+                match method.rescale_columns(&arr1(&[1.0, 2.0, 3.0, 0.0, 1.0, 1.0])) {
+                    Err(Error::InvalidWeight { index: 3 }) => {},
+                    other => panic!("expected an invalid weight, got {:?}", other),
+                }
+                assert_eq!(&q, method.q());
+            }
+
+            #[test]
+            fn force_positive_diagonal_restores_convention() {
+                for a in &[&*LARGE, &*F_LARGE] {
+                    let mut method = $method::from_matrix(*a).unwrap();
+                    method.compute(*a).unwrap();
+                    let (q, r) = (method.q().clone(), method.r().clone());
+
+                    method.rescale_columns(&arr1(&[-1.0, 1.0, -1.0, -1.0, 1.0, -1.0])).unwrap();
+                    method.force_positive_diagonal();
+                    assert!(method.q().all_close(&q, 1e-15));
+                    assert!(method.r().all_close(&r, 1e-15));
+
+                    let u = Array2::from_shape_fn((6, 1), |(i, _)| (i as f64).sin());
+                    let v = Array2::from_shape_fn((6, 1), |(i, _)| -100.0 * (i as f64).cos());
+                    let updated = *a + &u.dot(&v.t());
+                    method.low_rank_update(&u, &v).unwrap();
+                    method.force_positive_diagonal();
+                    assert!(method.r().diag().iter().all(|&r_ii| r_ii >= 0.0));
+                    assert!(updated.all_close(&method.q().dot(method.r()), 1e-10));
+                }
+            }
+
+            #[test]
+            fn from_parts_rejects_mismatched_r() {
+                match $method::from_parts(LARGE.clone(), Array2::zeros((5, 5))) {
+                    Err(Error::ShapeMismatch { expected: (6, 6), found: (5, 5) }) => {},
+                    other => panic!("expected a shape mismatch, got {:?}", other.map(|_| ())),
+                }
+            }
+
+            #[test]
+            fn wrong_shape_is_rejected() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                for a in [SMALL.view(), LARGE.slice(s![.., ..4]), LARGE.slice(s![..5, ..])].iter() {
+                    match method.compute(a) {
+                        Err(Error::ShapeMismatch { expected: (6, 6), found }) => assert_eq!(found, a.dim()),
+                        other => panic!("expected a shape mismatch, got {:?}", other),
+                    }
+                }
+                method.compute(&*LARGE).unwrap();
+            }
+
+            #[test]
+            fn wide_matrix_is_underdetermined() {
+                let a = Array2::<f64>::zeros((3, 5));
+                match $method::from_matrix(&a) {
+                    Err(Error::Underdetermined { rows: 3, cols: 5 }) => {},
+                    other => panic!("expected an underdetermined matrix, got {:?}", other.map(|_| ())),
+                }
+                match $method::from_parts(a, Array2::zeros((5, 5))) {
+                    Err(Error::Underdetermined { rows: 3, cols: 5 }) => {},
+                    other => panic!("expected an underdetermined matrix, got {:?}", other.map(|_| ())),
+                }
+            }
+
+            #[test]
+            fn degenerate_shapes_are_rejected() {
+                for &(rows, cols) in [(0, 5), (5, 0), (0, 0)].iter() {
+                    for &column_major in [false, true].iter() {
+                        match $method::<f64>::from_shape((rows, cols).set_f(column_major)) {
+                            Err(Error::InvalidShape { rows: r, cols: c }) if (r, c) == (rows, cols) => {},
+                            other => panic!("expected an invalid shape, got {:?}", other.map(|_| ())),
+                        }
+                    }
+                }
+
+                let mut method = $method::from_matrix(&*SMALL).unwrap();
+                match method.reset_shape((3, 0)) {
+                    Err(Error::InvalidShape { rows: 3, cols: 0 }) => {},
+                    other => panic!("expected an invalid shape, got {:?}", other),
+                }
+                assert_eq!(method.q().dim(), (4, 4));
+            }
+
+            #[test]
+            fn reset_shape_reuses_procedure() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                method.compute(&*LARGE).unwrap();
+
+                // Shrinking reuses the allocation of Q.
+                let q_ptr = method.q().as_ptr();
+                method.reset_shape((4, 4)).unwrap();
+                assert_eq!(method.q().as_ptr(), q_ptr);
+                method.compute(&*SMALL).unwrap();
+                assert!(SMALL.all_close(&method.q().dot(method.r()), 1e-12));
+
+                // The memory layout is derived from the new shape.
+                method.reset_shape((4, 4).f()).unwrap();
+                method.compute(&*F_SMALL).unwrap();
+                assert!(F_SMALL.all_close(&method.q().dot(method.r()), 1e-12));
+
+                match method.reset_shape((3, 5)) {
+                    Err(Error::Underdetermined { rows: 3, cols: 5 }) => {},
+                    other => panic!("expected an underdetermined matrix, got {:?}", other),
+                }
+                assert_eq!(method.q().dim(), (4, 4));
+            }
+
+            #[test]
+            fn broadcast_input_is_rejected() {
+                let row = arr1(&[1.0, 2.0, 3.0, 4.0]);
+                let broadcast = row.broadcast((4, 4)).unwrap();
+                match $method::from_matrix(&broadcast) {
+                    Err(Error::BroadcastInput) => {},
+                    other => panic!("expected a broadcast input error, got {:?}", other.map(|_| ())),
+                }
+                match $method::prepared(&broadcast) {
+                    Err(Error::BroadcastInput) => {},
+                    other => panic!("expected a broadcast input error, got {:?}", other.map(|_| ())),
+                }
+
+                let mut method = $method::from_matrix(&*UNITY).unwrap();
+                match method.compute(&broadcast) {
+                    Err(Error::BroadcastInput) => {},
+                    other => panic!("expected a broadcast input error, got {:?}", other),
+                }
+            }
+
+            #[test]
+            fn non_finite_input_is_rejected() {
+                for a in &[&*SMALL, &*F_SMALL] {
+                    for &value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY].iter() {
+                        let mut b = (*a).clone();
+                        b[(3, 2)] = value;
+                        b[(2, 1)] = value;
+                        let mut method = $method::from_matrix(&b).unwrap();
+                        match method.compute(&b) {
+                            Err(Error::NonFinite { row: 2, col: 1 }) => {},
+                            other => panic!("expected a non finite entry, got {:?}", other),
+                        }
+
+                        method.set_skip_finite_check(true);
+                        assert!(method.skips_finite_check());
+                        if let Err(Error::NonFinite { .. }) = method.compute(&b) {
+                            panic!("the check was not skipped");
+                        }
+                    }
+                }
+            }
+
+            #[test]
+            fn budgeted_compute_with_passed_deadline_advances_one_column() {
+                let deadline = Instant::now();
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                let mut status = method.compute_budgeted(&*LARGE, deadline).unwrap();
+                let mut steps = 1;
+                while let ComputeStatus::InProgress { next_col } = status {
+                    assert_eq!(next_col, steps);
+                    status = method.continue_from(&*LARGE, next_col, deadline).unwrap();
+                    steps += 1;
+                }
+                assert_eq!(steps, LARGE.cols());
+                assert!(LARGE.all_close(&method.q().dot(method.r()), 1e-12));
+            }
+
+            #[test]
+            fn observer_stops_and_computation_continues() {
+                let deadline = Instant::now() + std::time::Duration::from_secs(3600);
+
+                for a in &[&*LARGE, &*F_LARGE] {
+                    let mut reference = $method::from_matrix(*a).unwrap();
+                    reference.compute(*a).unwrap();
+
+                    let mut method = $method::from_matrix(*a).unwrap();
+                    let mut observed = Vec::new();
+                    let status = method.compute_observed(*a, |j, norm| {
+                        observed.push((j, norm));
+                        j < 2
+                    }).unwrap();
+                    assert_eq!(status, ComputeStatus::InProgress { next_col: 3 });
+                    let expected: Vec<_> = reference.r().diag().iter().cloned().enumerate().take(3).collect();
+                    assert_eq!(observed, expected);
+
+                    assert_eq!(method.continue_from(*a, 3, deadline).unwrap(), ComputeStatus::Done);
+                    assert!(a.all_close(&method.q().dot(method.r()), 1e-12));
+                }
+            }
+
+            #[test]
+            fn pinned_columns_are_observed_with_unit_norm() {
+                let a = arr2(&[[1.0, 0.5], [0.0, 0.3], [0.0, 1.0], [0.0, 0.0]]);
+
+                let mut observed = Vec::new();
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.set_pinned(1).unwrap();
+                let status = method.compute_observed(&a, |j, norm| { observed.push((j, norm)); true }).unwrap();
+                assert_eq!(status, ComputeStatus::Done);
+                assert_eq!(observed[0], (0, 1.0));
+                assert_eq!(observed.len(), 2);
+
+                // A truncation after the pinned columns does not orthonormalize any other column.
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.set_pinned(1).unwrap();
+                assert_eq!(method.compute_truncated(&a, 1, 1e-10).unwrap(), 1);
+                assert!(method.q().column(1).iter().all(|&q_i| q_i == 0.0));
+            }
+
+            #[test]
+            fn mutation_through_views_is_visible() {
+                for a in &[&*SMALL, &*F_SMALL] {
+                    let mut method = $method::from_matrix(*a).unwrap();
+                    method.compute(*a).unwrap();
+                    let (q, r) = (method.q().clone(), method.r().clone());
+
+                    method.q_mut().column_mut(2).mapv_inplace(|q_i| -q_i);
+                    method.r_mut().row_mut(2).mapv_inplace(|r_i| -r_i);
+                    assert_eq!(method.q().column(2), -&q.column(2));
+                    assert_eq!(method.r().row(2), -&r.row(2));
+                    assert_eq!(method.q().column(1), q.column(1));
+                    assert!(a.all_close(&method.q().dot(method.r()), 1e-12));
+                }
+            }
+
+            #[test]
+            fn pinned_columns_are_kept() {
+                for a in &[&*LARGE, &*F_LARGE] {
+                    let mut method = $method::from_matrix(*a).unwrap();
+                    method.compute(*a).unwrap();
+
+                    // An orthonormal pair which differs from the one found by the procedure.
+                    let mut pinned_a = (*a).clone();
+                    let q = method.q();
+                    pinned_a.column_mut(0).assign(&(&q.column(1) * -1.0));
+                    pinned_a.column_mut(1).assign(&q.column(0));
+
+                    method.set_pinned(2).unwrap();
+                    assert_eq!(method.pinned(), 2);
+                    method.compute(&pinned_a).unwrap();
+                    assert_eq!(method.q().column(0), pinned_a.column(0));
+                    assert_eq!(method.q().column(1), pinned_a.column(1));
+                    assert!(pinned_a.all_close(&method.q().dot(method.r()), 1e-12));
+                    assert!(crate::utils::orthogonality_loss(method.q()) < 1e-12);
+                }
+            }
+
+            #[test]
+            fn non_orthonormal_pinned_columns_fail() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                method.set_pinned(2).unwrap();
+                match method.compute(&*LARGE) {
+                    Err(Error::NotOrthonormal { column: 0 }) => {},
+                    other => panic!("expected a non-orthonormal pinned column, got {:?}", other),
+                }
+            }
+
+            #[test]
+            fn too_many_pinned_columns_are_rejected() {
+                let mut method = $method::from_matrix(&*LARGE).unwrap();
+                method.set_pinned(6).unwrap();
+                match method.set_pinned(7) {
+                    Err(Error::TooManyPinned { pinned: 7, cols: 6 }) => {},
+                    other => panic!("expected too many pinned columns, got {:?}", other),
+                }
+                assert_eq!(method.pinned(), 6);
+            }
+
+            #[test]
+            fn provenance_follows_input_columns() {
+                let mut a = LARGE.clone();
+                let first = a.column(0).to_owned();
+                a.column_mut(0).assign(&(&first / first.dot(&first).sqrt()));
+                let mut method = $method::from_matrix(&a).unwrap();
+                method.set_pinned(1).unwrap();
+                method.compute(&a).unwrap();
+
+                let provenance = method.provenance();
+                assert_eq!(provenance.len(), a.cols());
+                for (j, column) in provenance.iter().enumerate() {
+                    assert_eq!(column.input_column, j);
+                    assert_eq!(column.pinned, j == 0);
+                    assert!(!column.sign_flipped);
+                }
+                assert!(!provenance[0].reorthogonalized);
+            }
+
+            $(
+                /// The tests which only rely on the generic parts of the procedure, run in the
+                /// precision `$scalar` on the fixtures above cast to it.
+                mod $precision {
+                    use super::*;
+
+                    type Scalar = $scalar;
+
+                    const TOLERANCE: Scalar = $precision_tolerance;
+
+                    /// Returns `a` cast to `Scalar`, keeping its memory layout.
+                    fn cast(a: &Array2<f64>) -> Array2<Scalar> {
+                        a.mapv(|x| x as Scalar)
+                    }
+
+                    fn orthonormal(q: &Array2<Scalar>) -> bool {
+                        q.t().dot(q).all_close(&Array2::eye(q.cols()), TOLERANCE)
+                    }
+
+                    /// Returns the procedure after factorizing `a`.
+                    fn factorized(a: &Array2<Scalar>) -> $method<Scalar> {
+                        let mut method = $method::from_matrix(a).unwrap();
+                        method.compute(a).unwrap();
+                        method
+                    }
+
+                    #[test]
+                    fn fixtures_are_factorized() {
+                        for a in &[cast(&*UNITY), cast(&*F_UNITY)] {
+                            let method = factorized(a);
+                            assert_eq!(a, &method.q().dot(method.r()));
+                        }
+                        for a in &[cast(&*SMALL), cast(&*F_SMALL), cast(&*LARGE), cast(&*F_LARGE)] {
+                            let method = factorized(a);
+                            assert!(orthonormal(method.q()));
+                            assert!(a.all_close(&method.q().dot(method.r()), TOLERANCE));
+                        }
+                    }
+
+                    #[test]
+                    fn tall_qr_returns_original() {
+                        use ndarray_rand::RandomExt;
+                        use rand::{
+                            SeedableRng,
+                            distributions::Normal,
+                            rngs::StdRng,
+                        };
+
+                        let mut rng = StdRng::seed_from_u64(20);
+                        let a = cast(&Array2::random_using((20, 7), Normal::new(0.0, 1.0), &mut rng));
+                        let mut f_a = Array2::zeros((20, 7).f());
+                        f_a.assign(&a);
+
+                        for a in &[a, f_a] {
+                            let method = factorized(a);
+                            assert_eq!(method.q().dim(), (20, 7));
+                            assert_eq!(method.r().dim(), (7, 7));
+                            assert!(orthonormal(method.q()));
+                            assert!(a.all_close(&method.q().dot(method.r()), TOLERANCE));
+                        }
+                    }
+                }
+            )*
         }
     }
 }
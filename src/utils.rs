@@ -1,38 +1,810 @@
-use ndarray::Data;
+use ndarray::{
+    Data,
+    s,
+};
 use ndarray::prelude::*;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::slice;
+use std::time::Instant;
 
+use num_traits::{
+    Float,
+    Zero,
+};
+
+use crate::{
+    GramSchmidtScalar,
+    blas,
+};
+
+/// Returns the `n×n` Hilbert matrix with entries `1/(i+j+1)`, a standard ill-conditioned test
+/// matrix.
+#[cfg(test)]
+pub(crate) fn hilbert(n: usize) -> Array2<f64> {
+    Array2::from_shape_fn((n, n), |(i, j)| 1.0 / (i + j + 1) as f64)
+}
+
+/// Returns the `n×n` identity perturbed by Gaussian noise with standard deviation `epsilon`. The
+/// noise is seeded by `n`, so that the matrix is reproducible.
+#[cfg(test)]
+pub(crate) fn identity_plus_noise(n: usize, epsilon: f64) -> Array2<f64> {
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+
+    let mut rng = StdRng::seed_from_u64(n as u64);
+    Array2::eye(n) + Array2::random_using((n, n), Normal::new(0.0, epsilon), &mut rng)
+}
+
+/// Returns an `m×n` matrix with standard normal entries, which has full rank and is well
+/// conditioned for tall shapes. The entries are seeded by the shape, so that the matrix is
+/// reproducible.
+#[cfg(test)]
+pub(crate) fn matrix(m: usize, n: usize) -> Array2<f64> {
+    use ndarray_rand::RandomExt;
+    use rand::{
+        SeedableRng,
+        distributions::Normal,
+        rngs::StdRng,
+    };
+
+    let mut rng = StdRng::seed_from_u64((m * n) as u64);
+    Array2::random_using((m, n), Normal::new(0.0, 1.0), &mut rng)
+}
+
+/// Returns a proptest strategy for tall matrices with at most `max_rows` rows, stored in either
+/// layout, whose condition number is at most `kappa > 1`.
+///
+/// The matrices are `E + s·I` for entries of `E` drawn from `[-1, 1]`. Since `‖E‖₂ ≤ ‖E‖_F ≤ ρ`
+/// with `ρ = √(mn)`, the shift `s = ρ(κ + 1)/(κ - 1)` bounds the singular values to
+/// `[s - ρ, s + ρ]`, and thus the condition number to κ. Shrinking a failing case reduces its
+/// shape and moves the entries of `E` towards zero, i.e. the matrix towards the identity.
+#[cfg(test)]
+pub(crate) fn conditioned_matrix(max_rows: usize, kappa: f64) -> impl proptest::strategy::Strategy<Value = Array2<f64>> {
+    use proptest::prelude::*;
+
+    (1..=max_rows)
+        .prop_flat_map(|n_rows| (Just(n_rows), 1..=n_rows))
+        .prop_flat_map(|(n_rows, n_cols)| (
+            Just((n_rows, n_cols)),
+            proptest::collection::vec(-1.0..=1.0, n_rows * n_cols),
+            any::<bool>(),
+        ))
+        .prop_map(move |(dim, elements, column_major)| {
+            // NOTE: This unwrap is safe, because there is an element for every entry.
+            let mut a = Array2::from_shape_vec(dim.set_f(column_major), elements).unwrap();
+            let radius = ((dim.0 * dim.1) as f64).sqrt();
+            a.diag_mut().map_inplace(|a_ii| *a_ii += radius * (kappa + 1.0) / (kappa - 1.0));
+            a
+        })
+}
+
+/// Returns the loss of orthogonality `‖I - QᵀQ‖_F` of the matrix `q`.
 #[cfg(test)]
-pub(crate) fn orthogonal<S>(a: &ArrayBase<S,Ix2>, tol: f64) -> bool
+pub(crate) fn orthogonality_loss<S>(q: &ArrayBase<S, Ix2>) -> f64
     where S: Data<Elem=f64>
 {
-    let b = a.dot(&a.t());
-    b.all_close(&Array2::eye(b.shape()[0]), tol)
+    let deviation = Array2::<f64>::eye(q.cols()) - q.t().dot(q);
+    frobenius_norm(&deviation)
+}
+
+/// Checks that a procedure created by `accepting_any_layout` factorizes matrices stored in the
+/// layout opposite to the configured one like a procedure configured for their own layout, while
+/// one created by `from_shape` rejects them.
+#[cfg(test)]
+pub(crate) fn check_mixed_layouts<G, F>(accepting_any_layout: F)
+    where G: crate::GramSchmidt<Scalar = f64>,
+          F: Fn(ndarray::Shape<Ix2>) -> crate::Result<G>,
+{
+    let a = arr2(
+        &[[2.0, 0.5, 0.0],
+          [0.0, 0.3, 0.0],
+          [0.0, 1.0, 0.7],
+          [1.0, 0.0, 3.0]]
+    );
+    let mut f_a = Array2::zeros(a.dim().f());
+    f_a.assign(&a);
+
+    // Procedures configured for column and row major layouts respectively, fed the other layout.
+    for &(shape, input) in &[(a.dim().f(), &a), (a.dim().into_shape(), &f_a)] {
+        let mut strict = G::from_shape(shape).unwrap();
+        match strict.compute(input) {
+            Err(crate::Error::IncompatibleLayouts) => {},
+            other => panic!("expected incompatible layouts, got {:?}", other),
+        }
+
+        let mut expected = G::from_matrix(input).unwrap();
+        expected.compute(input).unwrap();
+        let mut mixed = accepting_any_layout(shape).unwrap();
+        mixed.compute(input).unwrap();
+        assert_ne!(get_layout(mixed.q()), get_layout(input));
+        assert!(mixed.q().all_close(expected.q(), 1e-14));
+        assert!(mixed.r().all_close(expected.r(), 1e-14));
+    }
+}
+
+/// Checks that the procedure `G` factorizes a nearly orthonormal matrix by normalizing its
+/// columns once `set_threshold` enabled the shortcut, and that it otherwise factorizes as usual.
+#[cfg(test)]
+pub(crate) fn check_orthonormal_shortcut<G>(set_threshold: fn(&mut G, f64))
+    where G: crate::GramSchmidt<Scalar = f64>
+{
+    let a = matrix(8, 6);
+    let basis = crate::cgs2_qr(&a).unwrap().into_tuple().0;
+    let perturbation = Array2::from_shape_fn(basis.dim(), |(i, j)| 1e-10 * ((i * 7 + j) as f64).sin());
+    let nearly_orthonormal = &basis + &perturbation;
+
+    let mut method = G::from_matrix(&a).unwrap();
+    set_threshold(&mut method, 1e-8);
+    method.compute(&nearly_orthonormal).unwrap();
+    let norms = nearly_orthonormal.map_axis(Axis(0), |column| column.dot(&column).sqrt());
+    assert!(method.q().all_close(&(&nearly_orthonormal / &norms), 1e-15));
+    assert!(method.r().diag().all_close(&norms, 1e-15));
+    assert!(method.r().indexed_iter().all(|((i, j), &r_ij)| i == j || r_ij == 0.0));
+    assert!(nearly_orthonormal.all_close(&method.q().dot(method.r()), 1e-15));
+
+    // The matrix itself is far from orthonormal, so that it is factorized as usual.
+    let reference = factorized::<G>(&a);
+    method.compute(&a).unwrap();
+    assert_eq!(method.q(), reference.q());
+    assert_eq!(method.r(), reference.r());
+}
+
+/// Returns the procedure `G` after factorizing `a`.
+#[cfg(test)]
+fn factorized<G>(a: &Array2<f64>) -> G
+    where G: crate::GramSchmidt<Scalar = f64>
+{
+    let mut method = G::from_matrix(a).unwrap();
+    method.compute(a).unwrap();
+    method
+}
+
+/// Checks that the loss of orthogonality of the Q computed by `G` from the Hilbert matrix of each
+/// size `n` in `bounds`, stored in either layout, is below the corresponding bound, and that Q·R
+/// still reconstructs the matrix.
+///
+/// The Hilbert matrix is notoriously ill-conditioned, so that the achievable orthogonality
+/// documents the numerical stability of each procedure.
+#[cfg(test)]
+pub(crate) fn check_hilbert_orthogonality<G>(bounds: &[(usize, f64)])
+    where G: crate::GramSchmidt<Scalar = f64>
+{
+    for &(n, bound) in bounds {
+        let mut f_a = Array2::zeros((n, n).f());
+        f_a.assign(&hilbert(n));
+
+        for a in &[hilbert(n), f_a] {
+            let method = factorized::<G>(a);
+            let loss = orthogonality_loss(method.q());
+            assert!(loss < bound, "n = {}: loss of orthogonality {:e} exceeds {:e}", n, loss, bound);
+            assert!(a.all_close(&method.q().dot(method.r()), 1e-14));
+        }
+    }
+}
+
+/// Checks that the loss of orthogonality of the Q computed by `G` from the identity perturbed by
+/// increasing noise is below `bound(κ)` for the condition number κ of the perturbed matrix.
+///
+/// The noise increases the condition number, and the loss of orthogonality is expected to grow
+/// like a procedure specific power of it.
+#[cfg(test)]
+pub(crate) fn check_identity_plus_noise_orthogonality<G, F>(bound: F)
+    where G: crate::GramSchmidt<Scalar = f64>,
+          F: Fn(f64) -> f64,
+{
+    use crate::GramSchmidt;
+
+    let n = 20;
+    for &epsilon in [1e-6, 1e-3, 1e-1, 1.0, 10.0].iter() {
+        let a = identity_plus_noise(n, epsilon);
+
+        // The upper bound κ(A) ≤ ‖A‖_F·‖A⁻¹‖_F of the condition number, with
+        // ‖A⁻¹‖_F² = trace((AᵀA)⁻¹).
+        let reference = factorized::<crate::Reorthogonalized<f64>>(&a);
+        let inverse_norm = reference.normal_equations_inverse().unwrap().diag().sum().sqrt();
+        let kappa = frobenius_norm(&a) * inverse_norm;
+
+        let loss = orthogonality_loss(factorized::<G>(&a).q());
+        let bound = bound(kappa);
+        assert!(loss < bound, "ε = {:e}: loss of orthogonality {:e} exceeds {:e}", epsilon, loss, bound);
+    }
+}
+
+/// Checks that `G` factorizes random matrices of varying shapes and layouts up to the relative
+/// residual `tolerance`, and with a loss of orthogonality below `bound(κ)` for their bounded
+/// condition number κ.
+///
+/// These catch stride and layout bugs which fixed matrices miss.
+#[cfg(test)]
+pub(crate) fn check_conditioned_matrices<G, F>(tolerance: f64, bound: F)
+    where G: crate::GramSchmidt<Scalar = f64>,
+          F: Fn(f64) -> f64,
+{
+    let kappa = 10.0;
+    let mut runner = proptest::test_runner::TestRunner::default();
+    let result = runner.run(&conditioned_matrix(12, kappa), |a| {
+        let method = factorized::<G>(&a);
+
+        let residual = &a - &method.q().dot(method.r());
+        let error = frobenius_norm(&residual) / frobenius_norm(&a);
+        proptest::prop_assert!(error < tolerance, "relative residual {:e}", error);
+
+        let loss = orthogonality_loss(method.q());
+        let bound = bound(kappa);
+        proptest::prop_assert!(loss < bound, "loss of orthogonality {:e} exceeds {:e}", loss, bound);
+        Ok(())
+    });
+    if let Err(err) = result {
+        panic!("{}", err);
+    }
+}
+
+/// Returns the provenance of the columns in their original order, one for every entry of
+/// `sign_flipped`, of which the first `pinned` ones were pinned and those for which
+/// `reorthogonalized` is true were orthogonalized twice.
+pub(crate) fn provenance<F>(pinned: usize, sign_flipped: &[bool], reorthogonalized: F) -> Vec<crate::ColumnProvenance>
+    where F: Fn(usize) -> bool
+{
+    sign_flipped.iter().enumerate()
+        .map(|(j, &sign_flipped)| crate::ColumnProvenance {
+            input_column: j,
+            pinned: j < pinned,
+            reorthogonalized: reorthogonalized(j),
+            sign_flipped,
+        })
+        .collect()
+}
+
+/// Forgets the sign flips of the columns `start..`, which are about to be computed anew.
+pub(crate) fn clear_sign_flips(sign_flipped: &mut [bool], start: usize) {
+    sign_flipped.iter_mut().skip(start).for_each(|sign_flipped| *sign_flipped = false);
+}
+
+/// Returns [`Error::TooManyPinned`](crate::Error::TooManyPinned) if more than `n_cols` columns
+/// are to be pinned.
+pub(crate) fn check_pinned(n_cols: usize, pinned: usize) -> crate::Result<()> {
+    if pinned > n_cols {
+        Err(crate::Error::TooManyPinned { pinned, cols: n_cols })?;
+    }
+    Ok(())
+}
+
+/// The largest deviation of `⟨q_i, q_j⟩` from `δ_ij` tolerated for pinned columns.
+pub(crate) const PINNED_TOLERANCE: f64 = 1e-10;
+
+/// Copies the columns `0..pinned` of `a` unchanged into `q`, and sets the corresponding columns
+/// of `r` to those of the identity. Returns an error if these columns of `a` are not orthonormal
+/// within [`PINNED_TOLERANCE`], in which case `q` and `r` are left untouched.
+pub(crate) fn pin_columns<S, T>(
+    q: &mut Array2<T>,
+    r: &mut Array2<T>,
+    a: &ArrayBase<S, Ix2>,
+    pinned: usize,
+) -> crate::Result<()>
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    let basis = a.slice(s![.., ..pinned]);
+    let gram = T::gram(basis.view());
+    for j in 0..pinned {
+        for i in 0..=j {
+            let expected = if i == j { T::one() } else { T::zero() };
+            let deviation = T::to_f64((gram[(i, j)] - expected).modulus());
+            if deviation > PINNED_TOLERANCE || deviation.is_nan() {
+                Err(crate::Error::NotOrthonormal { column: j })?;
+            }
+        }
+    }
+
+    q.slice_mut(s![.., ..pinned]).assign(&basis);
+    let mut r_pinned = r.slice_mut(s![.., ..pinned]);
+    r_pinned.fill(T::zero());
+    r_pinned.diag_mut().fill(T::one());
+    Ok(())
+}
+
+/// Factorizes `a` without any projections if its columns are already orthonormal up to
+/// `threshold`, i.e. if `‖I - AᴴA‖_F < threshold`: `q` is set to the normalized columns of `a`,
+/// and `r` to the diagonal matrix of their norms. Returns whether the shortcut was taken;
+/// otherwise, `q` and `r` are left untouched. A threshold of zero never takes the shortcut.
+pub(crate) fn orthonormal_shortcut<S, T>(
+    q: &mut Array2<T>,
+    r: &mut Array2<T>,
+    a: &ArrayBase<S, Ix2>,
+    threshold: f64,
+) -> bool
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    if threshold <= 0.0 {
+        return false;
+    }
+
+    let gram = T::gram(a.view());
+    let squared_error: f64 = gram.indexed_iter()
+        .map(|((i, j), &g_ij)| {
+            let expected = if i == j { T::one() } else { T::zero() };
+            T::to_f64((g_ij - expected).modulus()).powi(2)
+        })
+        .sum();
+    let error = squared_error.sqrt();
+    if error >= threshold || error.is_nan() {
+        return false;
+    }
+
+    q.assign(a);
+    r.fill(T::zero());
+    for (j, mut q_column) in q.gencolumns_mut().into_iter().enumerate() {
+        let norm = T::from_real(normalization(&q_column));
+        q_column /= norm;
+        r[(j, j)] = norm;
+    }
+    true
+}
+
+/// Returns the default tolerance `max(m, n)·ε` of the procedures for an `m×n` matrix, below which
+/// a column is considered to be linearly dependent on the previous ones, see
+/// [`GramSchmidt::set_tolerance`](crate::GramSchmidt::set_tolerance).
+pub(crate) fn default_tolerance<T: GramSchmidtScalar>(n_rows: usize, n_cols: usize) -> f64 {
+    n_rows.max(n_cols) as f64 * T::to_f64(T::Real::epsilon())
+}
+
+/// Returns an error if `norm`, the norm of the `column`-th column after removing its projections
+/// onto the previous columns, is zero, not a number, or at most `tolerance` times the norm of the
+/// original column. The latter is only computed by `column_norm` if `tolerance` is positive.
+pub(crate) fn check_rank<T, F>(column: usize, norm: T, tolerance: f64, column_norm: F) -> crate::Result<()>
+    where T: GramSchmidtScalar<Real = T>,
+          F: FnOnce() -> T,
+{
+    let norm = T::to_f64(norm);
+    let bound = if tolerance > 0.0 {
+        tolerance * T::to_f64(column_norm())
+    } else {
+        0.0
+    };
+    if norm <= bound || norm.is_nan() {
+        Err(crate::Error::RankDeficient { column })?;
+    }
+    Ok(())
+}
+
+/// Returns the number of floating point operations to orthonormalize the columns described by
+/// `provenance` of a matrix with `n_rows` rows, see
+/// [`GramSchmidt::last_flops`](crate::GramSchmidt::last_flops).
+pub(crate) fn gram_schmidt_flops(n_rows: usize, provenance: &[crate::ColumnProvenance]) -> u64 {
+    let n_rows = n_rows as u64;
+    provenance.iter()
+        .enumerate()
+        .filter(|(_, column)| !column.pinned)
+        .map(|(j, column)| {
+            let passes = if column.reorthogonalized { 2 } else { 1 };
+            passes * 4 * n_rows * j as u64 + 3 * n_rows
+        })
+        .sum()
+}
+
+/// Returns the floating point operations of the last computation, scaled by `cost_factor`, or
+/// zero if nothing was `computed` since the procedure was created or reshaped.
+pub(crate) fn last_flops(computed: bool, n_rows: usize, provenance: &[crate::ColumnProvenance], cost_factor: f64) -> u64 {
+    if !computed {
+        return 0;
+    }
+    (cost_factor * gram_schmidt_flops(n_rows, provenance) as f64).round() as u64
+}
+
+/// Returns [`Error::NonFinite`](crate::Error::NonFinite) for the first entry of `a`, in column
+/// major order, which is NaN or infinite.
+pub(crate) fn check_finite<S, T>(a: &ArrayBase<S, Ix2>) -> crate::Result<()>
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    for (col, column) in a.gencolumns().into_iter().enumerate() {
+        if let Some(row) = column.iter().position(|a_ij| !a_ij.modulus().is_finite()) {
+            Err(crate::Error::NonFinite { row, col })?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the optional `deadline` has passed. No deadline never passes.
+pub(crate) fn deadline_passed(deadline: Option<Instant>) -> bool {
+    match deadline {
+        Some(deadline) => Instant::now() >= deadline,
+        None => false,
+    }
+}
+
+/// Calls `observe` with the index and the diagonal entry of R of every column in `columns`, which
+/// have just been processed. Returns the status with which the computation stops if `observe`
+/// returns `false` for one of them, and `None` if it is to go on.
+pub(crate) fn observe_columns<T, F>(r: &Array2<T>, columns: Range<usize>, observe: &mut F) -> Option<crate::ComputeStatus>
+    where T: GramSchmidtScalar,
+          F: FnMut(usize, T::Real) -> bool,
+{
+    let n_cols = r.cols();
+    for j in columns {
+        if !observe(j, r[(j, j)].modulus()) {
+            return Some(if j + 1 < n_cols {
+                crate::ComputeStatus::InProgress { next_col: j + 1 }
+            } else {
+                crate::ComputeStatus::Done
+            });
+        }
+    }
+    None
+}
+
+/// Returns the Euclidean norm of the vector `v`, computed by blas `?nrm2` like the norms in the
+/// procedures if its elements are evenly spaced in memory.
+pub(crate) fn normalization<S, T>(v: &ArrayBase<S, Ix1>) -> T::Real
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    let stride = v.strides()[0];
+    if v.is_empty() || stride <= 0 {
+        return v.fold(T::Real::zero(), |acc, &x| acc + x.modulus() * x.modulus()).sqrt();
+    }
+    // The elements of `v` are the first of every `stride` elements of this slice.
+    let len = (v.len() - 1) * stride as usize + 1;
+    unsafe {
+        let v_slice = slice::from_raw_parts(v.as_ptr(), len);
+        T::nrm2(v.len() as i32, v_slice, stride as i32)
+    }
+}
+
+/// Returns the Frobenius norm of the matrix `a`.
+pub(crate) fn frobenius_norm<S, T>(a: &ArrayBase<S, Ix2>) -> T::Real
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    match a.as_slice_memory_order() {
+        Some(a_slice) => unsafe {
+            T::nrm2(a_slice.len() as i32, a_slice, 1)
+        },
+        None => a.fold(T::Real::zero(), |acc, &x| acc + x.modulus() * x.modulus()).sqrt(),
+    }
+}
+
+/// Returns the layout in which the matrix would be stored when transposed.
+pub(crate) fn transpose_layout(layout: blas::Layout) -> blas::Layout {
+    match layout {
+        blas::Layout::RowMajor => blas::Layout::ColumnMajor,
+        blas::Layout::ColumnMajor => blas::Layout::RowMajor,
+    }
+}
+
+/// Writes a header with the dimensions of the factorized matrix and its memory `layout`, followed
+/// by Q and R with their entries aligned in columns. The number of digits after the decimal point
+/// is the precision of the formatter, e.g. `{:.3}`, and defaults to 6.
+pub(crate) fn display_factorization<T>(
+    f: &mut fmt::Formatter,
+    name: &str,
+    layout: blas::Layout,
+    q: &Array2<T>,
+    r: &Array2<T>,
+) -> fmt::Result
+    where T: fmt::Display
+{
+    let layout = match layout {
+        blas::Layout::RowMajor => "row major",
+        blas::Layout::ColumnMajor => "column major",
+    };
+    write!(f, "{} factorization of a {}×{} matrix in {} layout", name, q.rows(), r.cols(), layout)?;
+    display_matrix(f, "Q", q)?;
+    display_matrix(f, "R", r)
+}
+
+fn display_matrix<T>(f: &mut fmt::Formatter, name: &str, a: &Array2<T>) -> fmt::Result
+    where T: fmt::Display
+{
+    let precision = f.precision().unwrap_or(6);
+    let entries: Vec<String> = a.iter().map(|a_ij| format!("{:.*}", precision, a_ij)).collect();
+    let width = entries.iter().map(|entry| entry.chars().count()).max().unwrap_or(0);
+
+    write!(f, "\n{} =", name)?;
+    for row in entries.chunks(a.cols().max(1)) {
+        writeln!(f)?;
+        for entry in row {
+            write!(f, "  {:>width$}", entry, width = width)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether `a` is a broadcast view, i.e. whether it has a zero stride along an axis
+/// of length greater than one.
+pub(crate) fn is_broadcast<S, T, D>(a: &ArrayBase<S, D>) -> bool
+    where S: Data<Elem=T>,
+          D: Dimension
+{
+    a.shape().iter().zip(a.strides()).any(|(&len, &stride)| len > 1 && stride == 0)
 }
 
 /// Returns slice and layout underlying an array `a`.
-pub(crate) fn get_layout<S, T, D>(a: &ArrayBase<S, D>) -> Option<cblas::Layout>
+pub(crate) fn get_layout<S, T, D>(a: &ArrayBase<S, D>) -> Option<blas::Layout>
     where S: Data<Elem=T>,
           D: Dimension
 {
-    if let Some(_) = a.as_slice() {
-        Some(cblas::Layout::RowMajor)
-    } else if let Some(_) = a.as_slice_memory_order() {
-        Some(cblas::Layout::ColumnMajor)
+    if a.as_slice().is_some() {
+        Some(blas::Layout::RowMajor)
+    } else if a.as_slice_memory_order().is_some() {
+        Some(blas::Layout::ColumnMajor)
     } else {
         None
     }
 }
 
 /// Returns slice and layout underlying an array `a`.
-pub(crate) fn as_slice_with_layout<S, T, D>(a: &ArrayBase<S, D>) -> Option<(&[T], cblas::Layout)>
+pub(crate) fn as_slice_with_layout<S, T, D>(a: &ArrayBase<S, D>) -> Option<(&[T], blas::Layout)>
     where S: Data<Elem=T>,
           D: Dimension
 {
     if let Some(a_slice) = a.as_slice() {
-        Some((a_slice, cblas::Layout::RowMajor))
+        Some((a_slice, blas::Layout::RowMajor))
     } else if let Some(a_slice) = a.as_slice_memory_order() {
-        Some((a_slice, cblas::Layout::ColumnMajor))
+        Some((a_slice, blas::Layout::ColumnMajor))
     } else {
         None
     }
 }
+
+/// Returns the slice underlying the upper triangular matrix `r` as an operand of a blas routine
+/// expecting `op(r)` in the memory layout `layout`, where `op` is the transposition `trans`.
+///
+/// If the layout of `r` differs from `layout`, its slice represents the lower triangular matrix
+/// `rᵀ` in `layout`. In that case the part is reported as `Lower` and the transposition is
+/// flipped, so that blas still operates on `op(r)`.
+pub(crate) fn as_triangular_operand<S>(
+    r: &ArrayBase<S, Ix2>,
+    layout: blas::Layout,
+    trans: blas::Transpose,
+) -> Option<(&[f64], blas::Part, blas::Transpose)>
+    where S: Data<Elem=f64>
+{
+    use blas::{Part, Transpose};
+
+    let (r_slice, r_layout) = as_slice_with_layout(r)?;
+    if r_layout == layout {
+        Some((r_slice, Part::Upper, trans))
+    } else {
+        let trans = match trans {
+            Transpose::None => Transpose::Ordinary,
+            _ => Transpose::None,
+        };
+        Some((r_slice, Part::Lower, trans))
+    }
+}
+
+/// Multiplies the columns of `q` by `weights` and divides the rows of `r` by them, recording the
+/// sign flips in `sign_flipped`. See
+/// [`GramSchmidt::rescale_columns`](crate::GramSchmidt::rescale_columns).
+pub(crate) fn rescale_columns<S, T>(
+    q: &mut Array2<T>,
+    r: &mut Array2<T>,
+    weights: &ArrayBase<S, Ix1>,
+    sign_flipped: &mut [bool],
+) -> crate::Result<()>
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    use crate::Error::*;
+
+    let n_cols = q.cols();
+    if weights.len() != n_cols {
+        Err(LengthMismatch { expected: n_cols, found: weights.len() })?;
+    }
+    if let Some(index) = weights.iter().position(|w| w.is_zero() || !w.modulus().is_finite()) {
+        Err(InvalidWeight { index })?;
+    }
+
+    scale_columns(q, r, weights, sign_flipped);
+    Ok(())
+}
+
+/// Multiplies the columns of `q` by the valid `weights` and divides the rows of `r` by them,
+/// recording in `sign_flipped` the columns whose weight is not real and positive.
+fn scale_columns<S, T>(
+    q: &mut Array2<T>,
+    r: &mut Array2<T>,
+    weights: &ArrayBase<S, Ix1>,
+    sign_flipped: &mut [bool],
+)
+    where S: Data<Elem=T>,
+          T: GramSchmidtScalar,
+{
+    for (((mut q_column, mut r_row), &w), sign_flipped) in q.gencolumns_mut().into_iter()
+        .zip(r.genrows_mut())
+        .zip(weights)
+        .zip(sign_flipped)
+    {
+        q_column *= w;
+        r_row /= w;
+        *sign_flipped |= w != T::from_real(w.modulus());
+    }
+}
+
+/// Moves the signs, or phases, of the nonzero diagonal entries of `r` into `q`. See
+/// [`GramSchmidt::force_positive_diagonal`](crate::GramSchmidt::force_positive_diagonal).
+pub(crate) fn force_positive_diagonal<T>(q: &mut Array2<T>, r: &mut Array2<T>, sign_flipped: &mut [bool])
+    where T: GramSchmidtScalar,
+{
+    let phases = r.diag().mapv(|r_ii| {
+        let modulus = r_ii.modulus();
+        if modulus.is_zero() {
+            T::one()
+        } else {
+            r_ii / T::from_real(modulus)
+        }
+    });
+    scale_columns(q, r, &phases, sign_flipped);
+}
+
+/// Zeroes the columns `start..` of both `q` and `r`. See
+/// [`GramSchmidt::clear_columns_from`](crate::GramSchmidt::clear_columns_from).
+pub(crate) fn clear_columns_from<T>(q: &mut Array2<T>, r: &mut Array2<T>, start: usize)
+    where T: Clone + Zero,
+{
+    let start = start.min(q.cols());
+    q.slice_mut(s![.., start..]).fill(T::zero());
+    r.slice_mut(s![.., start..]).fill(T::zero());
+}
+
+/// Checks that a matrix of shape `dim` is not wider than tall, since otherwise its columns cannot
+/// be orthonormal.
+pub(crate) fn check_tall((rows, cols): (usize, usize)) -> crate::Result<()> {
+    if rows < cols {
+        Err(crate::Error::Underdetermined { rows, cols })?;
+    }
+    Ok(())
+}
+
+/// Checks that a shape passed to `from_shape` has at least one row and one column, and is not
+/// wider than tall.
+pub(crate) fn check_shape((rows, cols): (usize, usize)) -> crate::Result<()> {
+    if rows == 0 || cols == 0 {
+        Err(crate::Error::InvalidShape { rows, cols })?;
+    }
+    check_tall((rows, cols))
+}
+
+/// Resizes `q` to `shape` and `r` to the corresponding square shape, returning their respective
+/// layouts. `r` is stored in the layout opposite to the one of `q` if `transpose_r` is set.
+///
+/// The elements are zeroed, and the allocations are reused if they are large enough. If `shape`
+/// is rejected, `q` and `r` are left unchanged.
+pub(crate) fn reset_factors<A, T>(
+    q: &mut Array2<A>,
+    r: &mut Array2<A>,
+    shape: T,
+    transpose_r: bool,
+) -> crate::Result<(blas::Layout, blas::Layout)>
+    where A: Clone + Zero,
+          T: ShapeBuilder<Dim = Ix2>,
+{
+    // The dimensions and the layout of a shape are only accessible via an array; one of zero
+    // sized elements does not allocate.
+    let shape = shape.into_shape();
+    let probe = Array2::from_elem(shape, ());
+    let memory_layout = match get_layout(&probe) {
+        Some(layout) => layout,
+        None => Err(crate::Error::NonContiguous)?,
+    };
+    check_shape(probe.dim())?;
+
+    let n_cols = probe.cols();
+    let r_layout = if transpose_r {
+        transpose_layout(memory_layout)
+    } else {
+        memory_layout
+    };
+    reuse_buffer(q, shape);
+    reuse_buffer(r, (n_cols, n_cols).set_f(r_layout == blas::Layout::ColumnMajor));
+    Ok((memory_layout, r_layout))
+}
+
+/// Replaces `a` by a zeroed array of the given shape, reusing its allocation.
+pub(crate) fn reuse_buffer<A, D>(a: &mut Array<A, D>, shape: ndarray::Shape<D>)
+    where A: Clone + Zero,
+          D: Dimension,
+{
+    let len = Array::from_elem(shape.clone(), ()).len();
+    let empty = Array::zeros(D::zeros(a.ndim()));
+    let mut elements = mem::replace(a, empty).into_raw_vec();
+    elements.clear();
+    elements.resize(len, A::zero());
+    // NOTE: This unwrap is safe, because the number of elements matches the shape.
+    *a = Array::from_shape_vec(shape, elements).unwrap();
+}
+
+/// Checks that `q` and `r` can make up a factorization, returning their respective layouts.
+pub(crate) fn parts_layouts<T>(q: &Array2<T>, r: &Array2<T>) -> crate::Result<(blas::Layout, blas::Layout)> {
+    use crate::Error::*;
+
+    check_tall(q.dim())?;
+    let n_cols = q.cols();
+    if r.dim() != (n_cols, n_cols) {
+        Err(ShapeMismatch { expected: (n_cols, n_cols), found: r.dim() })?;
+    }
+    match (get_layout(q), get_layout(r)) {
+        (Some(q_layout), Some(r_layout)) => Ok((q_layout, r_layout)),
+        _ => Err(NonContiguous),
+    }
+}
+
+/// Returns the sign of the determinant of the orthogonal matrix `q`, i.e. its determinant up to
+/// rounding errors, by an LU decomposition with partial pivoting.
+pub(crate) fn orthogonal_det_sign(q: &Array2<f64>) -> f64 {
+    let mut lu = q.clone();
+    let n = lu.rows();
+    let mut sign = 1.0;
+    for k in 0..n {
+        let pivot = (k..n).fold(k, |pivot, i| if lu[(i, k)].abs() > lu[(pivot, k)].abs() { i } else { pivot });
+        if pivot != k {
+            for j in 0..n {
+                lu.swap((k, j), (pivot, j));
+            }
+            sign = -sign;
+        }
+
+        let pivot_value = lu[(k, k)];
+        if pivot_value < 0.0 {
+            sign = -sign;
+        }
+        for i in (k + 1)..n {
+            let factor = lu[(i, k)] / pivot_value;
+            for j in (k + 1)..n {
+                lu[(i, j)] -= factor * lu[(k, j)];
+            }
+        }
+    }
+    sign
+}
+
+/// Returns the sign of the permutation `permutation`, `1.0` if it is even and `-1.0` if it is odd.
+pub(crate) fn permutation_sign(permutation: &[usize]) -> f64 {
+    // A cycle of length l is a product of l - 1 transpositions.
+    let mut visited = vec![false; permutation.len()];
+    let mut transpositions = 0;
+    for start in 0..permutation.len() {
+        let mut i = start;
+        while !visited[i] {
+            visited[i] = true;
+            i = permutation[i];
+            if i != start {
+                transpositions += 1;
+            }
+        }
+    }
+    if transpositions % 2 == 0 { 1.0 } else { -1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use num_complex::Complex;
+    use super::*;
+
+    #[test]
+    fn normalization_of_known_vectors() {
+        let a = arr2(&[[3.0, 1.0],
+                       [0.0, 2.0],
+                       [4.0, 2.0]]);
+        assert_eq!(normalization(&a.column(0)), 5.0);
+        assert_eq!(normalization(&a.row(2)), 20f64.sqrt());
+
+        let mut reversed = a.column(1);
+        reversed.invert_axis(Axis(0));
+        assert_eq!(normalization(&reversed), 3.0);
+        assert_eq!(normalization(&Array1::<f64>::zeros(0)), 0.0);
+
+        let z = arr1(&[Complex::new(3.0, 4.0), Complex::new(0.0, 12.0)]);
+        assert_eq!(normalization(&z), 13.0);
+    }
+}
@@ -0,0 +1,261 @@
+use ndarray::{
+    Data,
+    ShapeBuilder,
+    linalg::general_mat_mul,
+};
+use ndarray::prelude::*;
+
+use crate::{
+    Error,
+    GramSchmidt,
+    Result,
+    blas,
+    cholesky::cholesky_upper,
+    utils::{
+        as_slice_with_layout,
+        get_layout,
+    },
+};
+
+/// The largest relative deviation `|m_ij - m_ji|` tolerated for a symmetric metric, relative to
+/// the largest absolute entry of the metric.
+const SYMMETRY_TOLERANCE: f64 = 1e-12;
+
+/// A QR decomposition `A = Q·R` orthonormalizing the columns of A with respect to the inner
+/// product `⟨x, y⟩_M = xᵀMy` of a symmetric positive definite metric M, e.g. the mass matrix of
+/// a finite element discretization, so that `QᵀMQ = I`.
+///
+/// Instead of replacing every dot product and norm of the wrapped procedure by its weighted
+/// counterpart, the metric is factorized once as `M = LᵀL` by a Cholesky decomposition. Since
+/// `⟨x, y⟩_M = (Lx)ᵀ(Ly)`, the wrapped procedure factorizes `L·A = Q̃·R` with the plain inner
+/// product, and `Q = L⁻¹·Q̃` is obtained by a triangular solve. R is the same for both
+/// factorizations, and the stability of the wrapped procedure carries over.
+///
+/// # Example
+///
+/// ```
+/// extern crate openblas_src;
+///
+/// use gramschmidt::{Reorthogonalized, Weighted};
+/// use ndarray::{arr2, Array2};
+///
+/// # fn main() -> gramschmidt::Result<()> {
+///
+/// let a = arr2(&[[1.0, 0.0],
+///                [1.0, 1.0],
+///                [1.0, 2.0]]);
+/// let mass = arr2(&[[2.0, 1.0, 0.0],
+///                   [1.0, 4.0, 1.0],
+///                   [0.0, 1.0, 2.0]]);
+/// let mut weighted = Weighted::<Reorthogonalized>::from_matrix(&a, &mass)?;
+/// weighted.compute(&a)?;
+///
+/// let q = weighted.q();
+/// assert!(q.t().dot(&mass).dot(q).all_close(&Array2::eye(2), 1e-14));
+/// assert!(a.all_close(&q.dot(weighted.r()), 1e-14));
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Weighted<G: GramSchmidt<Scalar = f64>> {
+    inner: G,
+    cholesky: Array2<f64>,
+    transformed: Array2<f64>,
+    q: Array2<f64>,
+}
+
+impl<G: GramSchmidt<Scalar = f64>> Weighted<G> {
+    /// Wraps the procedure `inner`, which determines the shape and layout of the matrices to be
+    /// factorized, orthonormalizing with respect to `metric`.
+    ///
+    /// Returns [`Error::ShapeMismatch`] if `metric` is not a square matrix with as many rows as
+    /// the matrices to be factorized, [`Error::NotSymmetric`] if it is not symmetric up to a
+    /// relative deviation of `1e-12`, and [`Error::NotPositiveDefinite`] if its Cholesky
+    /// decomposition fails.
+    ///
+    /// [`Error::ShapeMismatch`]: enum.Error.html#variant.ShapeMismatch
+    /// [`Error::NotSymmetric`]: enum.Error.html#variant.NotSymmetric
+    /// [`Error::NotPositiveDefinite`]: enum.Error.html#variant.NotPositiveDefinite
+    pub fn new<S>(inner: G, metric: &ArrayBase<S, Ix2>) -> Result<Self>
+        where S: Data<Elem = f64>
+    {
+        let dim = inner.q().dim();
+        let n_rows = dim.0;
+        if metric.dim() != (n_rows, n_rows) {
+            Err(Error::ShapeMismatch { expected: (n_rows, n_rows), found: metric.dim() })?;
+        }
+
+        let scale = metric.fold(0.0, |acc: f64, m_ij| acc.max(m_ij.abs()));
+        let symmetric = metric.indexed_iter()
+            .filter(|&((i, j), _)| i < j)
+            .all(|((i, j), &m_ij)| (m_ij - metric[(j, i)]).abs() <= SYMMETRY_TOLERANCE * scale);
+        if !symmetric {
+            Err(Error::NotSymmetric)?;
+        }
+
+        // All matrices share the layout of Q, so that the triangular solve needs no transposition.
+        let column_major = get_layout(inner.q()) == Some(blas::Layout::ColumnMajor);
+        let mut cholesky = Array2::zeros((n_rows, n_rows).set_f(column_major));
+        cholesky_upper(metric, &mut cholesky, None)?;
+
+        Ok(Self {
+            inner,
+            cholesky,
+            transformed: Array2::zeros(dim.set_f(column_major)),
+            q: Array2::zeros(dim.set_f(column_major)),
+        })
+    }
+
+    /// Reserves the memory for a weighted QR decomposition of matrices with the shape and layout
+    /// of `a`. See [`GramSchmidt::from_matrix`] and [`new`].
+    ///
+    /// [`GramSchmidt::from_matrix`]: trait.GramSchmidt.html#method.from_matrix
+    /// [`new`]: #method.new
+    pub fn from_matrix<S, T>(a: &ArrayBase<S, Ix2>, metric: &ArrayBase<T, Ix2>) -> Result<Self>
+        where S: Data<Elem = f64>,
+              T: Data<Elem = f64>,
+    {
+        Self::new(G::from_matrix(a)?, metric)
+    }
+
+    /// Computes the weighted QR decomposition `A = Q·R` of the matrix `a`, such that `QᵀMQ = I`.
+    ///
+    /// Returns an error if the shape of `a` differs from the configured one, or if the wrapped
+    /// procedure fails.
+    pub fn compute<S>(&mut self, a: &ArrayBase<S, Ix2>) -> Result<()>
+        where S: Data<Elem = f64>
+    {
+        use blas::Layout::*;
+
+        if a.dim() != self.q.dim() {
+            Err(Error::ShapeMismatch { expected: self.q.dim(), found: a.dim() })?;
+        }
+
+        general_mat_mul(1.0, &self.cholesky, a, 0.0, &mut self.transformed);
+        self.inner.compute(&self.transformed)?;
+        self.q.assign(self.inner.q());
+
+        let (n_rows, n_cols) = self.q.dim();
+        // NOTE: This unwrap is safe, because the Cholesky factor is allocated contiguously.
+        let (l_slice, layout) = as_slice_with_layout(&self.cholesky).unwrap();
+        let leading_dim = match layout {
+            ColumnMajor => n_rows,
+            RowMajor => n_cols,
+        };
+        // NOTE: This unwrap is safe, because Q is allocated contiguously.
+        let q_slice = self.q.as_slice_memory_order_mut().unwrap();
+
+        // Solve L · Q = Q̃ for Q, overwriting the copy of Q̃.
+        unsafe {
+            blas::dtrsm(
+                layout,
+                blas::Side::Left,
+                blas::Part::Upper,
+                blas::Transpose::None,
+                blas::Diagonal::Generic,
+                n_rows as i32,
+                n_cols as i32,
+                1.0,
+                l_slice,
+                n_rows as i32,
+                q_slice,
+                leading_dim as i32,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Return a reference to the matrix q, whose columns are orthonormal with respect to the
+    /// metric.
+    pub fn q(&self) -> &Array2<f64> {
+        &self.q
+    }
+
+    /// Return a reference to the matrix r.
+    pub fn r(&self) -> &Array2<f64> {
+        self.inner.r()
+    }
+
+    /// Return a reference to the upper triangular Cholesky factor L of the metric, `M = LᵀL`.
+    pub fn cholesky(&self) -> &Array2<f64> {
+        &self.cholesky
+    }
+
+    /// Return a reference to the wrapped procedure, which holds the factorization of `L·A`.
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate openblas_src;
+
+    use ndarray::prelude::*;
+    use crate::{
+        Classical,
+        Modified,
+        Reorthogonalized,
+    };
+    use crate::utils::matrix;
+    use super::*;
+
+    fn check_m_orthonormal<G: GramSchmidt<Scalar = f64>>(a: &Array2<f64>, metric: &Array2<f64>) {
+        let mut weighted = Weighted::<G>::from_matrix(a, metric).unwrap();
+        weighted.compute(a).unwrap();
+        let q = weighted.q();
+        assert_eq!(get_layout(q), get_layout(a));
+        assert!(q.t().dot(metric).dot(q).all_close(&Array2::eye(a.cols()), 1e-13));
+        assert!(a.all_close(&q.dot(weighted.r()), 1e-13));
+    }
+
+    #[test]
+    fn diagonal_metric_gives_m_orthonormal_columns() {
+        let a = matrix(5, 3);
+        let mut f_a = Array2::zeros(a.dim().f());
+        f_a.assign(&a);
+        let mut metric = Array2::zeros((5, 5));
+        metric.diag_mut().assign(&arr1(&[1.0, 2.0, 0.5, 4.0, 3.0]));
+        for input in [a, f_a].iter() {
+            check_m_orthonormal::<Classical>(input, &metric);
+            check_m_orthonormal::<Modified>(input, &metric);
+            check_m_orthonormal::<Reorthogonalized>(input, &metric);
+        }
+    }
+
+    #[test]
+    fn identity_metric_agrees_with_plain_factorization() {
+        let a = matrix(5, 3);
+        let mut weighted = Weighted::<Modified>::from_matrix(&a, &Array2::eye(5)).unwrap();
+        weighted.compute(&a).unwrap();
+        let mut mgs = Modified::from_matrix(&a).unwrap();
+        mgs.compute(&a).unwrap();
+        assert!(weighted.q().all_close(mgs.q(), 1e-15));
+        assert!(weighted.r().all_close(mgs.r(), 1e-15));
+    }
+
+    #[test]
+    fn invalid_metrics_are_rejected() {
+        let a = matrix(5, 3);
+        match Weighted::<Modified>::from_matrix(&a, &Array2::eye(3)) {
+            Err(Error::ShapeMismatch { expected: (5, 5), found: (3, 3) }) => {},
+            other => panic!("expected a shape mismatch, got {:?}", other),
+        }
+
+        let mut asymmetric = Array2::eye(5);
+        asymmetric[(0, 4)] = 0.5;
+        match Weighted::<Modified>::from_matrix(&a, &asymmetric) {
+            Err(Error::NotSymmetric) => {},
+            other => panic!("expected a not symmetric matrix, got {:?}", other),
+        }
+
+        let mut indefinite = Array2::eye(5);
+        indefinite[(2, 2)] = -1.0;
+        match Weighted::<Modified>::from_matrix(&a, &indefinite) {
+            Err(Error::NotPositiveDefinite) => {},
+            other => panic!("expected a not positive definite matrix, got {:?}", other),
+        }
+    }
+}